@@ -0,0 +1,335 @@
+// immutable, arena backed copy of a MathTree that is Send + Sync: nodes live in one Vec
+// and refer to their operands by NodeId, variable names are shared with Arc.
+// convert back to a MathTree to simplify, see ArenaTree::to_tree
+
+use std::{collections::HashMap, rc::Rc, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    math_tree::{MathTree, TreeNodeRef},
+    number::Number,
+    FunctionToken, MathToken, MathTokenType, OperationToken,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NodeId(u32);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ArenaVal {
+    Constant(Number),
+    Variable(Arc<str>),
+    Operator(OperationToken),
+    Function(FunctionToken),
+}
+
+impl ArenaVal {
+    pub fn kind(&self) -> MathTokenType {
+        match self {
+            ArenaVal::Constant(_) => MathTokenType::Constant,
+            ArenaVal::Variable(_) => MathTokenType::Variable,
+            ArenaVal::Operator(_) => MathTokenType::Operator,
+            ArenaVal::Function(_) => MathTokenType::Function,
+        }
+    }
+}
+
+// operands by order of insertion, iterated by type like Operands
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArenaOperands {
+    nodes: Vec<(NodeId, MathTokenType)>,
+}
+
+impl ArenaOperands {
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn iter_order(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes.iter().map(|(id, _)| *id)
+    }
+
+    // operators then functions then variables then constants
+    pub fn iter(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.operators()
+            .chain(self.functions())
+            .chain(self.variables())
+            .chain(self.constants())
+    }
+
+    // constants then variables then functions then operators
+    pub fn iter_mul(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.constants()
+            .chain(self.variables())
+            .chain(self.functions())
+            .chain(self.operators())
+    }
+
+    pub fn constants(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.get_kind(MathTokenType::Constant)
+    }
+
+    pub fn variables(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.get_kind(MathTokenType::Variable)
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.get_kind(MathTokenType::Function)
+    }
+
+    pub fn operators(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.get_kind(MathTokenType::Operator)
+    }
+
+    fn get_kind(&self, kind: MathTokenType) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes
+            .iter()
+            .filter(move |(_, k)| *k == kind)
+            .map(|(id, _)| *id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArenaNode {
+    pub val: ArenaVal,
+    pub operands: ArenaOperands,
+}
+
+impl ArenaNode {
+    // same order as TreeNode::calculate_iter: constants first for orderless operators
+    pub fn calculate_iter(&self) -> Box<dyn Iterator<Item = NodeId> + '_> {
+        match self.val {
+            ArenaVal::Operator(op) if op.info().orderless => Box::new(self.operands.iter_mul()),
+            _ => Box::new(self.operands.iter_order()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArenaTree {
+    // operands come before the node using them, root is last
+    nodes: Vec<ArenaNode>,
+    root: NodeId,
+}
+
+impl PartialEq for ArenaTree {
+    fn eq(&self, other: &Self) -> bool {
+        self.node_eq(self.root, other, other.root)
+    }
+}
+
+impl ArenaTree {
+    pub fn from_tree(tree: &MathTree) -> Self {
+        Self::from_node(&tree.root)
+    }
+
+    pub fn from_node(node: &TreeNodeRef) -> Self {
+        let mut arena = Self {
+            nodes: Vec::new(),
+            root: NodeId(0),
+        };
+        let mut variables = HashMap::new();
+
+        arena.root = arena.push(node, &mut variables);
+        arena
+    }
+
+    // variable bounds found while simplifying are not kept
+    pub fn to_tree(&self) -> MathTree {
+        MathTree {
+            root: self.to_node(),
+            bounds: Default::default(),
+        }
+    }
+
+    pub fn to_node(&self) -> TreeNodeRef {
+        let mut variables = HashMap::new();
+        self.build(self.root, &mut variables)
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    pub fn node(&self, id: NodeId) -> &ArenaNode {
+        &self.nodes[id.0 as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn push(
+        &mut self,
+        node: &TreeNodeRef,
+        variables: &mut HashMap<Rc<String>, Arc<str>>,
+    ) -> NodeId {
+        let borrow = node.borrow();
+        let operands = ArenaOperands {
+            nodes: borrow
+                .operands()
+                .iter_order()
+                .map(|(_, child)| (self.push(child, variables), child.val().kind))
+                .collect(),
+        };
+
+        let val = node.val();
+        let val = match val.kind {
+            MathTokenType::Constant => ArenaVal::Constant(val.constant.unwrap()),
+            // avoid allocating same variable string twice
+            MathTokenType::Variable => ArenaVal::Variable(
+                variables
+                    .entry(val.variable.unwrap())
+                    .or_insert_with_key(|v| Arc::from(v.as_str()))
+                    .clone(),
+            ),
+            MathTokenType::Operator => ArenaVal::Operator(val.operation.unwrap()),
+            MathTokenType::Function => ArenaVal::Function(val.function.unwrap()),
+        };
+
+        self.nodes.push(ArenaNode { val, operands });
+        NodeId(self.nodes.len() as u32 - 1)
+    }
+
+    fn build(&self, id: NodeId, variables: &mut HashMap<Arc<str>, Rc<String>>) -> TreeNodeRef {
+        let node = self.node(id);
+        let token = match &node.val {
+            ArenaVal::Constant(c) => MathToken::constant(c.clone()),
+            ArenaVal::Variable(v) => MathToken::variable(
+                variables
+                    .entry(v.clone())
+                    .or_insert_with_key(|v| Rc::new(v.to_string()))
+                    .clone(),
+            ),
+            ArenaVal::Operator(op) => MathToken::operator(*op),
+            ArenaVal::Function(f) => MathToken::function(*f),
+        };
+
+        let operands = node
+            .operands
+            .iter_order()
+            .map(|child| self.build(child, variables))
+            .collect();
+        TreeNodeRef::new_vals(token, operands)
+    }
+
+    fn node_eq(&self, id: NodeId, other: &ArenaTree, other_id: NodeId) -> bool {
+        let (a, b) = (self.node(id), other.node(other_id));
+        a.val == b.val
+            && a.operands.len() == b.operands.len()
+            && a.calculate_iter()
+                .zip(b.calculate_iter())
+                .all(|(a, b)| self.node_eq(a, other, b))
+    }
+}
+
+impl From<&MathTree> for ArenaTree {
+    fn from(tree: &MathTree) -> Self {
+        Self::from_tree(tree)
+    }
+}
+
+impl From<&ArenaTree> for MathTree {
+    fn from(tree: &ArenaTree) -> Self {
+        tree.to_tree()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    use super::{ArenaTree, ArenaVal};
+    use crate::{
+        math_tree::{MathTree, TreeNodeRef},
+        stepper::Steps,
+    };
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn arena_round_trip() {
+        assert_send_sync::<ArenaTree>();
+
+        for txt in [
+            "x",
+            "2 * x + 1",
+            "(x + 1) * (x - 1) / 2",
+            "sin(x)^2 + log(x, 2) + sqrt(x)",
+            "x * y * 3 + x",
+        ] {
+            let tree = MathTree::parse(txt).unwrap();
+            let arena = ArenaTree::from_tree(&tree);
+            assert_eq!(arena.to_tree().root, tree.root, "{}", txt);
+            assert_eq!(ArenaTree::from_tree(&arena.to_tree()), arena);
+        }
+    }
+
+    #[test]
+    fn arena_operands_by_type() {
+        let arena = ArenaTree::from_tree(&MathTree::parse("x * (y + 1) * 2 * sin(x)").unwrap());
+        let root = arena.node(arena.root());
+        let vals = |ids: Vec<_>| {
+            ids.into_iter()
+                .map(|id| arena.node(id).val.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vals(root.operands.constants().collect()),
+            vec![ArenaVal::Constant(dec!(2).into())]
+        );
+        assert_eq!(
+            vals(root.operands.variables().collect()),
+            vec![ArenaVal::Variable("x".into())]
+        );
+        assert_eq!(
+            vals(root.calculate_iter().collect())[0],
+            ArenaVal::Constant(dec!(2).into())
+        );
+        assert_eq!(root.operands.iter_order().count(), 4);
+    }
+
+    #[test]
+    fn arena_across_threads() {
+        let answers = ["2 + 3 * 4", "2 * x * 3", "sqrt(16) * y", "ln(x) + 1"]
+            .map(|txt| ArenaTree::from_tree(&MathTree::parse(txt).unwrap()));
+
+        let simplified = std::thread::scope(|s| {
+            let handles = answers
+                .iter()
+                .map(|answer| {
+                    s.spawn(move || {
+                        let mut tree = answer.to_tree();
+                        tree.simplify(&mut Steps::new()).unwrap();
+                        ArenaTree::from_tree(&tree)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap().to_node())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(
+            simplified,
+            vec![
+                TreeNodeRef::constant(dec!(14)),
+                TreeNodeRef::parse("6 * x"),
+                TreeNodeRef::parse("4 * y"),
+                TreeNodeRef::parse("ln(x) + 1"),
+            ]
+        );
+    }
+}
@@ -1,11 +1,13 @@
 use std::ops::RangeBounds;
 
 use itertools::Itertools;
-use rust_decimal::{Decimal, MathematicalOps};
-use rust_decimal_macros::dec;
 
+use super::{
+    functions::{self, perform_function},
+    power,
+};
 use crate::{
-    bounds::Bound, factorization, math_tree::{MathTree, TreeNodeRef, VarBounds}, stepper::{Step, Steps}, MathToken, OperationToken
+    bounds::Bound, complex, constants::is_positive, factorization, math_tree::{MathTree, TreeNodeRef, VarBounds}, number::Number, stepper::{Step, Steps}, units, FunctionToken, MathToken, OperationToken
 };
 
 // the operands are checked against these scenarios as they usually result in a different behavior and explanation
@@ -18,18 +20,24 @@ pub enum OpDescription {
     // b == 1
     ByOne(TreeNodeRef),
     // a and b are constants
-    BothConstants(Decimal, Decimal),
+    BothConstants(Number, Number),
 }
 
 pub fn get_description(a: &TreeNodeRef, b: &TreeNodeRef, orderless: bool) -> Option<OpDescription> {
-    if let Some(c2) = b.val().constant {
-        if let Some(c1) = a.val().constant {
+    // 1 s is not the number 1, see units
+    let constant = |node: &TreeNodeRef| {
+        let val = node.val();
+        val.unit.is_none().then_some(val.constant).flatten()
+    };
+
+    if let Some(c2) = constant(b) {
+        if let Some(c1) = constant(a) {
             return Some(OpDescription::BothConstants(c1, c2));
         }
 
-        if c2 == Decimal::ZERO {
+        if c2.is_zero() {
             return Some(OpDescription::ByZero(a.clone()));
-        } else if c2 == Decimal::ONE {
+        } else if c2.is_one() {
             return Some(OpDescription::ByOne(a.clone()));
         }
     } else if a == b {
@@ -37,10 +45,10 @@ pub fn get_description(a: &TreeNodeRef, b: &TreeNodeRef, orderless: bool) -> Opt
     }
 
     if orderless {
-        if let Some(c1) = a.val().constant {
-            if c1 == Decimal::ZERO {
+        if let Some(c1) = constant(a) {
+            if c1.is_zero() {
                 return Some(OpDescription::ByZero(b.clone()));
-            } else if c1 == Decimal::ONE {
+            } else if c1.is_one() {
                 return Some(OpDescription::ByOne(b.clone()));
             }
         }
@@ -58,7 +66,12 @@ pub fn get_description(a: &TreeNodeRef, b: &TreeNodeRef, orderless: bool) -> Opt
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
 #[derive(Debug, PartialEq)]
 pub enum OperationError {
+    // an exact result past Number::max_bits
     Overflow,
+    // 2 kg + 3 s
+    DimensionMismatch,
+    // 1 / 2 (mod 4)
+    NotInvertible,
 }
 
 pub fn perform_op(
@@ -66,6 +79,10 @@ pub fn perform_op(
     node: &mut TreeNodeRef,
     steps: &mut Steps,
 ) -> Result<Option<TreeNodeRef>, OperationError> {
+    if let Some(f) = node.val().function {
+        return perform_function(f, node);
+    }
+
     let Some(op) = node.val().operation else {
         panic!("Not operation")
     };
@@ -91,10 +108,21 @@ pub fn perform_op(
         // println!("OP {:#?} {:#?} B {:#?}", op, a, b);
         std::mem::drop(borrow);
 
-        let desc = get_description(&a, &b, orderless);
-        // let step = Step::PerformOp(desc.clone());
+        // 5 m/s * 3 s = 15 m, (1 + i)(1 - i) = 2, only when a unit or i is involved
+        let special = match units::perform_op_units(op, &a, &b)? {
+            Some(res) => Some(res),
+            None => complex::perform_op_complex(op, &a, &b)?,
+        };
+        let res = match special {
+            Some(res) => Some(res),
+            None => {
+                let desc = get_description(&a, &b, orderless);
+                // let step = Step::PerformOp(desc.clone());
+                do_op(&a, &b, desc, bounds)?
+            }
+        };
 
-        if let Some(res) = do_op(&a, &b, desc, bounds)? {
+        if let Some(res) = res {
             // steps.step((&a, &b), &res, step);
             node.borrow_mut().operands_result(a_pos, b_pos, res);
         } else {
@@ -129,7 +157,7 @@ fn get_op(
             |a: &TreeNodeRef, _b, desc, _bounds| {
                 Ok(match desc {
                     Some(OpDescription::BothConstants(c1, c2)) => {
-                        Some(TreeNodeRef::constant(c1 + c2))
+                        Some(TreeNodeRef::constant((c1 + c2).bounded()?))
                     }
                     // x + x = 2x
                     Some(OpDescription::EqualOperand) => Some(a.multiply(TreeNodeRef::two())),
@@ -145,7 +173,7 @@ fn get_op(
             |a: &TreeNodeRef, b, desc, _bounds| {
                 Ok(match desc {
                     Some(OpDescription::BothConstants(c1, c2)) => {
-                        Some(TreeNodeRef::constant(c1 - c2))
+                        Some(TreeNodeRef::constant((c1 - c2).bounded()?))
                     }
                     // x - x = 0
                     Some(OpDescription::EqualOperand) => Some(TreeNodeRef::zero()),
@@ -156,12 +184,12 @@ fn get_op(
                     _ => {
                         // -(-x) = x
                         // 0-x = -x = -1 * x
-                        if MathToken::constant(Decimal::ZERO) == a.val() {
-                            return Ok(Some(b.multiply(TreeNodeRef::constant(dec!(-1)))));
+                        if MathToken::constant(0) == a.val() {
+                            return Ok(Some(b.multiply(TreeNodeRef::constant(-1))));
                         } else {
                             return Ok(Some(
                                 // y - x + x = y + x * -1 + x
-                                a.add(b.multiply(TreeNodeRef::constant(dec!(-1)))),
+                                a.add(b.multiply(TreeNodeRef::constant(-1))),
                             ));
                         }
 
@@ -174,7 +202,7 @@ fn get_op(
             |a: &TreeNodeRef, _b: &TreeNodeRef, desc, _bounds| {
                 Ok(match desc {
                     Some(OpDescription::BothConstants(c1, c2)) => {
-                        Some(TreeNodeRef::constant(c1 * c2))
+                        Some(TreeNodeRef::constant((c1 * c2).bounded()?))
                     }
                     // x * x = x^2
                     Some(OpDescription::EqualOperand) => Some(a.pow(TreeNodeRef::two())),
@@ -206,7 +234,7 @@ fn get_op(
                 }
                 Ok(match desc {
                     Some(OpDescription::BothConstants(c1, c2)) => {
-                        Some(TreeNodeRef::constant(c1 / c2))
+                        Some(TreeNodeRef::constant((c1 / c2).bounded()?))
                     }
                     // x / x = 1
                     Some(OpDescription::EqualOperand) => Some(TreeNodeRef::one()),
@@ -226,13 +254,27 @@ fn get_op(
             }
         }
         OperationToken::Pow => {
-            |_a: &TreeNodeRef, _b, desc, _bounds| {
+            |a: &TreeNodeRef, b: &TreeNodeRef, desc, _bounds| {
+                // root(x, n)^n = x
+                if a.val().operation == Some(OperationToken::Root) {
+                    let (base, _) = power::get_node_as_power(a.clone());
+                    let index = a.borrow().calculate_iter().nth(1).unwrap().1.clone();
+                    if &index == b {
+                        return Ok(Some(base));
+                    }
+                }
+
+                // e^ln(x) = x
+                if functions::is_e(a) {
+                    if let Some(x) = functions::inverse_of(b, FunctionToken::Ln) {
+                        return Ok(Some(x));
+                    }
+                }
+
                 Ok(match desc {
+                    // exact only: 2^-2 = 0.25, 4^0.5 = 2, but 2^0.5 stays
                     Some(OpDescription::BothConstants(c1, c2)) => {
-                        Some(TreeNodeRef::constant(match c1.checked_powd(c2) {
-                            Some(k) => k,
-                            None => return Err(OperationError::Overflow),
-                        }))
+                        c1.pow(&c2)?.map(TreeNodeRef::constant)
                     }
                     // x ^ x = x ^ x
                     // Some(OpDescription::EqualOperand)
@@ -251,8 +293,53 @@ fn get_op(
                 })
             }
         }
-        OperationToken::Root => todo!(),
-        OperationToken::LParent | OperationToken::RParent => unreachable!(),
+        OperationToken::Root => {
+            |a: &TreeNodeRef, b: &TreeNodeRef, desc, _bounds| {
+                Ok(match desc {
+                    Some(OpDescription::BothConstants(c1, c2)) => {
+                        let Some(n) = power::root_index(&c2) else {
+                            return Ok(None);
+                        };
+
+                        if n == 2 && c1.is_sign_negative() {
+                            // root(-4, 2) = root(4, 2) * i
+                            Some(TreeNodeRef::constant(-c1).root(b.clone()).multiply(TreeNodeRef::var("i")))
+                        } else if let Some(r) = c1.nth_root(n) {
+                            // root(16, 2) = 4
+                            Some(TreeNodeRef::constant(r))
+                        } else {
+                            // root(8, 2) = 2 * root(2, 2)
+                            power::extract_root_factor(&c1, n).map(|(factor, remaining)| {
+                                TreeNodeRef::constant(factor)
+                                    .multiply(TreeNodeRef::constant(remaining).root(b.clone()))
+                            })
+                        }
+                    }
+                    // root(x, 1) = x
+                    Some(OpDescription::ByOne(x)) => Some(x),
+                    _ => {
+                        // root(x^n, n) = x for odd n, |x| for even n
+                        let (base, exponent) = power::get_node_as_power(a.clone());
+                        if &exponent != b {
+                            return Ok(None);
+                        }
+
+                        match b.val().constant.as_ref().and_then(power::root_index) {
+                            // root(pi^2, 2) = pi
+                            Some(n) if n % 2 == 1 || is_positive(&base) => Some(base),
+                            Some(_) => Some(TreeNodeRef::new_vals(
+                                MathToken::function(FunctionToken::Abs),
+                                vec![base],
+                            )),
+                            None => None,
+                        }
+                    }
+                })
+            }
+        }
+        OperationToken::LParent | OperationToken::RParent | OperationToken::Comma => {
+            unreachable!()
+        }
     }
 }
 
@@ -273,7 +360,7 @@ pub fn perform_op_constant<
         OperationToken::Multiply => a * b,
         OperationToken::Divide => a / b,
         OperationToken::Pow => a.pow(b),
-        OperationToken::Root => todo!(),
+        OperationToken::Root => a.root(b),
         _ => unreachable!(),
     }
 }
@@ -299,24 +386,36 @@ impl TreeNodeRef {
         self.op(OperationToken::Divide, node)
     }
 
-    pub fn pow(&self, node: TreeNodeRef) -> TreeNodeRef {
-        self.op(OperationToken::Pow, node)
+    pub fn pow(&self, node: impl Into<TreeNodeRef>) -> TreeNodeRef {
+        self.op(OperationToken::Pow, node.into())
+    }
+
+    // nth root, node is n
+    pub fn root(&self, node: impl Into<TreeNodeRef>) -> TreeNodeRef {
+        self.op(OperationToken::Root, node.into())
     }
 }
 
 pub trait Pow {
     fn pow(&self, b: Self) -> Self;
+    // bth root
+    fn root(&self, b: Self) -> Self;
 }
 
 impl Pow for f64 {
     fn pow(&self, b: Self) -> Self {
         self.powf(b)
     }
-}
 
-impl Pow for Decimal {
-    fn pow(&self, b: Self) -> Self {
-        self.powd(b)
+    fn root(&self, b: Self) -> Self {
+        if b == 2.0 {
+            self.sqrt()
+        } else if *self < 0.0 && b % 2.0 == 1.0 {
+            // odd roots of negatives are real
+            -(-self).powf(1.0 / b)
+        } else {
+            self.powf(1.0 / b)
+        }
     }
 }
 
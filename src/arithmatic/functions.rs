@@ -0,0 +1,313 @@
+use std::f64::consts::LN_2;
+
+use itertools::Itertools;
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_traits::ToPrimitive;
+use crate::{
+    complex::Complex,
+    constants::is_positive,
+    math_tree::TreeNodeRef,
+    number::Number,
+    number_theory::{gcd, lcm},
+    FunctionToken, OperationToken,
+};
+
+use super::arithmatic::{OperationError, Pow};
+
+// evaluates a function node exactly, only when the result is exact (sin(0) = 0, ln(e) = 1),
+// anything else stays symbolic: sin(1), ln(x)
+pub fn perform_function(
+    f: FunctionToken,
+    node: &TreeNodeRef,
+) -> Result<Option<TreeNodeRef>, OperationError> {
+    let args = node
+        .borrow()
+        .calculate_iter()
+        .map(|(_, n)| n.clone())
+        .collect_vec();
+    let x = &args[0];
+    let c = x.val().constant;
+
+    Ok(match f {
+        FunctionToken::Sin | FunctionToken::Tan => {
+            // sin(0) = sin(pi) = 0
+            if c == Some(Number::zero()) || is_pi(x) {
+                Some(TreeNodeRef::zero())
+            } else {
+                None
+            }
+        }
+        FunctionToken::Cos => {
+            if c == Some(Number::zero()) {
+                Some(TreeNodeRef::one())
+            } else if is_pi(x) {
+                Some(TreeNodeRef::constant(-1))
+            } else {
+                None
+            }
+        }
+        FunctionToken::Ln => {
+            if c == Some(Number::one()) {
+                Some(TreeNodeRef::zero())
+            } else if is_e(x) {
+                Some(TreeNodeRef::one())
+            } else {
+                // ln(e^n) = n
+                match power_of_e(x) {
+                    Some(n) => Some(n),
+                    None => inverse_of(x, FunctionToken::Exp),
+                }
+            }
+        }
+        FunctionToken::Exp => {
+            if c == Some(Number::zero()) {
+                Some(TreeNodeRef::one())
+            } else if c == Some(Number::one()) {
+                Some(TreeNodeRef::new_val(crate::MathToken::variable(
+                    String::from("e").into(),
+                )))
+            } else {
+                // exp(ln(x)) = x
+                inverse_of(x, FunctionToken::Ln)
+            }
+        }
+        FunctionToken::Log => {
+            let base = &args[1];
+            if c == Some(Number::one()) {
+                Some(TreeNodeRef::zero())
+            } else if x == base {
+                Some(TreeNodeRef::one())
+            } else if let (Some(x), Some(base)) = (c, base.val().constant) {
+                exact_log(x, base).map(TreeNodeRef::constant)
+            } else {
+                None
+            }
+        }
+        FunctionToken::Abs => match c {
+            Some(c) => Some(TreeNodeRef::constant(c.abs())),
+            // abs(abs(x)) = abs(x), abs(pi) = pi
+            None if x.val().function == Some(FunctionToken::Abs) || is_positive(x) => {
+                Some(x.clone())
+            }
+            // abs(3 + 4i) = root(9 + 16, 2) = 5
+            None => Complex::from_node(x).map(|z| match z.norm().nth_root(2) {
+                Some(r) => TreeNodeRef::constant(r),
+                None => TreeNodeRef::constant(z.norm()).root(TreeNodeRef::two()),
+            }),
+        },
+        FunctionToken::Re => Complex::from_node(x).map(|z| TreeNodeRef::constant(z.re)),
+        FunctionToken::Im => Complex::from_node(x).map(|z| TreeNodeRef::constant(z.im)),
+        FunctionToken::Conj => Complex::from_node(x).map(|z| z.conj().to_node()),
+        // arg(-1) = pi, arg(1 + i) = 1/4 * pi, arg(3 + 4i) stays
+        FunctionToken::Arg => Complex::from_node(x).and_then(|z| z.exact_arg()),
+        // gcd(12, 18) = 6, only for whole numbers
+        FunctionToken::Gcd | FunctionToken::Lcm => match (c, args[1].val().constant) {
+            (Some(a), Some(b)) if a.is_integer() && b.is_integer() => {
+                let f = if f == FunctionToken::Gcd { gcd } else { lcm };
+                let res = Number::from(f(a.numer(), b.numer())).bounded()?;
+                Some(TreeNodeRef::constant(res))
+            }
+            _ => None,
+        },
+        // already equal to its argument, kept so it is written as the factorization
+        FunctionToken::Factorint => None,
+        // a function node built by hand, lowered to the root operator the parser builds
+        FunctionToken::Sqrt | FunctionToken::Root => Some(TreeNodeRef::call(f, args)),
+    })
+}
+
+// f(x) for bulk evaluation, see FastFunction
+pub fn perform_function_float(f: FunctionToken, args: &[f64]) -> f64 {
+    let x = args[0];
+    match f {
+        FunctionToken::Sin => x.sin(),
+        FunctionToken::Cos => x.cos(),
+        FunctionToken::Tan => x.tan(),
+        FunctionToken::Ln => x.ln(),
+        FunctionToken::Log => x.ln() / args[1].ln(),
+        FunctionToken::Exp => x.exp(),
+        FunctionToken::Abs => x.abs(),
+        FunctionToken::Re | FunctionToken::Conj => x,
+        FunctionToken::Im => 0.0,
+        // pi for negatives
+        FunctionToken::Arg => 0f64.atan2(x),
+        FunctionToken::Gcd | FunctionToken::Lcm => gcd_float(f, x, args[1]),
+        FunctionToken::Factorint => x,
+        FunctionToken::Sqrt => x.root(2.0),
+        FunctionToken::Root => x.root(args[1]),
+    }
+}
+
+// f(z) for complex bulk evaluation, see FastFunction::evaluate_complex
+pub fn perform_function_complex(f: FunctionToken, args: &[Complex64]) -> Complex64 {
+    let z = args[0];
+    match f {
+        FunctionToken::Sin => z.sin(),
+        FunctionToken::Cos => z.cos(),
+        FunctionToken::Tan => z.tan(),
+        FunctionToken::Ln => z.ln(),
+        FunctionToken::Log => z.ln() / args[1].ln(),
+        FunctionToken::Exp => z.exp(),
+        FunctionToken::Abs => Complex64::from(z.norm()),
+        FunctionToken::Re => Complex64::from(z.re),
+        FunctionToken::Im => Complex64::from(z.im),
+        FunctionToken::Conj => z.conj(),
+        FunctionToken::Arg => Complex64::from(z.arg()),
+        FunctionToken::Gcd | FunctionToken::Lcm if z.im == 0.0 && args[1].im == 0.0 => {
+            Complex64::from(gcd_float(f, z.re, args[1].re))
+        }
+        FunctionToken::Gcd | FunctionToken::Lcm => Complex64::new(f64::NAN, f64::NAN),
+        FunctionToken::Factorint => z,
+        FunctionToken::Sqrt => z.sqrt(),
+        FunctionToken::Root => z.root(args[1]),
+    }
+}
+
+// gcd and lcm of whole floats, NaN for anything else
+pub(crate) fn gcd_float(f: FunctionToken, a: f64, b: f64) -> f64 {
+    // past 2^53 floats are no longer exact integers
+    let whole = |x: f64| x.fract() == 0.0 && x.abs() <= 2f64.powi(53);
+    if !whole(a) || !whole(b) {
+        return f64::NAN;
+    }
+
+    let (a, b) = (a.abs() as u64, b.abs() as u64);
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    match f {
+        FunctionToken::Gcd => x as f64,
+        _ if x == 0 => 0.0,
+        _ => (a / x) as f64 * b as f64,
+    }
+}
+
+fn is_named(node: &TreeNodeRef, names: &[&str]) -> bool {
+    match node.val().variable {
+        Some(v) => names.contains(&v.as_str()),
+        None => false,
+    }
+}
+
+fn is_pi(node: &TreeNodeRef) -> bool {
+    is_named(node, &["pi", "π"])
+}
+
+pub(crate) fn is_e(node: &TreeNodeRef) -> bool {
+    is_named(node, &["e"])
+}
+
+// e^n => n
+fn power_of_e(node: &TreeNodeRef) -> Option<TreeNodeRef> {
+    if node.val().operation != Some(OperationToken::Pow) {
+        return None;
+    }
+
+    let (base, exponent) = node
+        .borrow()
+        .calculate_iter()
+        .map(|(_, n)| n.clone())
+        .collect_tuple()?;
+
+    is_e(&base).then_some(exponent)
+}
+
+// f(g(x)) = x when f is the inverse of g
+pub(crate) fn inverse_of(node: &TreeNodeRef, inverse: FunctionToken) -> Option<TreeNodeRef> {
+    if node.val().function != Some(inverse) {
+        return None;
+    }
+
+    let arg = node.borrow().calculate_iter().next()?.1.clone();
+    Some(arg)
+}
+
+// whole n such that base^n = x
+fn exact_log(x: Number, base: Number) -> Option<Number> {
+    if x <= Number::zero() || base <= Number::zero() || base.is_one() {
+        return None;
+    }
+
+    // the only whole candidate, the float estimate is far closer than 1/2 when the log is whole.
+    // checked exactly, pow refuses powers past max_bits: log(10^28, 1.0000001)
+    let n = (ln(&x) / ln(&base)).round();
+    if !n.is_finite() || n.abs() > i64::MAX as f64 {
+        return None;
+    }
+    let n = Number::from(n as i64);
+    (base.pow(&n).ok()?? == x).then_some(n)
+}
+
+// ln(x) past the range of f64: ln(2^200000)
+fn ln(x: &Number) -> f64 {
+    let ln_int = |i: &BigInt| {
+        let shift = i.bits().saturating_sub(f64::MANTISSA_DIGITS as u64);
+        (i >> shift).to_f64().unwrap_or(f64::NAN).ln() + shift as f64 * LN_2
+    };
+    ln_int(x.numer()) - ln_int(x.denom())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    use super::perform_function_float;
+    use crate::{
+        math_tree::{MathTree, TreeNodeRef, VarBounds},
+        simplify::simplify::tests::simplify_test,
+        stepper::Steps,
+        FunctionToken, MathToken,
+    };
+
+    #[test]
+    fn exact_functions() {
+        simplify_test("sin(0)", TreeNodeRef::zero());
+        simplify_test("cos(0)", TreeNodeRef::one());
+        simplify_test("cos(pi)", TreeNodeRef::constant(dec!(-1)));
+        simplify_test("tan(2 - 2)", TreeNodeRef::zero());
+        simplify_test("ln(e)", TreeNodeRef::one());
+        simplify_test("ln(1)", TreeNodeRef::zero());
+        simplify_test("exp(0)", TreeNodeRef::one());
+        simplify_test("log(1000)", TreeNodeRef::constant(dec!(3)));
+        simplify_test("log(8, 2)", TreeNodeRef::constant(dec!(3)));
+        simplify_test("log(0.25, 2)", TreeNodeRef::constant(dec!(-2)));
+        simplify_test("log(8, 0.5)", TreeNodeRef::constant(dec!(-3)));
+        simplify_test("log(2^1000, 2)", TreeNodeRef::constant(dec!(1000)));
+        simplify_test("abs(0 - 3)", TreeNodeRef::constant(dec!(3)));
+        simplify_test("1 + sin(0)", TreeNodeRef::one());
+        simplify_test("ln(e^x)", TreeNodeRef::parse("x"));
+        simplify_test("exp(ln(x))", TreeNodeRef::parse("x"));
+
+        // built as function nodes rather than parsed into root operators
+        let sqrt = TreeNodeRef::new_vals(
+            MathToken::function(FunctionToken::Sqrt),
+            vec![TreeNodeRef::constant(16)],
+        );
+        let mut tree = MathTree {
+            root: sqrt,
+            bounds: VarBounds::new(),
+        };
+        tree.simplify(&mut Steps::new()).unwrap();
+        assert_eq!(tree.root, TreeNodeRef::constant(4));
+        assert_eq!(perform_function_float(FunctionToken::Sqrt, &[16.0]), 4.0);
+        assert_eq!(perform_function_float(FunctionToken::Root, &[27.0, 3.0]), 3.0);
+    }
+
+    #[test]
+    fn symbolic_functions() {
+        // no exact value, stays as is
+        for expr in [
+            "sin(1)",
+            "ln(x)",
+            "log(7, 2)",
+            "log(10000000000000000000000000000, 1.0000001)",
+        ] {
+            let mut tree = MathTree::parse(expr).unwrap();
+            tree.simplify(&mut Steps::new()).unwrap();
+            assert_eq!(tree.root, TreeNodeRef::parse(expr));
+        }
+    }
+}
@@ -1,2 +1,3 @@
 pub mod arithmatic;
+pub mod functions;
 pub mod power;
\ No newline at end of file
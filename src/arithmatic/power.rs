@@ -1,6 +1,8 @@
 use itertools::Itertools;
+use crate::{math_tree::TreeNodeRef, number::Number, MathTokenType, OperationToken};
 
-use crate::{math_tree::TreeNodeRef, MathTokenType, OperationToken};
+// largest k checked when pulling perfect powers out of a root
+const MAX_ROOT_FACTOR: u64 = 1_000_000;
 
 pub fn get_node_as_power(node: TreeNodeRef) -> (TreeNodeRef, TreeNodeRef) {
     let val = node.val();
@@ -29,3 +31,98 @@ pub fn get_node_as_power(node: TreeNodeRef) -> (TreeNodeRef, TreeNodeRef) {
 
     (node, TreeNodeRef::one())
 }
+
+
+// n as a whole number index of a root: 2, 3...
+pub fn root_index(n: &Number) -> Option<u32> {
+    if *n < Number::two() {
+        return None;
+    }
+    n.to_u32()
+}
+
+// splits a whole radicand into coefficient and remaining radicand: root(8, 2) = 2 * root(2, 2)
+pub fn extract_root_factor(c: &Number, n: u32) -> Option<(Number, Number)> {
+    if c.is_zero() {
+        return None;
+    }
+
+    let mut remaining = c.abs().to_u128()?;
+    let mut factor: u128 = 1;
+    let mut k: u128 = 2;
+
+    while k <= MAX_ROOT_FACTOR as u128 {
+        let Some(power) = k.checked_pow(n) else {
+            break;
+        };
+        if power > remaining {
+            break;
+        }
+
+        if remaining % power == 0 {
+            remaining /= power;
+            factor *= k;
+        } else {
+            k += 1;
+        }
+    }
+
+    if factor == 1 {
+        return None;
+    }
+
+    let remaining = Number::from(remaining);
+    Some((
+        Number::from(factor),
+        if c.is_sign_negative() {
+            -remaining
+        } else {
+            remaining
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    use super::extract_root_factor;
+    use crate::{
+        math_tree::TreeNodeRef, number::Number, simplify::simplify::tests::simplify_test,
+    };
+
+    #[test]
+    fn exact_roots() {
+        let root = |c, n| Number::from(c).nth_root(n);
+        assert_eq!(root(dec!(16), 2), Some(dec!(4).into()));
+        assert_eq!(root(dec!(27), 3), Some(dec!(3).into()));
+        assert_eq!(root(dec!(-8), 3), Some(dec!(-2).into()));
+        assert_eq!(root(dec!(0.25), 2), Some(dec!(0.5).into()));
+        assert_eq!(root(dec!(-4), 2), None);
+        assert_eq!(root(dec!(2), 2), None);
+    }
+
+    #[test]
+    fn root_factors() {
+        let factor = |c, n| extract_root_factor(&Number::from(c), n);
+        assert_eq!(factor(dec!(8), 2), Some((dec!(2).into(), dec!(2).into())));
+        assert_eq!(factor(dec!(72), 2), Some((dec!(6).into(), dec!(2).into())));
+        assert_eq!(factor(dec!(-54), 3), Some((dec!(3).into(), dec!(-2).into())));
+        assert_eq!(factor(dec!(15), 2), None);
+    }
+
+    #[test]
+    fn simplify_roots() {
+        simplify_test("sqrt(16)", TreeNodeRef::constant(dec!(4)));
+        simplify_test("√9 + 1", TreeNodeRef::constant(dec!(4)));
+        simplify_test("root(27, 3)", TreeNodeRef::constant(dec!(3)));
+        simplify_test("root(0 - 8, 3)", TreeNodeRef::constant(dec!(-2)));
+        simplify_test("sqrt(8)", TreeNodeRef::parse("2 * sqrt(2)"));
+        simplify_test("sqrt(x)^2", TreeNodeRef::parse("x"));
+        simplify_test("root(x^3, 3)", TreeNodeRef::parse("x"));
+        simplify_test("sqrt(x^2)", TreeNodeRef::parse("abs(x)"));
+        // there is no 0th root, it stays as written
+        simplify_test("root(8, 0)", TreeNodeRef::parse("root(8, 0)"));
+    }
+}
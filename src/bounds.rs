@@ -1,11 +1,11 @@
 use serde::{Deserialize, Serialize};
 
-use crate::math_tree::TreeNodeRef;
+use crate::{interval::Interval, math_tree::TreeNodeRef};
 
 // constraints
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NumberType {
     Natural,    // N positive whole numbers
     Integer,    // Z whole numbers
@@ -36,6 +36,8 @@ pub struct Bound {
     pub kind: BoundType,
     pub function_id: Option<usize>,
     node: Option<TreeNodeRef>,
+    // Ordering: lo <= x <= hi
+    interval: Option<Interval>,
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
@@ -45,6 +47,7 @@ impl Bound {
             function_id: Some(function_id),
             kind: BoundType::BelongsToFunction,
             node: None,
+            interval: None,
         }
     }
 
@@ -53,9 +56,23 @@ impl Bound {
             kind: BoundType::NotEqual,
             function_id: None,
             node: Some(node),
+            interval: None,
         }
     }
 
+    pub fn within(interval: Interval) -> Self {
+        Self {
+            kind: BoundType::Ordering,
+            function_id: None,
+            node: None,
+            interval: Some(interval),
+        }
+    }
+
+    pub fn interval(&self) -> Option<Interval> {
+        self.interval
+    }
+
     // pub fn to_string(&self) -> String {
     //     match self.kind {
     //         BoundType::BelongsToNumberType => todo!(),
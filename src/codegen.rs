@@ -0,0 +1,634 @@
+// a standalone function computing the tree in f64 (float in GLSL), for evaluating outside of wasm.
+// subexpressions computed more than once are stored in temporaries t0, t1...
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use itertools::Itertools;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arithmatic::functions::gcd_float,
+    constants::{get_constant, is_constant},
+    function::function::Function,
+    interner::Interner,
+    math_tree::{MathTree, TreeNodeRef},
+    number::Number,
+    render::Emitter,
+    FunctionToken, MathTokenType, OperationToken,
+};
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Target {
+    Rust,
+    C,
+    JavaScript,
+    Glsl,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl MathTree {
+    // the variables, except constants such as pi, are the parameters in alphabetical order
+    pub fn to_code(&self, target: Target, name: &str) -> String {
+        self.root.to_code(target, name)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Function {
+    pub fn to_code(&self, target: Target, name: &str) -> String {
+        self.simplified.to_code(target, name)
+    }
+}
+
+impl TreeNodeRef {
+    pub fn to_code(&self, target: Target, name: &str) -> String {
+        let mut interner = Interner::new();
+        let root = interner.intern(self);
+        let temps = shared_temps(&root, interner.repeated());
+
+        let mut params = Vec::new();
+        scan_params(&root, &mut params);
+        params.sort();
+        let helpers = helpers(&root, target);
+
+        let writer = CodeWriter {
+            target,
+            temps: &temps,
+        };
+        let body = temps
+            .iter()
+            .enumerate()
+            .map(|(i, temp)| writer.assign(i, &writer.expr(temp, Some(i))))
+            .collect::<String>();
+
+        let result = writer.expr(&root, None);
+        match target {
+            Target::Rust => format!(
+                "{}fn {}({}) -> f64 {{\n{}    {}\n}}\n",
+                helpers,
+                name,
+                params.iter().map(|p| format!("{}: f64", p)).join(", "),
+                body,
+                result
+            ),
+            Target::C => format!(
+                "#include <math.h>\n\n{}double {}({}) {{\n{}    return {};\n}}\n",
+                helpers,
+                name,
+                params.iter().map(|p| format!("double {}", p)).join(", "),
+                body,
+                result
+            ),
+            Target::JavaScript => format!(
+                "{}function {}({}) {{\n{}    return {};\n}}\n",
+                helpers,
+                name,
+                params.join(", "),
+                body,
+                result
+            ),
+            Target::Glsl => format!(
+                "{}float {}({}) {{\n{}    return {};\n}}\n",
+                helpers,
+                name,
+                params.iter().map(|p| format!("float {}", p)).join(", "),
+                body,
+                result
+            ),
+        }
+    }
+}
+
+fn scan_params(node: &TreeNodeRef, params: &mut Vec<String>) {
+    if let Some(var) = node.val().variable {
+        if !is_constant(var.as_str()) && !params.contains(&var) {
+            params.push(var.to_string());
+        }
+    }
+
+    for (_, child) in node.borrow().operands().iter_order() {
+        scan_params(child, params);
+    }
+}
+
+// gcd and lcm calls that are not computed here, see CodeWriter::function
+fn scan_gcd(node: &TreeNodeRef, used: &mut Vec<FunctionToken>) {
+    if let Some(f @ (FunctionToken::Gcd | FunctionToken::Lcm)) = node.val().function {
+        if gcd_constant(node, f).is_none() && !used.contains(&f) {
+            used.push(f);
+        }
+    }
+
+    for (_, child) in node.borrow().operands().iter_order() {
+        scan_gcd(child, used);
+    }
+}
+
+// gcd(12, 18) is computed, None when an argument is not a constant
+fn gcd_constant(node: &TreeNodeRef, f: FunctionToken) -> Option<f64> {
+    let borrow = node.borrow();
+    let (a, b) = borrow
+        .calculate_iter()
+        .map(|(_, n)| n.val().constant)
+        .collect_tuple()?;
+    Some(gcd_float(f, a?.to_f64(), b?.to_f64()))
+}
+
+// definitions of the gcd and lcm the code calls, none of the targets has them
+fn helpers(root: &TreeNodeRef, target: Target) -> String {
+    let mut used = Vec::new();
+    scan_gcd(root, &mut used);
+    if used.is_empty() {
+        return String::new();
+    }
+
+    let (gcd, lcm) = match target {
+        Target::Rust => (RUST_GCD, RUST_LCM),
+        Target::C => (C_GCD, C_LCM),
+        Target::JavaScript => (JS_GCD, JS_LCM),
+        Target::Glsl => (GLSL_GCD, GLSL_LCM),
+    };
+    // lcm is written with gcd
+    if used.contains(&FunctionToken::Lcm) {
+        format!("{}\n{}\n", gcd, lcm)
+    } else {
+        format!("{}\n", gcd)
+    }
+}
+
+// NaN for anything but whole numbers, like gcd_float
+const RUST_GCD: &str = "fn gcd(a: f64, b: f64) -> f64 {
+    if a.fract() != 0.0 || b.fract() != 0.0 {
+        return f64::NAN;
+    }
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0.0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+";
+
+const RUST_LCM: &str = "fn lcm(a: f64, b: f64) -> f64 {
+    let g = gcd(a, b);
+    if g == 0.0 { 0.0 } else { (a / g * b).abs() }
+}
+";
+
+const C_GCD: &str = "double gcd(double a, double b) {
+    if (a != trunc(a) || b != trunc(b)) {
+        return NAN;
+    }
+    a = fabs(a);
+    b = fabs(b);
+    while (b != 0.0) {
+        double t = fmod(a, b);
+        a = b;
+        b = t;
+    }
+    return a;
+}
+";
+
+const C_LCM: &str = "double lcm(double a, double b) {
+    double g = gcd(a, b);
+    return g == 0.0 ? 0.0 : fabs(a / g * b);
+}
+";
+
+const JS_GCD: &str = "function gcd(a, b) {
+    if (!Number.isInteger(a) || !Number.isInteger(b)) {
+        return NaN;
+    }
+    a = Math.abs(a);
+    b = Math.abs(b);
+    while (b !== 0) {
+        [a, b] = [b, a % b];
+    }
+    return a;
+}
+";
+
+const JS_LCM: &str = "function lcm(a, b) {
+    const g = gcd(a, b);
+    return g === 0 ? 0 : Math.abs(a / g * b);
+}
+";
+
+const GLSL_GCD: &str = "float gcd(float a, float b) {
+    if (a != floor(a) || b != floor(b)) {
+        return 0.0 / 0.0;
+    }
+    a = abs(a);
+    b = abs(b);
+    while (b != 0.0) {
+        float t = mod(a, b);
+        a = b;
+        b = t;
+    }
+    return a;
+}
+";
+
+const GLSL_LCM: &str = "float lcm(float a, float b) {
+    float g = gcd(a, b);
+    return g == 0.0 ? 0.0 : abs(a / g * b);
+}
+";
+
+// repeated subtrees worth a temporary: the ones still used twice once the others are inlined,
+// x + 1 is only used by the temporary for (x + 1)^2 in (x + 1)^2 + sin((x + 1)^2)
+fn shared_temps(root: &TreeNodeRef, mut temps: Vec<TreeNodeRef>) -> Vec<TreeNodeRef> {
+    loop {
+        let mut uses = vec![0; temps.len()];
+        count_uses(root, &temps, None, &mut uses);
+        for (i, temp) in temps.iter().enumerate() {
+            count_uses(temp, &temps, Some(i), &mut uses);
+        }
+
+        let before = temps.len();
+        let mut uses = uses.into_iter();
+        temps.retain(|_| uses.next().unwrap() > 1);
+        if temps.len() == before {
+            return temps;
+        }
+    }
+}
+
+fn count_uses(node: &TreeNodeRef, temps: &[TreeNodeRef], skip: Option<usize>, uses: &mut [usize]) {
+    if let Some(i) = temp_index(node, temps, skip) {
+        uses[i] += 1;
+        return;
+    }
+
+    for (_, child) in node.borrow().operands().iter_order() {
+        count_uses(child, temps, None, uses);
+    }
+}
+
+// interned trees share equal subtrees, so the same pointer is the same subexpression
+fn temp_index(node: &TreeNodeRef, temps: &[TreeNodeRef], skip: Option<usize>) -> Option<usize> {
+    temps
+        .iter()
+        .position(|temp| temp.ptr_eq(node))
+        .filter(|i| Some(*i) != skip)
+}
+
+const ATOM: i8 = i8::MAX;
+// a negative literal, parenthesized wherever it is an operand
+const UNARY: i8 = 0;
+
+struct CodeWriter<'a> {
+    target: Target,
+    temps: &'a [TreeNodeRef],
+}
+
+impl Emitter for CodeWriter<'_> {
+    type Output = String;
+
+    fn write(&self, node: &TreeNodeRef) -> String {
+        self.expr(node, None)
+    }
+
+    fn precedence(&self, node: &TreeNodeRef) -> i8 {
+        if temp_index(node, self.temps, None).is_some() {
+            return ATOM;
+        }
+
+        let val = node.val();
+        match val.operation {
+            Some(
+                op @ (OperationToken::Add
+                | OperationToken::Subtract
+                | OperationToken::Multiply
+                | OperationToken::Divide),
+            ) => op.info().precedence,
+            _ if val.constant.as_ref().is_some_and(|c| c.is_sign_negative()) => UNARY,
+            // functions, pow and sqrt are calls
+            _ => ATOM,
+        }
+    }
+
+    fn group(&self, res: String) -> String {
+        format!("({})", res)
+    }
+}
+
+impl CodeWriter<'_> {
+    fn assign(&self, i: usize, expr: &str) -> String {
+        match self.target {
+            Target::Rust => format!("    let t{} = {};\n", i, expr),
+            Target::C => format!("    const double t{} = {};\n", i, expr),
+            Target::JavaScript => format!("    const t{} = {};\n", i, expr),
+            Target::Glsl => format!("    float t{} = {};\n", i, expr),
+        }
+    }
+
+    // skip is the temporary being defined
+    fn expr(&self, node: &TreeNodeRef, skip: Option<usize>) -> String {
+        if let Some(i) = temp_index(node, self.temps, skip) {
+            return format!("t{}", i);
+        }
+
+        let val = node.val();
+        match val.kind {
+            MathTokenType::Constant => literal(val.constant.unwrap().to_f64()),
+            MathTokenType::Variable => {
+                let var = val.variable.unwrap();
+                match get_constant(var.as_str()) {
+                    Some(c) => self.constant(&var, c.value),
+                    None => var.to_string(),
+                }
+            }
+            MathTokenType::Function => self.function(node, val.function.unwrap()),
+            MathTokenType::Operator => self.operator(node, val.operation.unwrap()),
+        }
+    }
+
+    fn arg(&self, node: &TreeNodeRef) -> String {
+        self.expr(node, None)
+    }
+
+    fn constant(&self, name: &str, value: Option<Decimal>) -> String {
+        match (self.target, name) {
+            (Target::Rust, "pi" | "π") => String::from("std::f64::consts::PI"),
+            (Target::Rust, "e") => String::from("std::f64::consts::E"),
+            (Target::JavaScript, "pi" | "π") => String::from("Math.PI"),
+            (Target::JavaScript, "e") => String::from("Math.E"),
+            // M_PI is not standard C, GLSL has no constants
+            _ => literal(value.and_then(|v| v.to_f64()).unwrap_or(f64::NAN)),
+        }
+    }
+
+    fn operator(&self, node: &TreeNodeRef, op: OperationToken) -> String {
+        let borrow = node.borrow();
+        let childs = borrow.display_iter().map(|(_, n)| n.clone()).collect_vec();
+
+        match op {
+            OperationToken::Add
+            | OperationToken::Subtract
+            | OperationToken::Multiply
+            | OperationToken::Divide => {
+                let precedence = op.info().precedence;
+                let right = matches!(op, OperationToken::Subtract | OperationToken::Divide);
+                childs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, child)| self.operand(child, precedence, right && i > 0))
+                    .join(&format!(" {} ", op.to_char()))
+            }
+            OperationToken::Pow => self.call("pow", &childs[0], &[&childs[1]]),
+            OperationToken::Root => self.root(&childs[0], &childs[1]),
+            OperationToken::LParent | OperationToken::RParent | OperationToken::Comma => {
+                unreachable!()
+            }
+        }
+    }
+
+    fn function(&self, node: &TreeNodeRef, f: FunctionToken) -> String {
+        let borrow = node.borrow();
+        let args = borrow.calculate_iter().map(|(_, n)| n).collect_vec();
+
+        match f {
+            FunctionToken::Sin => self.call("sin", args[0], &[]),
+            FunctionToken::Cos => self.call("cos", args[0], &[]),
+            FunctionToken::Tan => self.call("tan", args[0], &[]),
+            FunctionToken::Exp => self.call("exp", args[0], &[]),
+            FunctionToken::Abs => self.call("abs", args[0], &[]),
+            // real code, the value is its own real part and conjugate
+            FunctionToken::Re | FunctionToken::Conj => format!("({})", self.arg(args[0])),
+            FunctionToken::Im => literal(0.0),
+            FunctionToken::Arg => match self.target {
+                Target::Rust => format!("0.0f64.atan2({})", self.arg(args[0])),
+                Target::C => format!("atan2(0.0, {})", self.arg(args[0])),
+                Target::JavaScript => format!("Math.atan2(0, {})", self.arg(args[0])),
+                Target::Glsl => format!("atan(0.0, {})", self.arg(args[0])),
+            },
+            FunctionToken::Ln => self.call("ln", args[0], &[]),
+            FunctionToken::Log => match (self.target, args[1].val().constant) {
+                (Target::Glsl, _) => {
+                    format!("(log({}) / log({}))", self.arg(args[0]), self.arg(args[1]))
+                }
+                (_, Some(c)) if c == Number::ten() => self.call("log10", args[0], &[]),
+                (Target::Rust, _) => self.call("log", args[0], &[args[1]]),
+                _ => format!(
+                    "({} / {})",
+                    self.call("ln", args[0], &[]),
+                    self.call("ln", args[1], &[])
+                ),
+            },
+            // constant operands are computed, the rest call the helpers written before the function
+            FunctionToken::Gcd | FunctionToken::Lcm => match gcd_constant(node, f) {
+                Some(value) if value.is_nan() => self.nan(),
+                Some(value) => literal(value),
+                None => format!("{}({}, {})", f.name(), self.arg(args[0]), self.arg(args[1])),
+            },
+            // the factorization is only how it is written, its value is the argument itself
+            FunctionToken::Factorint => format!("({})", self.arg(args[0])),
+            FunctionToken::Sqrt => self.root(args[0], &TreeNodeRef::two()),
+            FunctionToken::Root => self.root(args[0], args[1]),
+        }
+    }
+
+    fn root(&self, radicand: &TreeNodeRef, index: &TreeNodeRef) -> String {
+        match index.val().constant {
+            Some(c) if c == Number::two() => self.call("sqrt", radicand, &[]),
+            // no cbrt in GLSL
+            Some(c) if c == Number::from(3) && self.target != Target::Glsl => {
+                self.call("cbrt", radicand, &[])
+            }
+            _ => {
+                let exp = format!("1.0 / {}", self.operand(index, 2, true));
+                self.call_code("pow", radicand, &[exp])
+            }
+        }
+    }
+
+    fn nan(&self) -> String {
+        String::from(match self.target {
+            Target::Rust => "f64::NAN",
+            Target::C => "NAN",
+            Target::JavaScript => "NaN",
+            Target::Glsl => "(0.0 / 0.0)",
+        })
+    }
+
+    // f is the name of the f64 method in Rust
+    fn call(&self, f: &str, first: &TreeNodeRef, rest: &[&TreeNodeRef]) -> String {
+        let rest = rest.iter().map(|arg| self.arg(arg)).collect_vec();
+        self.call_code(f, first, &rest)
+    }
+
+    fn call_code(&self, f: &str, first: &TreeNodeRef, rest: &[String]) -> String {
+        let name = match (self.target, f) {
+            (Target::Rust, "pow") => "powf",
+            (Target::Rust, _) => f,
+            (_, "ln") => "log",
+            (Target::C, "abs") => "fabs",
+            (Target::Glsl, "log10") => unreachable!(),
+            _ => f,
+        };
+
+        match self.target {
+            Target::Rust => {
+                let receiver = self.operand(first, ATOM, false);
+                format!("{}.{}({})", receiver, name, rest.join(", "))
+            }
+            _ => {
+                let args = std::iter::once(self.arg(first))
+                    .chain(rest.iter().cloned())
+                    .join(", ");
+                match self.target {
+                    Target::JavaScript => format!("Math.{}({})", name, args),
+                    _ => format!("{}({})", name, args),
+                }
+            }
+        }
+    }
+}
+
+// always a float literal: 2.0, 0.5
+fn literal(c: f64) -> String {
+    format!("{:?}", c)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::Target;
+    use crate::{
+        function::function::Function,
+        math_tree::{MathTree, TreeNodeRef},
+        FunctionToken, MathToken,
+    };
+
+    fn code(txt: &str, target: Target) -> String {
+        MathTree::parse(txt).unwrap().to_code(target, "f")
+    }
+
+    #[test]
+    fn codegen_targets() {
+        assert_eq!(
+            code("x^2", Target::Rust),
+            "fn f(x: f64) -> f64 {\n    x.powf(2.0)\n}\n"
+        );
+        assert_eq!(
+            code("x^2", Target::C),
+            "#include <math.h>\n\ndouble f(double x) {\n    return pow(x, 2.0);\n}\n"
+        );
+        assert_eq!(
+            code("x^2", Target::JavaScript),
+            "function f(x) {\n    return Math.pow(x, 2.0);\n}\n"
+        );
+        assert_eq!(
+            code("x^2", Target::Glsl),
+            "float f(float x) {\n    return pow(x, 2.0);\n}\n"
+        );
+        assert_eq!(
+            code("2 * pi * r + y / (x - 1)", Target::Rust),
+            "fn f(r: f64, x: f64, y: f64) -> f64 {\n    2.0 * std::f64::consts::PI * r + y / (x - 1.0)\n}\n"
+        );
+    }
+
+    #[test]
+    fn codegen_expressions() {
+        let body = |txt: &str, target: Target| {
+            let code = code(txt, target);
+            code.lines().rev().nth(1).unwrap().trim().to_string()
+        };
+
+        assert_eq!(
+            body("(x + 1)^0.5 - (-2)", Target::Rust),
+            "(x + 1.0).powf(0.5) - (-2.0)"
+        );
+        assert_eq!(body("x - (y - 1)", Target::C), "return x - (y - 1.0);");
+        assert_eq!(
+            body("sqrt(x) + root(x, 3)", Target::Rust),
+            "x.sqrt() + x.cbrt()"
+        );
+        // a sqrt function node built by hand is written like the parsed root operator
+        let sqrt = TreeNodeRef::new_vals(
+            MathToken::function(FunctionToken::Sqrt),
+            vec![TreeNodeRef::var("x")],
+        );
+        assert_eq!(
+            sqrt.to_code(Target::C, "f").lines().nth(3).unwrap(),
+            "    return sqrt(x);"
+        );
+        assert_eq!(
+            body("root(x, 3) * root(x, 5)", Target::Glsl),
+            "return pow(x, 1.0 / 3.0) * pow(x, 1.0 / 5.0);"
+        );
+        assert_eq!(
+            body("ln(x) + log(x) + log(x, 2)", Target::Rust),
+            "x.ln() + x.log10() + x.log(2.0)"
+        );
+        assert_eq!(
+            body("log(x, 2) * abs(e)", Target::C),
+            "return (log(x) / log(2.0)) * fabs(2.718281828459045);"
+        );
+        assert_eq!(
+            body("sin(x) / cos(pi)", Target::JavaScript),
+            "return Math.sin(x) / Math.cos(Math.PI);"
+        );
+        assert_eq!(body("log(x)", Target::Glsl), "return (log(x) / log(10.0));");
+    }
+
+    #[test]
+    fn codegen_common_subexpressions() {
+        assert_eq!(
+            code("(x + 1)^2 + sin((x + 1)^2)", Target::Rust),
+            "fn f(x: f64) -> f64 {\n    let t0 = (x + 1.0).powf(2.0);\n    t0 + t0.sin()\n}\n"
+        );
+        assert_eq!(
+            code(
+                "sin(x * y) * cos(x * y) + (x + 1) / (x + 1)^2",
+                Target::JavaScript
+            ),
+            [
+                "function f(x, y) {",
+                "    const t0 = x * y;",
+                "    const t1 = x + 1.0;",
+                "    return Math.sin(t0) * Math.cos(t0) + t1 / Math.pow(t1, 2.0);",
+                "}",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn codegen_gcd() {
+        let body = |txt: &str, target: Target| {
+            let code = code(txt, target);
+            code.lines().rev().nth(1).unwrap().trim().to_string()
+        };
+
+        assert_eq!(body("gcd(12, 18) + x", Target::Rust), "6.0 + x");
+        assert_eq!(body("gcd(1.5, 3) + x", Target::C), "return NAN + x;");
+        assert_eq!(body("factorint(x)", Target::JavaScript), "return (x);");
+
+        let rust = code("lcm(x, 4)", Target::Rust);
+        assert!(rust.starts_with("fn gcd(a: f64, b: f64) -> f64 {\n"));
+        assert!(rust.contains("\nfn lcm(a: f64, b: f64) -> f64 {\n"));
+        assert!(rust.ends_with("fn f(x: f64) -> f64 {\n    lcm(x, 4.0)\n}\n"));
+
+        let c = code("gcd(x, y)", Target::C);
+        assert!(c.starts_with("#include <math.h>\n\ndouble gcd(double a, double b) {\n"));
+        assert!(!c.contains("lcm"));
+        assert!(c.ends_with("double f(double x, double y) {\n    return gcd(x, y);\n}\n"));
+    }
+
+    #[test]
+    fn codegen_function() {
+        let f = Function::from(MathTree::parse("2 * 3 * x").unwrap()).unwrap();
+        assert_eq!(
+            f.to_code(Target::Glsl, "g"),
+            "float g(float x) {\n    return 6.0 * x;\n}\n"
+        );
+    }
+}
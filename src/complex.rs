@@ -0,0 +1,349 @@
+// exact complex constants a + bi with rational parts, i is the imaginary unit symbol (see constants).
+// written into the tree as 3 + 2 * i, so they print and simplify like any other expression
+
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_integer::Integer;
+use num_traits::{Signed, ToPrimitive};
+
+use crate::{
+    arithmatic::arithmatic::{OperationError, Pow},
+    math_tree::TreeNodeRef,
+    number::Number,
+    FunctionToken, OperationToken,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Complex {
+    pub re: Number,
+    pub im: Number,
+}
+
+impl Complex {
+    pub fn new(re: Number, im: Number) -> Self {
+        Self { re, im }
+    }
+
+    pub fn real(re: Number) -> Self {
+        Self::new(re, Number::zero())
+    }
+
+    pub fn i() -> Self {
+        Self::new(Number::zero(), Number::one())
+    }
+
+    pub fn is_real(&self) -> bool {
+        self.im.is_zero()
+    }
+
+    // 2i, but not 0 or 1 + 2i
+    pub fn is_imaginary(&self) -> bool {
+        self.re.is_zero() && !self.im.is_zero()
+    }
+
+    // 3, i, 2i, 3 + 2i, (1 + i)(1 - i), None for anything with a variable
+    pub fn from_node(node: &TreeNodeRef) -> Option<Complex> {
+        let val = node.val();
+        if let Some(c) = val.constant {
+            return Some(Self::real(c));
+        }
+        if is_imaginary_unit(node) {
+            return Some(Self::i());
+        }
+
+        let operands = node
+            .borrow()
+            .calculate_iter()
+            .map(|(_, n)| Self::from_node(n))
+            .collect::<Option<Vec<_>>>()?;
+        let mut operands = operands.into_iter();
+        let first = operands.next()?;
+        match val.operation? {
+            OperationToken::Add => Some(operands.fold(first, |a, b| a.add(&b))),
+            OperationToken::Subtract => Some(operands.fold(first, |a, b| a.sub(&b))),
+            OperationToken::Multiply => Some(operands.fold(first, |a, b| a.mul(&b))),
+            _ => None,
+        }
+    }
+
+    // 3 + 2 * i, i, 3
+    pub fn to_node(&self) -> TreeNodeRef {
+        let imaginary = if self.im.is_one() {
+            TreeNodeRef::var("i")
+        } else {
+            TreeNodeRef::constant(self.im.clone()).multiply(TreeNodeRef::var("i"))
+        };
+
+        match (self.re.is_zero(), self.im.is_zero()) {
+            (_, true) => TreeNodeRef::constant(self.re.clone()),
+            (true, false) => imaginary,
+            (false, false) => TreeNodeRef::constant(self.re.clone()).add(imaginary),
+        }
+    }
+
+    pub fn add(&self, other: &Complex) -> Complex {
+        Self::new(&self.re + &other.re, &self.im + &other.im)
+    }
+
+    pub fn sub(&self, other: &Complex) -> Complex {
+        Self::new(&self.re - &other.re, &self.im - &other.im)
+    }
+
+    pub fn mul(&self, other: &Complex) -> Complex {
+        Self::new(
+            &(&self.re * &other.re) - &(&self.im * &other.im),
+            &(&self.re * &other.im) + &(&self.im * &other.re),
+        )
+    }
+
+    // None for a zero divisor
+    pub fn div(&self, other: &Complex) -> Option<Complex> {
+        let norm = other.norm();
+        let numerator = self.mul(&other.conj());
+        Some(Self::new(
+            numerator.re.checked_div(&norm)?,
+            numerator.im.checked_div(&norm)?,
+        ))
+    }
+
+    pub fn conj(&self) -> Complex {
+        Self::new(self.re.clone(), -&self.im)
+    }
+
+    // |z|^2 = re^2 + im^2
+    pub fn norm(&self) -> Number {
+        &(&self.re * &self.re) + &(&self.im * &self.im)
+    }
+
+    // whole exponents: i^2 = -1, (1 + i)^-1 = 1/2 - 1/2i, None for 0^-1
+    pub fn powi(&self, exp: &Number) -> Result<Option<Complex>, OperationError> {
+        if !exp.is_integer() {
+            return Ok(None);
+        }
+
+        let (n, negative) = if self.norm().is_one() && (self.is_real() || self.is_imaginary()) {
+            // ±1 and ±i repeat every 4 powers: i^-1 = i^3
+            (exp.numer().mod_floor(&BigInt::from(4)), false)
+        } else {
+            let bits = self.re.bits().max(self.im.bits()) + 1;
+            if exp.numer().abs() * bits > BigInt::from(Number::max_bits()) {
+                return Err(OperationError::Overflow);
+            }
+            (exp.numer().abs(), exp.is_sign_negative())
+        };
+
+        let base = if negative {
+            match Self::real(Number::one()).div(self) {
+                Some(base) => base,
+                None => return Ok(None),
+            }
+        } else {
+            self.clone()
+        };
+
+        let mut n = n.to_u64().ok_or(OperationError::Overflow)?;
+        let mut square = base;
+        let mut res = Self::real(Number::one());
+        while n > 0 {
+            if n % 2 == 1 {
+                res = res.mul(&square);
+            }
+            square = square.mul(&square);
+            n /= 2;
+        }
+        Ok(Some(res))
+    }
+
+    // both parts within Number::max_bits
+    pub fn bounded(self) -> Result<Complex, OperationError> {
+        Ok(Self::new(self.re.bounded()?, self.im.bounded()?))
+    }
+
+    // k * pi / 4 when it is exact: arg(i) = 1/2 * pi, arg(-1 - i) = -3/4 * pi
+    pub fn exact_arg(&self) -> Option<TreeNodeRef> {
+        let (re, im) = (&self.re, &self.im);
+        let k = if im.is_zero() {
+            match re {
+                re if re.is_zero() => return None,
+                re if re.is_sign_negative() => 4,
+                _ => 0,
+            }
+        } else if re.is_zero() {
+            2
+        } else if re.abs() == im.abs() {
+            if re.is_sign_negative() {
+                3
+            } else {
+                1
+            }
+        } else {
+            return None;
+        };
+        let k = if im.is_sign_negative() { -k } else { k };
+
+        let pi = TreeNodeRef::var("pi");
+        Some(match Number::new(k, 4) {
+            k if k.is_zero() => TreeNodeRef::zero(),
+            k if k.is_one() => pi,
+            k => TreeNodeRef::constant(k).multiply(pi),
+        })
+    }
+
+    pub fn to_f64(&self) -> Complex64 {
+        Complex64::new(self.re.to_f64(), self.im.to_f64())
+    }
+}
+
+pub fn is_imaginary_unit(node: &TreeNodeRef) -> bool {
+    node.val().variable.is_some_and(|v| v.as_str() == "i")
+}
+
+// a op b for complex constants: (1 + i)(1 - i) = 2, i^2 = -1, i / 2 = 1/2i.
+// None when either is not a constant or both are real, those are left to get_op
+pub fn perform_op_complex(
+    op: OperationToken,
+    a: &TreeNodeRef,
+    b: &TreeNodeRef,
+) -> Result<Option<TreeNodeRef>, OperationError> {
+    let (Some(x), Some(y)) = (Complex::from_node(a), Complex::from_node(b)) else {
+        return Ok(None);
+    };
+    if x.is_real() && y.is_real() {
+        return Ok(None);
+    }
+
+    let res = match op {
+        // 3 + 2i is already as simple as it gets
+        OperationToken::Add | OperationToken::Subtract
+            if (x.is_real() && y.is_imaginary()) || (x.is_imaginary() && y.is_real()) =>
+        {
+            return Ok(None)
+        }
+        OperationToken::Add => x.add(&y),
+        OperationToken::Subtract => x.sub(&y),
+        // so is 2i
+        OperationToken::Multiply
+            if (x.is_real() && y == Complex::i()) || (x == Complex::i() && y.is_real()) =>
+        {
+            return Ok(None)
+        }
+        OperationToken::Multiply => x.mul(&y),
+        OperationToken::Divide => match x.div(&y) {
+            Some(res) => res,
+            None => return Ok(None),
+        },
+        OperationToken::Pow if y.is_real() => match x.powi(&y.re)? {
+            Some(res) => res,
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+
+    Ok(Some(res.bounded()?.to_node()))
+}
+
+impl TreeNodeRef {
+    // z as |z| * e^(arg(z) * i), simplifies to 2 * e^(1/2 * pi * i) for 2i
+    pub fn to_polar(&self) -> TreeNodeRef {
+        let r = TreeNodeRef::call(FunctionToken::Abs, vec![self.clone()]);
+        let theta = TreeNodeRef::call(FunctionToken::Arg, vec![self.clone()]);
+        r.multiply(TreeNodeRef::var("e").pow(theta.multiply(TreeNodeRef::var("i"))))
+    }
+
+    // r * (cos(theta) + sin(theta) * i), simplifies to -2 for r = 2, theta = pi
+    pub fn from_polar(r: TreeNodeRef, theta: TreeNodeRef) -> TreeNodeRef {
+        let cos = TreeNodeRef::call(FunctionToken::Cos, vec![theta.clone()]);
+        let sin = TreeNodeRef::call(FunctionToken::Sin, vec![theta]);
+        r.multiply(cos.add(sin.multiply(TreeNodeRef::var("i"))))
+    }
+}
+
+impl Pow for Complex64 {
+    fn pow(&self, b: Self) -> Self {
+        self.powc(b)
+    }
+
+    // principal root: sqrt(-4) = 2i
+    fn root(&self, b: Self) -> Self {
+        if b == Complex64::from(2.0) {
+            self.sqrt()
+        } else {
+            self.powc(b.inv())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_complex::Complex64;
+    use pretty_assertions::assert_eq;
+
+    use super::Complex;
+    use crate::{
+        function::{
+            fast_function::{FastFunction, VariableVal},
+            function::Function,
+        },
+        math_tree::{MathTree, TreeNodeRef},
+        number::Number,
+        simplify::simplify::tests::{simplify_test, simplify_test_latex},
+        stepper::Steps,
+    };
+
+    fn complex(re: i32, im: i32) -> TreeNodeRef {
+        Complex::new(Number::from(re), Number::from(im)).to_node()
+    }
+
+    #[test]
+    fn complex_arithmetic() {
+        simplify_test("i^2", TreeNodeRef::constant(-1));
+        simplify_test("i^3", complex(0, -1));
+        simplify_test("i^4", TreeNodeRef::one());
+        simplify_test("i * i", TreeNodeRef::constant(-1));
+        simplify_test("(1 + i) * (1 - i)", TreeNodeRef::constant(2));
+        simplify_test("(3 + 2i) + (1 - 5i)", complex(4, -3));
+        simplify_test(
+            "(1 + 2i) / (3 - 4i)",
+            Complex::new(Number::new(-1, 5), Number::new(2, 5)).to_node(),
+        );
+        simplify_test("sqrt(-4)", complex(0, 2));
+        simplify_test_latex("2 + 3i", "2+3i");
+    }
+
+    #[test]
+    fn complex_functions() {
+        simplify_test("Re(3 + 4i)", TreeNodeRef::constant(3));
+        simplify_test("Im(3 + 4i)", TreeNodeRef::constant(4));
+        simplify_test("conj(3 + 4i)", complex(3, -4));
+        simplify_test("abs(3 + 4i)", TreeNodeRef::constant(5));
+        simplify_test("arg(-1)", TreeNodeRef::var("pi"));
+        simplify_test(
+            "arg(2i)",
+            TreeNodeRef::constant(Number::new(1, 2)).multiply(TreeNodeRef::var("pi")),
+        );
+
+        let mut tree = MathTree::parse("1").unwrap();
+        tree.root = TreeNodeRef::from_polar(TreeNodeRef::two(), TreeNodeRef::var("pi"));
+        tree.simplify(&mut Steps::new()).unwrap();
+        assert_eq!(tree.root, TreeNodeRef::constant(-2));
+    }
+
+    #[test]
+    fn complex_evaluation() {
+        let mut fx =
+            FastFunction::from(&Function::from(MathTree::parse("sqrt(x) + i").unwrap()).unwrap())
+                .unwrap();
+
+        assert_eq!(
+            fx.evaluate_complex(vec![VariableVal::new("x".to_string(), -4.0)]),
+            Ok(Some(Complex64::new(0.0, 3.0)))
+        );
+        assert_eq!(
+            Complex::new(Number::from(3), Number::from(4))
+                .to_f64()
+                .to_polar()
+                .0,
+            5.0
+        );
+    }
+}
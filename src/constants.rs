@@ -1,12 +1,139 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::RwLock};
 
 use rust_decimal::Decimal;
 
+use crate::{bounds::NumberType, math_tree::TreeNodeRef};
+
+// a named number kept as a symbol in the tree: 2π, not 6.283...
+// only approximated by numeric evaluation, see MathTree::approximate and FastFunction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constant {
+    // None when it has no real value: i
+    pub value: Option<Decimal>,
+    pub number_type: NumberType,
+    pub positive: bool,
+}
+
+impl Constant {
+    pub fn new(value: Decimal, number_type: NumberType) -> Self {
+        Self {
+            value: Some(value),
+            number_type,
+            positive: value.is_sign_positive() && !value.is_zero(),
+        }
+    }
+
+    // i^2 = -1
+    pub fn imaginary_unit() -> Self {
+        Self {
+            value: None,
+            number_type: NumberType::Imaginary,
+            positive: false,
+        }
+    }
+}
 
 lazy_static::lazy_static! {
-    pub static ref CONSTANTS_MAP: HashMap<&'static str, Decimal> = HashMap::from([
-        ("pi", Decimal::PI),
-        ("π", Decimal::PI),
-        ("e", Decimal::E),
-    ]);
-}
\ No newline at end of file
+    static ref CONSTANTS: RwLock<HashMap<String, Constant>> = RwLock::new(HashMap::from([
+        (String::from("pi"), Constant::new(Decimal::PI, NumberType::Irrational)),
+        (String::from("π"), Constant::new(Decimal::PI, NumberType::Irrational)),
+        (String::from("e"), Constant::new(Decimal::E, NumberType::Irrational)),
+        (String::from("i"), Constant::imaginary_unit()),
+    ]));
+}
+
+// adds or replaces a constant: g, c, φ
+pub fn register_constant(name: &str, constant: Constant) {
+    CONSTANTS
+        .write()
+        .unwrap()
+        .insert(name.to_string(), constant);
+}
+
+// removes a registered or built in constant, None when there was none
+pub fn unregister_constant(name: &str) -> Option<Constant> {
+    CONSTANTS.write().unwrap().remove(name)
+}
+
+pub fn get_constant(name: &str) -> Option<Constant> {
+    CONSTANTS.read().unwrap().get(name).cloned()
+}
+
+pub fn is_constant(name: &str) -> bool {
+    CONSTANTS.read().unwrap().contains_key(name)
+}
+
+pub fn constant_names() -> Vec<String> {
+    CONSTANTS.read().unwrap().keys().cloned().collect()
+}
+
+// pi, but not x
+pub fn is_constant_node(node: &TreeNodeRef) -> bool {
+    node.val().variable.is_some_and(|v| is_constant(&v))
+}
+
+// 2, pi, but not x or -1
+pub fn is_positive(node: &TreeNodeRef) -> bool {
+    let val = node.val();
+    match (val.constant, val.variable) {
+        (Some(c), _) => !c.is_zero() && !c.is_sign_negative(),
+        (_, Some(v)) => get_constant(&v).is_some_and(|c| c.positive),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    use super::{is_constant, register_constant, unregister_constant, Constant};
+    use crate::{
+        bounds::NumberType,
+        function::function::Function,
+        math_tree::{MathTree, TreeNodeRef},
+        simplify::simplify::tests::{simplify_test, simplify_test_latex},
+        stepper::Steps,
+        MathToken,
+    };
+
+    #[test]
+    fn symbolic_constants() {
+        simplify_test("e^0", TreeNodeRef::one());
+        simplify_test("e^ln(x)", TreeNodeRef::parse("x"));
+        simplify_test("abs(pi)", TreeNodeRef::parse("pi"));
+        simplify_test("sqrt(pi^2)", TreeNodeRef::parse("pi"));
+        // never expanded to 6.283...
+        simplify_test_latex("2 * pi", "2\\pi");
+
+        // only the variable is substituted
+        let mut f = Function::from(MathTree::parse("x * pi").unwrap()).unwrap();
+        let res = f.evaluate(TreeNodeRef::constant(2)).unwrap().unwrap();
+        let pi = MathToken::variable(String::from("pi").into());
+        assert!(MathTree::find_node(&res, &pi).is_some());
+
+        let mut tree = MathTree::parse("2e").unwrap().approximate();
+        tree.simplify(&mut Steps::new()).unwrap();
+        assert_eq!(tree.root, TreeNodeRef::constant(dec!(2) * Decimal::E));
+    }
+
+    #[test]
+    fn registered_constants() {
+        // registered for the whole process, a name no other test uses
+        register_constant("φ", Constant::new(dec!(1.618), NumberType::Irrational));
+
+        let mut f = Function::from(MathTree::parse("φ * t").unwrap()).unwrap();
+        let res = f.evaluate(TreeNodeRef::constant(2)).unwrap().unwrap();
+        let phi = MathToken::variable(String::from("φ").into());
+        assert!(MathTree::find_node(&res, &phi).is_some());
+
+        let mut tree = MathTree::parse("2φ").unwrap().approximate();
+        tree.simplify(&mut Steps::new()).unwrap();
+        assert_eq!(tree.root, TreeNodeRef::constant(dec!(3.236)));
+
+        assert!(unregister_constant("φ").is_some());
+        assert!(!is_constant("φ"));
+        assert_eq!(unregister_constant("φ"), None);
+    }
+}
@@ -0,0 +1,285 @@
+// plain infix text that parses back to the same tree: parse(tree.to_string()) == tree.
+// parentheses are only written where the parser would otherwise read another tree.
+// exact fractions are written as a division, 1 / 3, which parses back as one.
+// factorint(360) is the exception, written out as its factorization 2^3·3^2·5
+
+use std::fmt;
+
+use crate::{
+    math_tree::{MathTree, TreeNodeRef},
+    number::Number,
+    number_theory::{factorint, write_factors},
+    render::Emitter,
+    FunctionToken, MathTokenType, OperationToken,
+};
+
+impl fmt::Display for MathTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root.fmt(f)
+    }
+}
+
+impl fmt::Display for TreeNodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&write(self))
+    }
+}
+
+// node on its own, never parenthesized
+fn write(node: &TreeNodeRef) -> String {
+    let val = node.val();
+    match val.kind {
+        MathTokenType::Constant => match val.unit {
+            // 5 m/s
+            Some(unit) => format!("{} {}", constant(&val.constant.unwrap()), unit),
+            None => constant(&val.constant.unwrap()),
+        },
+        MathTokenType::Variable => val.variable.unwrap().to_string(),
+        MathTokenType::Function => function(node, val.function.unwrap()),
+        MathTokenType::Operator => operator(node, val.operation.unwrap()),
+    }
+}
+
+fn constant(c: &Number) -> String {
+    if c.terminates() {
+        c.to_string()
+    } else {
+        format!("{} / {}", c.numer(), c.denom())
+    }
+}
+
+// the shared precedence handling, parentheses only where the parser needs them
+struct Infix;
+
+impl Emitter for Infix {
+    type Output = String;
+
+    fn write(&self, node: &TreeNodeRef) -> String {
+        write(node)
+    }
+
+    fn precedence(&self, node: &TreeNodeRef) -> i8 {
+        let val = node.val();
+        match val.operation {
+            // written as sqrt(x) or root(x, n)
+            Some(op) if op != OperationToken::Root => op.info().precedence,
+            None if val.constant.as_ref().is_some_and(|c| !c.terminates()) => {
+                OperationToken::Divide.info().precedence
+            }
+            // (5 m)^2, (2^3·3^2·5)^2
+            None if val.unit.is_some() || factorint(node).is_some() => {
+                OperationToken::Multiply.info().precedence
+            }
+            _ => i8::MAX,
+        }
+    }
+
+    fn group(&self, res: String) -> String {
+        format!("({})", res)
+    }
+
+    // a minus is only read as unary at the start or after ( and ,
+    fn leads_with_minus(&self, _: &TreeNodeRef, res: &String) -> bool {
+        res.starts_with('-')
+    }
+}
+
+fn operator(node: &TreeNodeRef, op: OperationToken) -> String {
+    let borrow = node.borrow();
+    let childs = borrow
+        .display_iter()
+        .map(|(_, n)| n.clone())
+        .collect::<Vec<_>>();
+    let precedence = op.info().precedence;
+
+    match op {
+        // left associative: the first operand is never parenthesized for an equal precedence
+        OperationToken::Add | OperationToken::Subtract | OperationToken::Divide => {
+            let mut res = Infix.leading(&childs[0], precedence);
+            for child in &childs[1..] {
+                res.push_str(&format!(" {} ", op.to_char()));
+                res.push_str(&Infix.operand(child, precedence, true));
+            }
+            res
+        }
+        OperationToken::Multiply => {
+            // -x is read as -1 * x, but -2 as a constant
+            let negated = childs.len() > 1
+                && childs[0].val().constant == Some(Number::neg_one())
+                && childs[1].val().kind != MathTokenType::Constant;
+
+            let factors = if negated { &childs[1..] } else { &childs[..] };
+            let res = factors
+                .iter()
+                .enumerate()
+                .map(|(i, factor)| match i {
+                    0 if !negated => Infix.leading(factor, precedence),
+                    _ => Infix.operand(factor, precedence, true),
+                })
+                .collect::<Vec<_>>()
+                .join(" * ");
+
+            if negated {
+                format!("-{}", res)
+            } else {
+                res
+            }
+        }
+        OperationToken::Pow => {
+            // (x + 1)^2, (-2)^2, (x^2)^3, (1 / 3)^2
+            let base = Infix.operand(&childs[0], i8::MAX, false);
+            format!("{}^{}", base, Infix.operand(&childs[1], precedence, true))
+        }
+        OperationToken::Root => {
+            if childs[1].val().constant == Some(Number::two()) {
+                format!("sqrt({})", write(&childs[0]))
+            } else {
+                format!("root({}, {})", write(&childs[0]), write(&childs[1]))
+            }
+        }
+        OperationToken::LParent | OperationToken::RParent | OperationToken::Comma => {
+            unreachable!()
+        }
+    }
+}
+
+fn function(node: &TreeNodeRef, f: FunctionToken) -> String {
+    if let Some(factors) = factorint(node) {
+        return write_factors(&factors, "·", |p, k| format!("{}^{}", p, k));
+    }
+
+    let borrow = node.borrow();
+    let mut args = borrow.calculate_iter().map(|(_, n)| n).collect::<Vec<_>>();
+
+    // log(x) is base 10
+    if f.default_operand().is_some() && args.last().unwrap().val().constant == f.default_operand() {
+        args.pop();
+    }
+
+    let args = args.into_iter().map(write).collect::<Vec<_>>().join(", ");
+    format!("{}({})", f.name(), args)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    use crate::{
+        math_tree::{MathTree, TreeNodeRef},
+        FunctionToken, MathToken, OperationToken,
+    };
+
+    #[test]
+    fn display() {
+        for txt in [
+            "x",
+            "2 * x + 1",
+            "x - (y + 1)",
+            "x - y + 1",
+            "(x - 1) / (x + 1)",
+            "x / (2 * y)",
+            "2 * x / y",
+            "3 * x^2 + sin(x)^2",
+            "(x + 1)^(1 / 2)",
+            "(x^2)^3",
+            "x^(-1)",
+            "(-2)^2",
+            "-x^2",
+            "-2 * x",
+            "sqrt(x) + root(x, 3)",
+            "log(x) + log(x, 2)",
+            "abs(x - 1) * exp(x)",
+        ] {
+            assert_eq!(MathTree::parse(txt).unwrap().to_string(), txt);
+        }
+
+        // redundant parentheses are dropped
+        assert_eq!(
+            MathTree::parse("((x)) + (2 * (y))").unwrap().to_string(),
+            "x + 2 * y"
+        );
+        assert_eq!(MathTree::parse("2x").unwrap().to_string(), "2 * x");
+    }
+
+    #[test]
+    fn display_negatives() {
+        let x = TreeNodeRef::var("x");
+
+        assert_eq!(TreeNodeRef::constant(dec!(-2)).to_string(), "-2");
+        assert_eq!((&x * -1).to_string(), "-x");
+        assert_eq!((&x * -2).to_string(), "-2 * x");
+        assert_eq!((&x + -2).to_string(), "x + (-2)");
+        assert_eq!((&x - -2).to_string(), "x - (-2)");
+        let negated = &x * -1;
+        assert_eq!((TreeNodeRef::two() * negated).to_string(), "2 * (-1) * x");
+        assert_eq!((TreeNodeRef::zero() - &x).to_string(), "0 - x");
+        assert_eq!(x.pow(-1).to_string(), "x^(-1)");
+    }
+
+    // small deterministic generator, the same trees every run
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, n: u64) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) % n
+        }
+
+        fn tree(&mut self, depth: u32) -> TreeNodeRef {
+            if depth == 0 || self.next(4) == 0 {
+                return match self.next(3) {
+                    0 => TreeNodeRef::constant(Decimal::new(self.next(21) as i64 - 10, 0)),
+                    1 => TreeNodeRef::constant(Decimal::new(self.next(41) as i64 - 20, 1)),
+                    _ => TreeNodeRef::var(["x", "y", "pi"][self.next(3) as usize]),
+                };
+            }
+
+            let ops = [
+                OperationToken::Add,
+                OperationToken::Subtract,
+                OperationToken::Multiply,
+                OperationToken::Divide,
+                OperationToken::Pow,
+                OperationToken::Root,
+            ];
+            let functions = [
+                FunctionToken::Sin,
+                FunctionToken::Ln,
+                FunctionToken::Abs,
+                FunctionToken::Log,
+            ];
+
+            match self.next(4) {
+                0 => {
+                    let f = functions[self.next(functions.len() as u64) as usize];
+                    let args = (0..f.info().arity).map(|_| self.tree(depth - 1)).collect();
+                    TreeNodeRef::new_vals(MathToken::function(f), args)
+                }
+                _ => {
+                    let op = ops[self.next(ops.len() as u64) as usize];
+                    let arity = match op {
+                        OperationToken::Add | OperationToken::Multiply => 2 + self.next(2),
+                        _ => 2,
+                    };
+                    let args = (0..arity).map(|_| self.tree(depth - 1)).collect();
+                    TreeNodeRef::new_vals(MathToken::operator(op), args)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let mut lcg = Lcg(7);
+        for _ in 0..2000 {
+            let tree = lcg.tree(4);
+            let txt = tree.to_string();
+            assert_eq!(MathTree::parse(&txt).map(|t| t.root), Ok(tree), "{}", txt);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+// graphviz DOT of the tree, for debugging: dot -Tsvg tree.dot > tree.svg
+// nodes are colored by the partition of Operands they are stored in,
+// edges are labeled with the OperandPos of the operand
+
+use std::{collections::HashSet, fmt::Write};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    math_tree::{MathTree, TreeNodeRef},
+    MathTokenType,
+};
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl MathTree {
+    pub fn to_dot(&self) -> String {
+        self.root.to_dot()
+    }
+
+    // self and after side by side, subtrees of after that are not in self drawn bold
+    pub fn to_dot_diff(&self, after: &MathTree) -> String {
+        self.root.to_dot_diff(&after.root)
+    }
+}
+
+impl TreeNodeRef {
+    pub fn to_dot(&self) -> String {
+        let mut writer = DotWriter::default();
+        writer.node(self, None);
+        format!("digraph {{\n{}{}}}\n", HEADER, writer.out)
+    }
+
+    pub fn to_dot_diff(&self, after: &TreeNodeRef) -> String {
+        let mut before = DotWriter::default();
+        before.node(self, None);
+
+        let mut seen = HashSet::new();
+        subtree_hashes(self, &mut seen);
+        let mut after_writer = DotWriter {
+            next: before.next,
+            unchanged: Some(seen),
+            ..Default::default()
+        };
+        after_writer.node(after, None);
+
+        format!(
+            "digraph {{\n{}{}{}}}\n",
+            HEADER,
+            cluster("before", &before.out),
+            cluster("after", &after_writer.out)
+        )
+    }
+}
+
+const HEADER: &str = "    node [shape=box, style=filled, fontname=monospace];\n";
+
+fn cluster(name: &str, body: &str) -> String {
+    let body = body
+        .lines()
+        .map(|line| format!("    {}\n", line))
+        .collect::<String>();
+    format!(
+        "    subgraph cluster_{} {{\n        label=\"{}\";\n{}    }}\n",
+        name, name, body
+    )
+}
+
+fn subtree_hashes(node: &TreeNodeRef, seen: &mut HashSet<u64>) {
+    seen.insert(node.structural_hash());
+    for (_, child) in node.borrow().operands().iter_order() {
+        subtree_hashes(child, seen);
+    }
+}
+
+fn color(kind: MathTokenType) -> &'static str {
+    match kind {
+        MathTokenType::Constant => "lightblue",
+        MathTokenType::Variable => "palegreen",
+        MathTokenType::Function => "plum",
+        MathTokenType::Operator => "orange",
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[derive(Default)]
+struct DotWriter {
+    out: String,
+    // id of the next node
+    next: usize,
+    // hashes of the tree compared to, nodes found there are unchanged
+    unchanged: Option<HashSet<u64>>,
+}
+
+impl DotWriter {
+    // parent is the id of the parent and the operand position of node in it
+    fn node(&mut self, node: &TreeNodeRef, parent: Option<(usize, usize)>) {
+        let id = self.next;
+        self.next += 1;
+
+        let val = node.val();
+        let changed = match &self.unchanged {
+            Some(seen) if !seen.contains(&node.structural_hash()) => ", penwidth=3",
+            _ => "",
+        };
+        writeln!(
+            self.out,
+            "    n{} [label=\"{}\\n{:?}\", fillcolor={}{}];",
+            id,
+            escape(&format!("{:?}", val)),
+            val.kind,
+            color(val.kind),
+            changed
+        )
+        .unwrap();
+
+        if let Some((parent, pos)) = parent {
+            writeln!(self.out, "    n{} -> n{} [label=\"{}\"];", parent, id, pos).unwrap();
+        }
+
+        // slab order, the positions are the keys the simplifier works with
+        let borrow = node.borrow();
+        for (pos, child) in borrow.operands().iter_order() {
+            self.node(child, Some((id, pos.index())));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::math_tree::MathTree;
+
+    #[test]
+    fn dot() {
+        assert_eq!(
+            MathTree::parse("2 * x + 1").unwrap().to_dot(),
+            [
+                "digraph {",
+                "    node [shape=box, style=filled, fontname=monospace];",
+                "    n0 [label=\"Add\\nOperator\", fillcolor=orange];",
+                "    n1 [label=\"Multiply\\nOperator\", fillcolor=orange];",
+                "    n0 -> n1 [label=\"0\"];",
+                "    n2 [label=\"2\\nConstant\", fillcolor=lightblue];",
+                "    n1 -> n2 [label=\"0\"];",
+                "    n3 [label=\"x\\nVariable\", fillcolor=palegreen];",
+                "    n1 -> n3 [label=\"1\"];",
+                "    n4 [label=\"1\\nConstant\", fillcolor=lightblue];",
+                "    n0 -> n4 [label=\"1\"];",
+                "}",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn dot_diff() {
+        let before = MathTree::parse("sin(x) + 2 * 3").unwrap();
+        let after = MathTree::parse("sin(x) + 6").unwrap();
+        let dot = before.to_dot_diff(&after);
+
+        assert!(dot.contains("subgraph cluster_before {"));
+        assert!(dot.contains("subgraph cluster_after {"));
+        // ids continue in the second tree
+        assert!(dot.contains("        n7 [label=\"sin\\nFunction\", fillcolor=plum];"));
+        assert!(
+            dot.contains("        n9 [label=\"6\\nConstant\", fillcolor=lightblue, penwidth=3];")
+        );
+        // the root changed, sin(x) did not
+        assert!(
+            dot.contains("        n6 [label=\"Add\\nOperator\", fillcolor=orange, penwidth=3];")
+        );
+        assert_eq!(dot.matches("penwidth=3").count(), 2);
+    }
+}
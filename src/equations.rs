@@ -1,9 +1,16 @@
 use itertools::Itertools;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{Signed, Zero};
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    complex::Complex,
     function::function::Function,
-    math_tree::{MathTree, ParseError, TreeNodeRef, TreePos},
+    lexer::Span,
+    math_tree::{MathTree, ParseError, ParseErrorKind, TreeNodeRef, TreePos, VarBounds},
+    modular::Modular,
+    number::Number,
     stepper::Steps,
     MathToken, OperationToken,
 };
@@ -11,6 +18,16 @@ use crate::{
 pub struct Equation {
     left: MathTree,
     right: MathTree,
+    // solved as a congruence: 3x = 4 (mod 7)
+    modulus: Option<BigInt>,
+}
+
+// larger moduli are not searched for quadratic congruences
+const MAX_CONGRUENCE_SEARCH: u32 = 100_000;
+
+// 0, 1, ..., n - 1
+fn residues(n: &BigInt) -> impl Iterator<Item = BigInt> + '_ {
+    std::iter::successors(Some(BigInt::zero()), |k| Some(k + 1u32)).take_while(move |k| k < n)
 }
 
 // Quadratic formula ax^2 + bx + c = 0; 0 = (-b ± sqrt(b^2 - 4ac))/2a
@@ -35,36 +52,81 @@ pub enum EquationSolution {
     // ∅ - null sign (empty set)
     NoSolution,
     SolutionsFor(TreeNodeRef, Vec<TreeNodeRef>),
+    // past what the solver handles, not a proof there is no solution: x^3 = 1 (mod 7)
+    Unsupported,
 }
 
 impl Equation {
     pub fn new(left: MathTree, right: MathTree) -> Self {
-        Self { left, right }
+        Self {
+            left,
+            right,
+            modulus: None,
+        }
+    }
+
+    pub fn with_modulus(mut self, modulus: impl Into<BigInt>) -> Self {
+        self.modulus = Some(modulus.into());
+        self
     }
 
     pub fn parse(equation: &str) -> Result<Equation, ParseError> {
+        // 3x = 4 (mod 7)
+        let (equation, modulus) = match equation
+            .trim_end()
+            .strip_suffix(')')
+            .and_then(|e| e.rsplit_once("(mod"))
+        {
+            Some((body, n)) => match n.trim().parse::<BigInt>() {
+                Ok(n) if n.is_positive() => (body, Some(n)),
+                _ => {
+                    return Err(ParseError::new(
+                        ParseErrorKind::MalformedNumber,
+                        Span::new(body.len(), equation.len()),
+                    ))
+                }
+            },
+            None => (equation, None),
+        };
+
         let Some((left, right)) = equation.split("=").collect_tuple() else {
-            return Err(ParseError::MissingOperand);
+            return Err(ParseError::new(
+                ParseErrorKind::MissingEqualSign,
+                Span::new(0, equation.len()),
+            ));
         };
 
-        Ok(Self::new(MathTree::parse(left)?, MathTree::parse(right)?))
+        Ok(Self {
+            left: MathTree::parse(left)?,
+            // report errors relative to the whole equation
+            right: MathTree::parse(right).map_err(|e| e.offset(left.len() + 1))?,
+            modulus,
+        })
     }
 
     pub fn solve(&mut self) -> EquationSolution {
+        let var = MathToken::variable(String::from("x").into());
+        if let Some(n) = self.modulus.clone() {
+            return self.solve_congruence(&var, &n);
+        }
+
         // concentrate the variable on the left side
 
         // if right_variables.len() > right_variables.len() {
         //     self.flip_sides();
         // }
 
+        // ax^2 + bx + c = 0, complex roots when Δ < 0
+        if let Some(solution) = self.solve_quadratic(&var) {
+            return solution;
+        }
+
         let mut steps = Steps::new();
         self.right.simplify(&mut steps).unwrap();
         self.left.simplify(&mut steps).unwrap();
         let left_variables = Function::scan_variables(&self.left.root);
         let right_variables = Function::scan_variables(&self.right.root);
 
-        let var = MathToken::variable(String::from("x").into());
-
         // move all non variables from left to right
         // for _ in 0..2 {
         loop {
@@ -141,6 +203,189 @@ impl Equation {
         EquationSolution::NoSolution
     }
 
+    // x^2 + 1 = 0; x = ±i
+    fn solve_quadratic(&self, var: &MathToken) -> Option<EquationSolution> {
+        // read off each side as written, simplifying would factor x^2 - 2x into x(x - 2)
+        let left = Self::quadratic_coefficients(&self.left.root, var)?;
+        let right = Self::quadratic_coefficients(&self.right.root, var)?;
+        let [c, b, a] = [0, 1, 2].map(|i| &left[i] - &right[i]);
+        if a.is_zero() {
+            return None;
+        }
+
+        let x = TreeNodeRef::new_val(var.clone());
+        let discriminant = &(&b * &b) - &(&(Number::from(4) * a.clone()) * &c);
+        let two_a = Number::two() * a;
+        let vertex = (-b).checked_div(&two_a)?;
+
+        let roots = match discriminant.abs().nth_root(2) {
+            // exact: x^2 - 1 = 0, x^2 + 4 = 0
+            Some(root) => {
+                let offset = root.checked_div(&two_a)?;
+                let (plus, minus) = if discriminant.is_sign_negative() {
+                    (
+                        Complex::new(vertex.clone(), offset.clone()),
+                        Complex::new(vertex, -offset),
+                    )
+                } else {
+                    (
+                        Complex::real(&vertex + &offset),
+                        Complex::real(&vertex - &offset),
+                    )
+                };
+                vec![plus.to_node(), minus.to_node()]
+            }
+            // (-b ± sqrt(Δ)) / 2a
+            None => [OperationToken::Add, OperationToken::Subtract]
+                .into_iter()
+                .map(|op| {
+                    let sqrt = TreeNodeRef::constant(discriminant.clone()).root(TreeNodeRef::two());
+                    let mut root = MathTree {
+                        root: TreeNodeRef::constant(vertex.clone())
+                            .op(op, sqrt.divide(TreeNodeRef::constant(two_a.clone()))),
+                        bounds: VarBounds::new(),
+                    };
+                    root.simplify(&mut Steps::new()).ok()?;
+                    Some(root.root)
+                })
+                .collect::<Option<Vec<_>>>()?,
+        };
+
+        Some(EquationSolution::SolutionsFor(
+            x,
+            // Δ = 0: a single root
+            roots.into_iter().dedup().collect(),
+        ))
+    }
+
+    // every residue x with left = right (mod n): 3x = 4 (mod 7); x = 6, 2x = 4 (mod 6); x = 2, 5
+    fn solve_congruence(&self, var: &MathToken, n: &BigInt) -> EquationSolution {
+        let mut tree = MathTree {
+            root: self.left.root.subtract(self.right.root.clone()),
+            bounds: VarBounds::new(),
+        };
+        // x / 2 = 1 (mod 4)
+        if tree.simplify_mod(n.clone()).is_err() {
+            return EquationSolution::Unsupported;
+        }
+        let Some(coefficients) = Self::quadratic_coefficients(&tree.root, var) else {
+            return EquationSolution::Unsupported;
+        };
+        let [c, b, a] = coefficients.map(|c| c.numer().mod_floor(n));
+
+        let solutions = if a.is_zero() {
+            // bx = -c has gcd(b, n) solutions when gcd(b, n) divides c, n / gcd(b, n) apart
+            let gcd = b.gcd(n);
+            let c = (-c).mod_floor(n);
+            if !c.is_multiple_of(&gcd) {
+                return EquationSolution::NoSolution;
+            }
+
+            let step = n / &gcd;
+            let Some(inverse) = Modular::new(&(&b / &gcd), &step).inverse().value else {
+                return EquationSolution::Unsupported;
+            };
+            let first = (c / &gcd * inverse).mod_floor(&step);
+            residues(&gcd).map(|k| &first + k * &step).collect_vec()
+        } else if *n <= BigInt::from(MAX_CONGRUENCE_SEARCH) {
+            // ax^2 + bx + c, every residue is tried
+            residues(n)
+                .filter(|x| (&a * x * x + &b * x + &c).is_multiple_of(n))
+                .collect_vec()
+        } else {
+            return EquationSolution::Unsupported;
+        };
+
+        // every residue was tried
+        if solutions.is_empty() {
+            return EquationSolution::NoSolution;
+        }
+        EquationSolution::SolutionsFor(
+            TreeNodeRef::new_val(var.clone()),
+            solutions.into_iter().map(TreeNodeRef::constant).collect(),
+        )
+    }
+
+    // [c, b, a] of a polynomial of degree 2 in var, None for anything else.
+    // products are distributed: 2(x + 1) = 2x + 2, (x + 1)^2 = x^2 + 2x + 1
+    fn quadratic_coefficients(node: &TreeNodeRef, var: &MathToken) -> Option<[Number; 3]> {
+        let val = node.val();
+        if let Some(c) = val.constant {
+            return Some([c, Number::zero(), Number::zero()]);
+        }
+        if &val == var {
+            return Some([Number::zero(), Number::one(), Number::zero()]);
+        }
+
+        let borrow = node.borrow();
+        let mut terms = borrow
+            .calculate_iter()
+            .map(|(_, term)| Self::quadratic_coefficients(term, var));
+        match val.operation? {
+            OperationToken::Add => terms.try_fold(Self::quadratic_constant(0), |sum, term| {
+                Some(Self::quadratic_add(sum, term?, false))
+            }),
+            // a - b - c
+            OperationToken::Subtract => {
+                let first = terms.next()??;
+                terms.try_fold(first, |sum, term| {
+                    Some(Self::quadratic_add(sum, term?, true))
+                })
+            }
+            OperationToken::Multiply => terms
+                .try_fold(Self::quadratic_constant(1), |product, term| {
+                    Self::quadratic_product(&product, &term?)
+                }),
+            OperationToken::Pow => {
+                let (base, exponent) = borrow.calculate_iter().map(|(_, n)| n).collect_tuple()?;
+                let base = Self::quadratic_coefficients(base, var)?;
+                let exponent = exponent
+                    .val()
+                    .constant
+                    .filter(|e| e.is_integer() && !e.is_sign_negative())?;
+
+                // 2^10
+                if base[1].is_zero() && base[2].is_zero() {
+                    let power = base[0].pow(&exponent).ok()??;
+                    return Some([power, Number::zero(), Number::zero()]);
+                }
+                // (x + 1)^2, any larger power is past degree 2
+                (0..exponent.to_u32().filter(|e| *e <= 2)?)
+                    .try_fold(Self::quadratic_constant(1), |power, _| {
+                        Self::quadratic_product(&power, &base)
+                    })
+            }
+            _ => None,
+        }
+    }
+
+    fn quadratic_constant(c: i32) -> [Number; 3] {
+        [Number::from(c), Number::zero(), Number::zero()]
+    }
+
+    fn quadratic_add(p: [Number; 3], q: [Number; 3], subtract: bool) -> [Number; 3] {
+        let mut q = q.into_iter();
+        p.map(|a| {
+            let b = q.next().unwrap();
+            if subtract {
+                a - b
+            } else {
+                a + b
+            }
+        })
+    }
+
+    // p * q, None past degree 2
+    fn quadratic_product(p: &[Number; 3], q: &[Number; 3]) -> Option<[Number; 3]> {
+        let mut product = Self::quadratic_constant(0);
+        for (i, a) in p.iter().enumerate().filter(|(_, a)| !a.is_zero()) {
+            for (j, b) in q.iter().enumerate().filter(|(_, b)| !b.is_zero()) {
+                *product.get_mut(i + j)? += a * b;
+            }
+        }
+        Some(product)
+    }
+
     // pub fn opposite_operator(op: OperationToken) -> OperationToken {
     //     OPPOSITE_OPERATOR[op as usize]
     // }
@@ -170,7 +415,7 @@ mod tests {
 
     use crate::{
         equations::Equation,
-        math_tree::{ParseError, TreeNodeRef},
+        math_tree::{ParseError, ParseErrorKind, TreeNodeRef},
         MathToken,
     };
     use pretty_assertions::assert_eq;
@@ -187,7 +432,7 @@ mod tests {
         equation_test(
             equation,
             EquationSolution::SolutionsFor(
-                TreeNodeRef::new_val(MathToken::variable("x".to_string())),
+                TreeNodeRef::new_val(MathToken::variable("x".to_string().into())),
                 vec![res],
             ),
         );
@@ -210,4 +455,72 @@ mod tests {
         equation_test_single_x("2 * x + 4 = x + 4", TreeNodeRef::constant(dec!(0)));
         Ok(())
     }
+
+    #[test]
+    pub fn quadratic_equation() {
+        let x = TreeNodeRef::new_val(MathToken::variable("x".to_string().into()));
+        let i = TreeNodeRef::var("i");
+
+        equation_test(
+            "x^2 - 1 = 0",
+            EquationSolution::SolutionsFor(
+                x.clone(),
+                vec![TreeNodeRef::one(), TreeNodeRef::constant(-1)],
+            ),
+        );
+        equation_test(
+            "x^2 + 1 = 0",
+            EquationSolution::SolutionsFor(
+                x.clone(),
+                vec![i.clone(), TreeNodeRef::constant(-1).multiply(i.clone())],
+            ),
+        );
+        equation_test(
+            "x^2 + 2x + 5 = 0",
+            EquationSolution::SolutionsFor(
+                x.clone(),
+                vec![
+                    TreeNodeRef::constant(-1).add(TreeNodeRef::two().multiply(i.clone())),
+                    TreeNodeRef::constant(-1).add(TreeNodeRef::constant(-2).multiply(i)),
+                ],
+            ),
+        );
+        equation_test_single_x("x^2 - 2x = -1", TreeNodeRef::one());
+        equation_test(
+            "(x - 1)(x + 1) = 0",
+            EquationSolution::SolutionsFor(
+                x.clone(),
+                vec![TreeNodeRef::one(), TreeNodeRef::constant(-1)],
+            ),
+        );
+    }
+
+    #[test]
+    pub fn congruences() {
+        let x = TreeNodeRef::new_val(MathToken::variable("x".to_string().into()));
+        let solutions = |res: &[i32]| {
+            EquationSolution::SolutionsFor(
+                x.clone(),
+                res.iter().map(|r| TreeNodeRef::constant(*r)).collect(),
+            )
+        };
+
+        equation_test("3x = 4 (mod 7)", solutions(&[6]));
+        equation_test("2x = 4 (mod 6)", solutions(&[2, 5]));
+        equation_test("2x = 1 (mod 4)", EquationSolution::NoSolution);
+        equation_test("x^2 = 1 (mod 8)", solutions(&[1, 3, 5, 7]));
+        equation_test("x^2 = 2 (mod 5)", EquationSolution::NoSolution);
+        equation_test("x / 2 = 1 (mod 7)", solutions(&[2]));
+        equation_test("2(x + 1) = 4 (mod 7)", solutions(&[1]));
+        equation_test("(x + 1)^2 = 0 (mod 7)", solutions(&[6]));
+
+        // not solved, rather than no solution
+        equation_test("x^3 = 1 (mod 7)", EquationSolution::Unsupported);
+        equation_test("x^2 = 4 (mod 1000003)", EquationSolution::Unsupported);
+        equation_test("x / 2 = 1 (mod 4)", EquationSolution::Unsupported);
+        assert_eq!(
+            Equation::parse("x = 1 (mod y)").err().map(|e| e.kind),
+            Some(ParseErrorKind::MalformedNumber)
+        );
+    }
 }
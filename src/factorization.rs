@@ -1,12 +1,14 @@
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use itertools::Itertools;
-use rust_decimal::Decimal;
-use rust_decimal_macros::dec;
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
 
 use crate::{
     arithmatic::power,
     math_tree::{MathTree, TreeNode, TreeNodeRef},
+    number::Number,
+    number_theory::{gcd, lcm},
     MathToken, MathTokenType, OperationToken,
 };
 
@@ -41,12 +43,16 @@ impl MathTree {
         Self::find_common_variable(multipliers)
     }
 
-    fn get_constant_multiplier(node: TreeNodeRef) -> (Decimal, TreeNodeRef) {
+    fn get_constant_multiplier(node: TreeNodeRef) -> (Number, TreeNodeRef) {
         if node.val().operation == Some(OperationToken::Multiply) {
             let borrow = node.borrow();
-            let mut iter = borrow.calculate_iter().map(|x| x.1.clone());
+            let mut iter = borrow.calculate_iter().map(|x| x.1.clone()).peekable();
 
-            let constant = iter.next().unwrap().val().constant.unwrap();
+            // constants come first, there may be none: x * y
+            let Some(constant) = iter.peek().and_then(|x| x.val().constant) else {
+                return (Number::one(), node.clone());
+            };
+            iter.next();
 
             // remaining variables
             let mut childs = iter.collect_vec();
@@ -58,7 +64,7 @@ impl MathTree {
 
             (constant, multiplier)
         } else {
-            (Decimal::ONE, node)
+            (Number::one(), node)
         }
     }
     // (x, x^2) common: x
@@ -94,9 +100,9 @@ impl MathTree {
             node == of
                 || match val.kind {
                     MathTokenType::Constant => {
-                        val.constant.unwrap() * dec!(-1) == of.val().constant.unwrap()
+                        Some(-val.constant.unwrap()) == of.val().constant
                     }
-                    MathTokenType::Variable => false,
+                    MathTokenType::Variable | MathTokenType::Function => false,
                     MathTokenType::Operator => {
                         val == MathToken::operator(OperationToken::Multiply)
                             && node.borrow().calculate_iter().any(|(_, n)| n == of)
@@ -121,60 +127,39 @@ impl MathTree {
         constant_multiplier
     }
 
-    fn find_common_factor_constant(mut constants: BTreeSet<Decimal>) -> Option<Decimal> {
-        // sorted for efficiency
-
-        // all the numbers need to be divisiable by the common term
-
+    // largest rational dividing all constants: gcd of the numerators over lcm of the denominators,
+    // negative when the smallest constant is: [-2, 4] => -2
+    fn find_common_factor_constant(constants: BTreeSet<Number>) -> Option<Number> {
         if constants.len() <= 1 {
             return None;
         }
 
-        // 1) first check smallest number
-        let mut check = constants.first().unwrap().clone();
-        let mut divider = dec!(1);
-
-        'outer: loop {
-            check = check / divider;
-            divider += dec!(1);
-
-            if check == Decimal::ONE {
-                break;
-            }
-
-            for c in &constants {
-                if c.checked_rem(check.clone()) != Some(Decimal::ZERO) {
-                    // break;
-                    continue 'outer;
-                }
-            }
-
-            return Some(check);
+        let numer = constants
+            .iter()
+            .fold(BigInt::zero(), |res, c| gcd(&res, c.numer()));
+        let denom = constants
+            .iter()
+            .fold(BigInt::one(), |res, c| lcm(&res, c.denom()));
+        let common = Number::new(numer, denom);
+        if common.is_zero() || common.is_one() {
+            return None;
         }
-        None
-    }
-
-    fn find_common_denominator_constant(mut constants: BTreeSet<Decimal>) -> Decimal {
-        let biggest = constants.pop_last().unwrap();
-        let mut counter = biggest;
-        loop {
-            if constants
-                .iter()
-                .all(|c| c.checked_rem(biggest) == Some(Decimal::ZERO))
-            {
-                return counter;
-            }
 
-            counter += biggest;
+        if constants.first().unwrap().is_sign_negative() {
+            Some(-common)
+        } else {
+            Some(common)
         }
     }
 }
 #[cfg(test)]
 mod tests {
+    use rust_decimal::Decimal;
     use rust_decimal_macros::dec;
 
     use crate::{
         math_tree::{MathTree, TreeNodeRef},
+        number::Number,
         simplify::simplify,
         MathToken,
     };
@@ -182,39 +167,57 @@ mod tests {
 
     #[test]
     fn common_multiplier_constant() {
+        let common = |constants: &[Decimal]| {
+            MathTree::find_common_factor_constant(constants.iter().map(|&c| c.into()).collect())
+        };
+
         assert_eq!(
-            MathTree::find_common_factor_constant([dec!(2)].into()),
+            common(&[dec!(2)]),
             None
         );
 
         assert_eq!(
-            MathTree::find_common_factor_constant([dec!(2), dec!(3)].into()),
+            common(&[dec!(2), dec!(3)]),
             None
         );
 
         assert_eq!(
-            MathTree::find_common_factor_constant([dec!(2), dec!(4)].into()),
-            Some(dec!(2))
+            common(&[dec!(2), dec!(4)]),
+            Some(dec!(2).into())
         );
 
         assert_eq!(
-            MathTree::find_common_factor_constant([dec!(2), dec!(-2)].into()),
-            Some(dec!(-2))
+            common(&[dec!(2), dec!(-2)]),
+            Some(dec!(-2).into())
         );
 
         assert_eq!(
-            MathTree::find_common_factor_constant([dec!(4), dec!(6)].into()),
-            Some(dec!(2))
+            common(&[dec!(4), dec!(6)]),
+            Some(dec!(2).into())
         );
 
         assert_eq!(
-            MathTree::find_common_factor_constant([dec!(4), dec!(8), dec!(12)].into()),
-            Some(dec!(4))
+            common(&[dec!(4), dec!(8), dec!(12)]),
+            Some(dec!(4).into())
         );
 
         assert_eq!(
-            MathTree::find_common_factor_constant([dec!(4), dec!(8), dec!(0.5)].into()),
-            Some(dec!(0.5))
+            common(&[dec!(4), dec!(8), dec!(0.5)]),
+            Some(dec!(0.5).into())
+        );
+
+        // exact, 1/3 is not rounded
+        assert_eq!(
+            MathTree::find_common_factor_constant(
+                [Number::new(1, 3), Number::new(2, 3)].into()
+            ),
+            Some(Number::new(1, 3))
+        );
+        assert_eq!(
+            MathTree::find_common_factor_constant(
+                [Number::new(1, 2), Number::new(1, 3)].into()
+            ),
+            Some(Number::new(1, 6))
         );
     }
 
@@ -1,12 +1,21 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    collections::HashMap,
+    ops::{Add, Div, Mul, Sub},
+    rc::Rc,
+};
 
 use crate::{
-    arithmatic::arithmatic::{perform_op_constant, OperationError},
-    constants::CONSTANTS_MAP,
+    arithmatic::{
+        arithmatic::{perform_op_constant, OperationError, Pow},
+        functions::{perform_function_complex, perform_function_float},
+    },
+    constants::get_constant,
+    interval::{perform_function_interval, Interval},
     lexer::Lexer,
     math_tree::{MathTree, ParseError, TreeNodeRef},
-    MathTokenType, OperationToken,
+    FunctionToken, MathTokenType, OperationToken,
 };
+use num_complex::Complex64;
 use rust_decimal::prelude::ToPrimitive;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
@@ -35,6 +44,7 @@ impl VariableVal {
 pub struct FastFunctionMathToken {
     pub val: Option<f64>,
     pub op: Option<OperationToken>,
+    pub func: Option<FunctionToken>,
 }
 
 impl FastFunctionMathToken {
@@ -42,6 +52,7 @@ impl FastFunctionMathToken {
         Self {
             val: Some(v),
             op: None,
+            func: None,
         }
     }
 
@@ -49,6 +60,15 @@ impl FastFunctionMathToken {
         Self {
             val: None,
             op: Some(op),
+            func: None,
+        }
+    }
+
+    pub fn func(f: FunctionToken) -> Self {
+        Self {
+            val: None,
+            op: None,
+            func: Some(f),
         }
     }
 }
@@ -84,7 +104,7 @@ impl MathTree {
         let val = node.val();
         match val.kind {
             MathTokenType::Constant => rpn.push(FastFunctionMathToken::val(
-                val.constant.unwrap().to_f64().unwrap(),
+                val.constant.unwrap().to_f64(),
             )),
 
             MathTokenType::Variable => {
@@ -95,6 +115,7 @@ impl MathTree {
                 rpn.push(FastFunctionMathToken::val(f64::MAX))
             }
             MathTokenType::Operator => rpn.push(FastFunctionMathToken::op(val.operation.unwrap())),
+            MathTokenType::Function => rpn.push(FastFunctionMathToken::func(val.function.unwrap())),
         }
     }
 }
@@ -124,7 +145,7 @@ impl FastFunction {
             // TODO: handle error
             let val = match values.get(var) {
                 Some(v) => *v,
-                None => CONSTANTS_MAP.get(var.as_str()).unwrap().to_f64().unwrap(),
+                None => constant_value(var),
             };
 
             let val = FastFunctionMathToken::val(val);
@@ -137,6 +158,11 @@ impl FastFunction {
         for token in &self.rpn {
             if let Some(operand) = token.val {
                 calculations_stack.push(operand);
+            } else if let Some(f) = token.func {
+                let args_at = calculations_stack.len() - f.info().arity as usize;
+                let res = perform_function_float(f, &calculations_stack[args_at..]);
+                calculations_stack.truncate(args_at);
+                calculations_stack.push(res);
             } else {
                 let b = calculations_stack.pop().unwrap();
                 let a = calculations_stack.pop().unwrap();
@@ -149,6 +175,105 @@ impl FastFunction {
     }
 }
 
+impl FastFunction {
+    // same as evaluate_float over complex numbers: sqrt(x) at -4 is 2i, i is substituted
+    pub fn evaluate_complex(
+        &mut self,
+        values: Vec<VariableVal>,
+    ) -> Result<Option<Complex64>, OperationError> {
+        let values = HashMap::<Rc<String>, Complex64>::from_iter(
+            values.into_iter().map(|x| (x.var.into(), Complex64::from(x.val))),
+        );
+
+        Ok(Some(self.evaluate_with(
+            |var| match values.get(var) {
+                Some(v) => *v,
+                None if var.as_str() == "i" => Complex64::i(),
+                None => Complex64::from(constant_value(var)),
+            },
+            perform_function_complex,
+        )))
+    }
+
+    // an enclosure of the values over the given intervals, see MathTree::evaluate_interval
+    pub fn evaluate_interval(&self, values: &HashMap<String, Interval>) -> Interval {
+        self.evaluate_with(
+            |var| match values.get(var.as_str()) {
+                Some(v) => *v,
+                None if get_constant(var).is_some_and(|c| c.value.is_some()) => {
+                    Interval::around(constant_value(var))
+                }
+                None => Interval::ENTIRE,
+            },
+            perform_function_interval,
+        )
+    }
+
+    // one enclosure per pixel column of [from, to] for plotting, a spike inside a column is never missed
+    pub fn evaluate_columns(
+        &self,
+        var: &str,
+        from: f64,
+        to: f64,
+        columns: usize,
+    ) -> Vec<Interval> {
+        let step = (to - from) / columns as f64;
+        (0..columns)
+            .map(|i| {
+                let column = Interval::new(from + step * i as f64, from + step * (i + 1) as f64);
+                self.evaluate_interval(&HashMap::from([(var.to_string(), column)]))
+            })
+            .collect()
+    }
+
+    // runs the rpn over any number type, var gives each variable its value
+    fn evaluate_with<
+        T: Copy + From<f64> + Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T> + Pow,
+    >(
+        &self,
+        var: impl Fn(&Rc<String>) -> T,
+        function: fn(FunctionToken, &[T]) -> T,
+    ) -> T {
+        let mut replace = HashMap::new();
+        for (name, indexes) in &self.replace {
+            let val = var(name);
+            for index in indexes {
+                replace.insert(*index, val);
+            }
+        }
+        let mut calculations_stack = SmallVec::<[T; 32]>::new();
+
+        for (i, token) in self.rpn.iter().enumerate() {
+            if let Some(operand) = token.val {
+                calculations_stack.push(match replace.get(&i) {
+                    Some(v) => *v,
+                    None => T::from(operand),
+                });
+            } else if let Some(f) = token.func {
+                let args_at = calculations_stack.len() - f.info().arity as usize;
+                let res = function(f, &calculations_stack[args_at..]);
+                calculations_stack.truncate(args_at);
+                calculations_stack.push(res);
+            } else {
+                let b = calculations_stack.pop().unwrap();
+                let a = calculations_stack.pop().unwrap();
+
+                calculations_stack.push(perform_op_constant(a, b, token.op.unwrap()));
+            }
+        }
+
+        calculations_stack.pop().unwrap()
+    }
+}
+
+// i has no real value
+fn constant_value(var: &str) -> f64 {
+    get_constant(var)
+        .and_then(|c| c.value)
+        .and_then(|v| v.to_f64())
+        .unwrap_or(f64::NAN)
+}
+
 #[cfg(test)]
 pub mod tests {
     use crate::{
@@ -224,4 +349,39 @@ pub mod tests {
             Ok(Some(-5.0))
         );
     }
+
+    #[test]
+    fn fast_func_functions() {
+        let mut fx = FastFunction::from(
+            &Function::from(MathTree::parse("sin(x) + log(x, 2) * abs(x)").unwrap()).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            fx.evaluate_float(vec![VariableVal::new("x".to_string(), 8.0)]),
+            Ok(Some(8f64.sin() + 3.0 * 8.0))
+        );
+
+        let mut fx =
+            FastFunction::from(&Function::from(MathTree::parse("exp(x) + cos(x)").unwrap()).unwrap())
+                .unwrap();
+
+        assert_eq!(
+            fx.evaluate_float(vec![VariableVal::new("x".to_string(), 0.0)]),
+            Ok(Some(2.0))
+        );
+    }
+
+    #[test]
+    fn fast_func_roots() {
+        let mut fx = FastFunction::from(
+            &Function::from(MathTree::parse("sqrt(x) + root(x - 17, 3)").unwrap()).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            fx.evaluate_float(vec![VariableVal::new("x".to_string(), 9.0)]),
+            Ok(Some(1.0))
+        );
+    }
 }
@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     arithmatic::arithmatic::OperationError,
+    constants::is_constant_node,
     math_tree::{MathTree, TreeNodeRef},
     operands::OperandPos,
     stepper::Steps,
@@ -65,7 +66,7 @@ impl Function {
     pub(crate) fn scan_variables(root: &TreeNodeRef) -> Vec<(TreeNodeRef, Option<OperandPos>)> {
         let mut variables = Vec::new();
         // Just X or some variable, unique case
-        if root.val().kind == MathTokenType::Variable {
+        if root.val().kind == MathTokenType::Variable && !is_constant_node(root) {
             variables.push((root.clone(), None));
         }
         Self::scan_variables_node(root, &mut variables);
@@ -85,6 +86,8 @@ impl Function {
         let b = borrow
             .operands()
             .variables()
+            // pi is not substituted
+            .filter(|pos| !is_constant_node(&borrow[*pos]))
             .map(|pos| (node.clone(), Some(pos)))
             .collect_vec();
 
@@ -95,9 +98,10 @@ impl Function {
 #[cfg(test)]
 mod tests {
     use crate::{
-        arithmatic::arithmatic::OperationError,
         math_tree::{MathTree, TreeNodeRef},
+        number::Number,
     };
+    use num_bigint::BigInt;
     use pretty_assertions::assert_eq;
     use rust_decimal_macros::dec;
 
@@ -162,10 +166,10 @@ mod tests {
             Ok(Some(TreeNodeRef::constant(dec!(0.25))))
         );
 
-        // overflow
+        // exact, 1/40^40 is beyond Decimal
         assert_eq!(
             fx.evaluate(TreeNodeRef::constant(dec!(-40))),
-            Err(OperationError::Overflow)
+            Ok(Some(TreeNodeRef::constant(Number::new(1, BigInt::from(40).pow(40)))))
         );
     }
 
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::math_tree::TreeNodeRef;
+
+// hash-consing: equal subtrees share one allocation, so repeated subexpressions
+// can be found by counting. interned trees are shared, mutating a node mutates it everywhere
+#[derive(Debug, Default)]
+pub struct Interner {
+    // position in nodes
+    index: HashMap<TreeNodeRef, usize>,
+    // every distinct subtree, by first occurrence, with how many times it was seen
+    nodes: Vec<(TreeNodeRef, usize)>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the shared copy of node, its operands interned first
+    pub fn intern(&mut self, node: &TreeNodeRef) -> TreeNodeRef {
+        let borrow = node.borrow();
+        let childs = borrow
+            .operands()
+            .iter_order()
+            .map(|(_, child)| (child.clone(), self.intern(child)))
+            .collect_vec();
+
+        // reuse the node when its operands already are the shared ones
+        let node = if childs.iter().all(|(old, new)| old.ptr_eq(new)) {
+            node.clone()
+        } else {
+            TreeNodeRef::new_vals(node.val(), childs.into_iter().map(|(_, new)| new).collect())
+        };
+        drop(borrow);
+
+        match self.index.get(&node) {
+            Some(&i) => {
+                self.nodes[i].1 += 1;
+                self.nodes[i].0.clone()
+            }
+            None => {
+                self.index.insert(node.clone(), self.nodes.len());
+                self.nodes.push((node.clone(), 1));
+                node
+            }
+        }
+    }
+
+    // distinct subtrees seen so far
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // operations seen more than once, common subexpressions: x + 1 in (x + 1)^2 + sin(x + 1).
+    // inner ones come first
+    pub fn repeated(&self) -> Vec<TreeNodeRef> {
+        self.nodes
+            .iter()
+            .filter(|(node, count)| *count > 1 && !node.borrow().operands().is_empty())
+            .map(|(node, _)| node.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::Interner;
+    use crate::math_tree::TreeNodeRef;
+
+    #[test]
+    fn intern_shares_subtrees() {
+        let mut interner = Interner::new();
+        let tree = interner.intern(&TreeNodeRef::parse("(x + 1)^2 + sin(x + 1)"));
+        assert_eq!(tree, TreeNodeRef::parse("(x + 1)^2 + sin(x + 1)"));
+
+        let borrow = tree.borrow();
+        let mut childs = borrow.operands().iter_order().map(|(_, n)| n.clone());
+        let pow = childs.next().unwrap();
+        let sin = childs.next().unwrap();
+
+        let a = pow
+            .borrow()
+            .operands()
+            .iter_order()
+            .next()
+            .unwrap()
+            .1
+            .clone();
+        let b = sin
+            .borrow()
+            .operands()
+            .iter_order()
+            .next()
+            .unwrap()
+            .1
+            .clone();
+        assert!(a.ptr_eq(&b));
+
+        // interning again returns the shared copy
+        let again = interner.intern(&TreeNodeRef::parse("x + 1"));
+        assert!(again.ptr_eq(&a));
+    }
+
+    #[test]
+    fn repeated_subexpressions() {
+        let mut interner = Interner::new();
+        interner.intern(&TreeNodeRef::parse(
+            "(x + 1)^2 + sin(x + 1) + sin(x + 1) * y",
+        ));
+
+        assert_eq!(
+            interner.repeated(),
+            vec![
+                TreeNodeRef::parse("x + 1"),
+                TreeNodeRef::parse("sin(x + 1)")
+            ]
+        );
+    }
+}
@@ -0,0 +1,532 @@
+// interval arithmetic: every result is a guaranteed enclosure [lo, hi] of the real values,
+// rounded outwards by a ulp so floating point error never loses a point (plotting, domains)
+
+use std::{
+    collections::HashMap,
+    f64::consts::{FRAC_PI_2, PI},
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arithmatic::{
+        arithmatic::{perform_op_constant, Pow},
+        functions::gcd_float,
+    },
+    bounds::Bound,
+    constants::get_constant,
+    math_tree::{MathTree, TreeNodeRef},
+    number::Number,
+    FunctionToken, MathTokenType,
+};
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64,
+}
+
+impl Interval {
+    // no real value: ln([-2, -1])
+    pub const EMPTY: Interval = Interval {
+        lo: f64::INFINITY,
+        hi: f64::NEG_INFINITY,
+    };
+    pub const ENTIRE: Interval = Interval {
+        lo: f64::NEG_INFINITY,
+        hi: f64::INFINITY,
+    };
+
+    pub fn new(lo: f64, hi: f64) -> Self {
+        if lo.is_nan() || hi.is_nan() {
+            return Self::ENTIRE;
+        }
+        Self { lo, hi }
+    }
+
+    pub fn point(x: f64) -> Self {
+        Self::new(x, x)
+    }
+
+    // a computed value within a ulp of the real one
+    pub fn around(x: f64) -> Self {
+        Self::new(next_down(x), next_up(x))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lo > self.hi
+    }
+
+    pub fn is_point(&self) -> bool {
+        self.lo == self.hi
+    }
+
+    pub fn contains(&self, x: f64) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    pub fn width(&self) -> f64 {
+        self.hi - self.lo
+    }
+
+    pub fn intersect(&self, other: &Interval) -> Interval {
+        Self {
+            lo: self.lo.max(other.lo),
+            hi: self.hi.min(other.hi),
+        }
+    }
+
+    pub fn hull(&self, other: &Interval) -> Interval {
+        match (self.is_empty(), other.is_empty()) {
+            (true, _) => *other,
+            (_, true) => *self,
+            _ => Self::new(self.lo.min(other.lo), self.hi.max(other.hi)),
+        }
+    }
+
+    // lo and hi computed with rounding to nearest, widened to contain the exact result
+    fn outward(lo: f64, hi: f64) -> Self {
+        Self::new(next_down(lo), next_up(hi))
+    }
+
+    // f increasing over self: exp, ln, odd roots
+    fn monotone(&self, f: impl Fn(f64) -> f64) -> Self {
+        if self.is_empty() {
+            return Self::EMPTY;
+        }
+        Self::outward(f(self.lo), f(self.hi))
+    }
+
+    // whole exponents, even ones are never negative: [-2, 3]^2 = [0, 9]
+    pub fn powi(&self, n: i32) -> Self {
+        if self.is_empty() {
+            return Self::EMPTY;
+        }
+        if n == 0 {
+            return Self::point(1.0);
+        }
+        if n < 0 {
+            return Self::point(1.0) / self.powi(-n);
+        }
+
+        let (lo, hi) = (self.lo.powi(n), self.hi.powi(n));
+        if n % 2 == 1 {
+            Self::outward(lo, hi)
+        } else if self.contains(0.0) {
+            Self::new(0.0, next_up(lo.max(hi)))
+        } else {
+            Self::outward(lo.min(hi), lo.max(hi))
+        }
+    }
+
+    pub fn abs(&self) -> Self {
+        if self.is_empty() {
+            Self::EMPTY
+        } else if self.lo >= 0.0 {
+            *self
+        } else if self.hi <= 0.0 {
+            -*self
+        } else {
+            Self::new(0.0, self.hi.max(-self.lo))
+        }
+    }
+
+    pub fn exp(&self) -> Self {
+        let res = self.monotone(f64::exp);
+        Self::new(res.lo.max(0.0), res.hi)
+    }
+
+    // only the positive part has a logarithm
+    pub fn ln(&self) -> Self {
+        if self.is_empty() || self.hi < 0.0 {
+            return Self::EMPTY;
+        }
+        Self::new(0f64.max(self.lo), self.hi).monotone(f64::ln)
+    }
+
+    pub fn sqrt(&self) -> Self {
+        if self.is_empty() || self.hi < 0.0 {
+            return Self::EMPTY;
+        }
+        let res = Self::new(0f64.max(self.lo), self.hi).monotone(f64::sqrt);
+        Self::new(res.lo.max(0.0), res.hi)
+    }
+
+    pub fn sin(&self) -> Self {
+        // sin is largest at pi/2 + 2kpi
+        self.periodic(f64::sin, FRAC_PI_2)
+    }
+
+    pub fn cos(&self) -> Self {
+        // cos is largest at 2kpi
+        self.periodic(f64::cos, 0.0)
+    }
+
+    // sin and cos, the extremes are at the ends unless a peak (max_at + 2kpi) or a trough (+ pi) is inside
+    fn periodic(&self, f: impl Fn(f64) -> f64, max_at: f64) -> Self {
+        if self.is_empty() {
+            return Self::EMPTY;
+        }
+        if self.width() >= 2.0 * PI {
+            return Self::new(-1.0, 1.0);
+        }
+
+        let (a, b) = (f(self.lo), f(self.hi));
+        let mut res = Self::outward(a.min(b), a.max(b));
+        if self.contains_period_point(max_at) {
+            res.hi = 1.0;
+        }
+        if self.contains_period_point(max_at + PI) {
+            res.lo = -1.0;
+        }
+        res.intersect(&Self::new(-1.0, 1.0))
+    }
+
+    // at + 2kpi for some whole k, erring towards yes
+    fn contains_period_point(&self, at: f64) -> bool {
+        let k = ((self.lo - SLACK - at) / (2.0 * PI)).ceil();
+        at + 2.0 * PI * k <= self.hi + SLACK
+    }
+
+    // poles at pi/2 + kpi make it the whole line
+    pub fn tan(&self) -> Self {
+        if self.is_empty() {
+            return Self::EMPTY;
+        }
+        if self.width() >= PI {
+            return Self::ENTIRE;
+        }
+
+        let k = ((self.lo - SLACK - FRAC_PI_2) / PI).ceil();
+        if FRAC_PI_2 + PI * k <= self.hi + SLACK {
+            Self::ENTIRE
+        } else {
+            self.monotone(f64::tan)
+        }
+    }
+}
+
+// pi is not a float, a peak or a pole this close to the interval counts as inside
+const SLACK: f64 = 1e-12;
+
+fn next_up(x: f64) -> f64 {
+    if x.is_nan() || x == f64::INFINITY {
+        x
+    } else if x == 0.0 {
+        f64::from_bits(1)
+    } else if x > 0.0 {
+        f64::from_bits(x.to_bits() + 1)
+    } else {
+        f64::from_bits(x.to_bits() - 1)
+    }
+}
+
+fn next_down(x: f64) -> f64 {
+    -next_up(-x)
+}
+
+// 0 * inf is 0 here, [0, 1] * [1, inf] = [0, inf]
+fn mul_bound(a: f64, b: f64) -> f64 {
+    if a == 0.0 || b == 0.0 {
+        0.0
+    } else {
+        a * b
+    }
+}
+
+impl From<&Number> for Interval {
+    fn from(n: &Number) -> Self {
+        let x = n.to_f64();
+        // whole numbers up to 2^53 are exact
+        match n.to_i64() {
+            Some(i) if i.unsigned_abs() < 1 << 53 => Self::point(x),
+            _ => Self::around(x),
+        }
+    }
+}
+
+// constants rounded to a float, whole ones are exact
+impl From<f64> for Interval {
+    fn from(x: f64) -> Self {
+        if x.fract() == 0.0 {
+            Self::point(x)
+        } else {
+            Self::around(x)
+        }
+    }
+}
+
+impl Neg for Interval {
+    type Output = Interval;
+
+    fn neg(self) -> Self::Output {
+        if self.is_empty() {
+            return self;
+        }
+        Self::new(-self.hi, -self.lo)
+    }
+}
+
+impl Add for Interval {
+    type Output = Interval;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        if self.is_empty() || rhs.is_empty() {
+            return Self::EMPTY;
+        }
+        Self::outward(self.lo + rhs.lo, self.hi + rhs.hi)
+    }
+}
+
+impl Sub for Interval {
+    type Output = Interval;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Mul for Interval {
+    type Output = Interval;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        if self.is_empty() || rhs.is_empty() {
+            return Self::EMPTY;
+        }
+        let products = [
+            mul_bound(self.lo, rhs.lo),
+            mul_bound(self.lo, rhs.hi),
+            mul_bound(self.hi, rhs.lo),
+            mul_bound(self.hi, rhs.hi),
+        ];
+        Self::outward(
+            products.into_iter().fold(f64::INFINITY, f64::min),
+            products.into_iter().fold(f64::NEG_INFINITY, f64::max),
+        )
+    }
+}
+
+impl Div for Interval {
+    type Output = Interval;
+
+    // divisors touching 0 go to infinity on that side: 1 / [0, 2] = [0.5, inf], 1 / [-1, 1] is everything
+    fn div(self, rhs: Self) -> Self::Output {
+        if self.is_empty() || rhs.is_empty() || (rhs.lo == 0.0 && rhs.hi == 0.0) {
+            return Self::EMPTY;
+        }
+
+        let reciprocal = if rhs.lo > 0.0 || rhs.hi < 0.0 {
+            Self::outward(1.0 / rhs.hi, 1.0 / rhs.lo)
+        } else if rhs.lo == 0.0 {
+            Self::new(next_down(1.0 / rhs.hi), f64::INFINITY)
+        } else if rhs.hi == 0.0 {
+            Self::new(f64::NEG_INFINITY, next_up(1.0 / rhs.lo))
+        } else {
+            return Self::ENTIRE;
+        };
+        self * reciprocal
+    }
+}
+
+impl Pow for Interval {
+    fn pow(&self, b: Self) -> Self {
+        match b.is_point().then_some(b.lo) {
+            Some(n) if n.fract() == 0.0 && n.abs() <= i32::MAX as f64 => self.powi(n as i32),
+            Some(0.5) => self.sqrt(),
+            // a^b = e^(b ln(a)), only for a > 0
+            _ => (b * self.ln()).exp(),
+        }
+    }
+
+    // odd roots keep the sign: root([-8, 27], 3) = [-2, 3]
+    fn root(&self, b: Self) -> Self {
+        match b.is_point().then_some(b.lo) {
+            Some(2.0) => self.sqrt(),
+            Some(n) if n.fract() == 0.0 && n % 2.0 == 1.0 => self.monotone(|x| x.root(n)),
+            _ => self.pow(Self::point(1.0) / b),
+        }
+    }
+}
+
+// f(x) over every x in the interval, see perform_function_float
+pub fn perform_function_interval(f: FunctionToken, args: &[Interval]) -> Interval {
+    let x = args[0];
+    match f {
+        FunctionToken::Sin => x.sin(),
+        FunctionToken::Cos => x.cos(),
+        FunctionToken::Tan => x.tan(),
+        FunctionToken::Ln => x.ln(),
+        FunctionToken::Log => x.ln() / args[1].ln(),
+        FunctionToken::Exp => x.exp(),
+        FunctionToken::Abs => x.abs(),
+        FunctionToken::Re | FunctionToken::Conj => x,
+        FunctionToken::Im => Interval::point(0.0),
+        // 0 for positives, pi for negatives
+        FunctionToken::Arg => {
+            let pi = Interval::around(PI);
+            match (x.lo < 0.0, x.hi >= 0.0) {
+                (true, true) => Interval::new(0.0, pi.hi),
+                (true, false) => pi,
+                _ => Interval::point(0.0),
+            }
+        }
+        // exact only for a single whole number on each side
+        FunctionToken::Gcd | FunctionToken::Lcm => {
+            let y = args[1];
+            if x.lo == x.hi && y.lo == y.hi {
+                Interval::point(gcd_float(f, x.lo, y.lo))
+            } else {
+                Interval::ENTIRE
+            }
+        }
+        FunctionToken::Factorint => x,
+        FunctionToken::Sqrt => x.root(Interval::point(2.0)),
+        FunctionToken::Root => x.root(args[1]),
+    }
+}
+
+impl MathTree {
+    pub fn restrict(&mut self, var: &str, interval: Interval) {
+        self.bounds
+            .entry(var.to_string().into())
+            .or_default()
+            .push(Bound::within(interval));
+    }
+
+    // an enclosure of every value the expression takes when its variables range over the given intervals,
+    // other variables are limited by their Ordering bounds (see restrict) or take any value
+    pub fn evaluate_interval(&self, values: &HashMap<String, Interval>) -> Interval {
+        self.evaluate_interval_node(&self.root, values)
+    }
+
+    fn evaluate_interval_node(
+        &self,
+        node: &TreeNodeRef,
+        values: &HashMap<String, Interval>,
+    ) -> Interval {
+        let val = node.val();
+        match val.kind {
+            MathTokenType::Constant => Interval::from(&val.constant.unwrap()),
+            MathTokenType::Variable => {
+                let var = val.variable.unwrap();
+                if let Some(interval) = values.get(var.as_str()) {
+                    return *interval;
+                }
+                if let Some(constant) = get_constant(&var) {
+                    // i has no real value
+                    return match constant.value.and_then(|v| v.to_f64()) {
+                        Some(v) => Interval::around(v),
+                        None => Interval::ENTIRE,
+                    };
+                }
+
+                self.bounds
+                    .get(&var)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|bound| bound.interval())
+                    .fold(Interval::ENTIRE, |a, b| a.intersect(&b))
+            }
+            MathTokenType::Operator => {
+                let op = val.operation.unwrap();
+                let borrow = node.borrow();
+                let mut operands = borrow
+                    .calculate_iter()
+                    .map(|(_, n)| self.evaluate_interval_node(n, values));
+                let first = operands.next().unwrap_or(Interval::ENTIRE);
+                operands.fold(first, |a, b| perform_op_constant(a, b, op))
+            }
+            MathTokenType::Function => {
+                let args = node
+                    .borrow()
+                    .calculate_iter()
+                    .map(|(_, n)| self.evaluate_interval_node(n, values))
+                    .collect::<Vec<_>>();
+                perform_function_interval(val.function.unwrap(), &args)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+
+    use super::Interval;
+    use crate::{
+        function::{fast_function::FastFunction, function::Function},
+        math_tree::MathTree,
+    };
+
+    fn evaluate(expr: &str, x: Interval) -> Interval {
+        MathTree::parse(expr)
+            .unwrap()
+            .evaluate_interval(&HashMap::from([(String::from("x"), x)]))
+    }
+
+    #[test]
+    fn interval_arithmetic() {
+        let res = evaluate("x + 1", Interval::new(1.0, 2.0));
+        assert!(res.contains(2.0) && res.contains(3.0) && res.width() < 1.0 + 1e-9);
+
+        // the even power never goes below 0
+        let res = evaluate("x^2", Interval::new(-2.0, 3.0));
+        assert!(res.lo == 0.0 && res.contains(9.0) && res.hi < 9.0 + 1e-9);
+        let res = evaluate("x^3", Interval::new(-2.0, 3.0));
+        assert!(res.contains(-8.0) && res.contains(27.0));
+
+        // through 0 goes to infinity
+        assert_eq!(
+            evaluate("1 / x", Interval::new(-1.0, 1.0)),
+            Interval::ENTIRE
+        );
+        assert_eq!(evaluate("1 / x", Interval::new(0.0, 1.0)).hi, f64::INFINITY);
+        assert!(evaluate("1 / x", Interval::new(0.0, 1.0)).contains(1.0));
+        assert!(evaluate("ln(x)", Interval::new(-2.0, -1.0)).is_empty());
+    }
+
+    #[test]
+    fn interval_functions() {
+        let res = evaluate("sin(x)", Interval::new(0.0, 3.0));
+        assert_eq!(res.hi, 1.0);
+        assert!(res.lo <= 0.0);
+        assert_eq!(
+            evaluate("cos(x)", Interval::new(-1.0, 7.0)),
+            Interval::new(-1.0, 1.0)
+        );
+        assert_eq!(
+            evaluate("tan(x)", Interval::new(1.0, 2.0)),
+            Interval::ENTIRE
+        );
+        assert_eq!(
+            evaluate("abs(x)", Interval::new(-3.0, 2.0)),
+            Interval::new(0.0, 3.0)
+        );
+        assert!(evaluate("sqrt(x)", Interval::new(4.0, 9.0)).contains(3.0));
+        assert!(evaluate("e^x", Interval::new(0.0, 1.0)).contains(std::f64::consts::E));
+
+        // y is within its bound
+        let mut tree = MathTree::parse("y * 2").unwrap();
+        tree.restrict("y", Interval::new(1.0, 2.0));
+        let res = tree.evaluate_interval(&HashMap::new());
+        assert!(res.contains(2.0) && res.contains(4.0) && res.width() < 2.0 + 1e-9);
+    }
+
+    #[test]
+    fn interval_columns() {
+        let fx = FastFunction::from(&Function::from(MathTree::parse("1 / x").unwrap()).unwrap())
+            .unwrap();
+
+        // the spike at 0 is not missed
+        let columns = fx.evaluate_columns("x", -1.0, 1.0, 4);
+        assert_eq!(columns.len(), 4);
+        assert_eq!(columns[1].lo, f64::NEG_INFINITY);
+        assert_eq!(columns[2].hi, f64::INFINITY);
+        assert!(columns[0].contains(-1.0) && columns[0].contains(-2.0));
+    }
+}
@@ -1,110 +1,358 @@
-use itertools::Itertools;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    math_tree::{MathTree, TreeNodeRef},
-    MathTokenType, OperationToken,
+    lexer::Lexer,
+    math_tree::{MathTree, ParseError, TreeNodeRef},
+    number::{Number, NumberForm, NumberStyle},
+    number_theory::{factorint, write_factors},
+    render::{Emitter, TermEmitter},
+    FunctionToken, MathTokenType, OperationToken,
 };
 
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DivisionStyle {
+    // \frac{a}{b}
+    #[default]
+    Fraction,
+    // a/b
+    Slash,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MultiplicationStyle {
+    // 2 \cdot 5
+    #[default]
+    Cdot,
+    // 2 \times 5
+    Times,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatexOptions {
+    pub division: DivisionStyle,
+    // only written where juxtaposition is ambiguous: 2 \cdot 5, but 2x
+    pub multiplication: MultiplicationStyle,
+    // 0.125, \frac{1}{8}, 0.\overline{3}, 2\frac{1}{2}
+    pub numbers: NumberStyle,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl LatexOptions {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(division: DivisionStyle, multiplication: MultiplicationStyle) -> Self {
+        Self {
+            division,
+            multiplication,
+            numbers: NumberStyle::default(),
+        }
+    }
+
+    pub fn with_numbers(mut self, numbers: NumberStyle) -> Self {
+        self.numbers = numbers;
+        self
+    }
+}
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 impl MathTree {
     pub fn to_latex(&self) -> String {
-        TreeNodeRef::to_latex(&self.root)
+        self.root.to_latex()
+    }
+
+    pub fn to_latex_with(&self, options: LatexOptions) -> String {
+        self.root.to_latex_with(options)
+    }
+
+    // LaTeX input, see Lexer::from_latex
+    pub fn from_latex(str: &str) -> Result<MathTree, ParseError> {
+        Self::from_lexer(Lexer::from_latex(str)?, str.len())
     }
 }
 
 impl TreeNodeRef {
     pub fn to_latex(&self) -> String {
-        let mut res = String::new();
+        self.to_latex_with(LatexOptions::default())
+    }
+
+    pub fn to_latex_with(&self, options: LatexOptions) -> String {
+        LatexWriter { options }.write(self)
+    }
+}
+
+struct LatexWriter {
+    options: LatexOptions,
+}
+
+impl Emitter for LatexWriter {
+    type Output = String;
+
+    fn write(&self, node: &TreeNodeRef) -> String {
+        let val = node.val();
+        match val.kind {
+            MathTokenType::Constant => match val.unit {
+                // 5\,\mathrm{m}/\mathrm{s}
+                Some(unit) => format!(
+                    "{}\\,{}",
+                    self.constant(&val.constant.unwrap()),
+                    unit.to_latex()
+                ),
+                None => self.constant(&val.constant.unwrap()),
+            },
+            MathTokenType::Variable => {
+                let name = val.variable.unwrap();
+                match name.as_str() {
+                    "pi" | "π" => String::from("\\pi"),
+                    _ => name.to_string(),
+                }
+            }
+            MathTokenType::Function => self.function(node, val.function.unwrap()),
+            MathTokenType::Operator => self.operator(node, val.operation.unwrap()),
+        }
+    }
+
+    fn precedence(&self, node: &TreeNodeRef) -> i8 {
+        let val = node.val();
+        match val.operation {
+            // roots are already grouped by \sqrt{}
+            Some(op) if op != OperationToken::Root => op.info().precedence,
+            None if val.unit.is_some() || factorint(node).is_some() => {
+                OperationToken::Multiply.info().precedence
+            }
+            None => val
+                .constant
+                .as_ref()
+                .map_or(i8::MAX, |c| self.constant_precedence(c)),
+            _ => i8::MAX,
+        }
+    }
 
-        let upper_precedence = if let Some(op) = self.val().operation {
-            op.info().precedence
+    fn group(&self, res: String) -> String {
+        // fractions are taller than a line, size the parentheses to them
+        if res.contains("\\frac") {
+            format!("\\left({}\\right)", res)
         } else {
-            0
-        };
+            format!("({})", res)
+        }
+    }
 
-        MathTree::to_latex_node(self.clone(), &mut res, upper_precedence);
-        res
+    // x - (-2), 2(-x)
+    fn leads_with_minus(&self, _: &TreeNodeRef, res: &String) -> bool {
+        res.starts_with('-')
     }
 }
 
-impl MathTree {
-    pub fn to_latex_node(node: TreeNodeRef, res: &mut String, precedence: i8) {
-        let borrow = node.borrow();
+impl TermEmitter for LatexWriter {
+    // in the options' NumberStyle, by default exact fractions as a division: \frac{1}{3},
+    // decimals when they terminate: 0.5
+    fn constant(&self, c: &Number) -> String {
+        match self.options.numbers.form(c) {
+            NumberForm::Decimal => c.approximate().to_string(),
+            NumberForm::Repeating(start, period) => format!("{}\\overline{{{}}}", start, period),
+            NumberForm::Fraction => self.fraction(c),
+            // 2\frac{1}{2}, 2\,1/2
+            NumberForm::Mixed(whole, fraction) => match self.options.division {
+                DivisionStyle::Fraction => format!("{}{}", whole, self.fraction(&fraction)),
+                DivisionStyle::Slash => format!("{}\\,{}", whole, self.fraction(&fraction)),
+            },
+        }
+    }
 
-        let mut childs = borrow.display_iter();
-        // Self::token_to_latex(childs.next().unwrap().1, res);
-        let Some(operator) = node.val().operation else {
-            Self::token_to_latex(&node, res, precedence);
-            return;
-        };
+    // 1/3 as a division, 2\frac{1}{2} as a sum
+    fn constant_precedence(&self, c: &Number) -> i8 {
+        match self.options.numbers.form(c) {
+            NumberForm::Fraction if self.options.division == DivisionStyle::Slash => {
+                OperationToken::Divide.info().precedence
+            }
+            NumberForm::Mixed(..) => OperationToken::Add.info().precedence,
+            _ => i8::MAX,
+        }
+    }
 
-        let multiply = operator == OperationToken::Multiply;
+    fn symbol(&self, op: OperationToken) -> String {
+        op.to_char().to_string()
+    }
 
-        // first childs wihtout op
-        let (_, child1) = childs.next().unwrap();
-        Self::token_to_latex(child1, res, precedence);
-        let last_child = child1;
+    fn minus(&self) -> String {
+        String::from("-")
+    }
 
-        for (_, child1) in childs {
-            // multiply anything except (constant and constant) requires no sign
-            if !multiply
-                || (last_child.val().kind == MathTokenType::Constant
-                    && child1.val().kind == MathTokenType::Constant)
-            {
-                res.push(node.val().operation.unwrap().to_char());
-            }
+    // only where juxtaposition is ambiguous: 2 \cdot 5, but 2x
+    fn times(
+        &self,
+        before: &String,
+        _: Option<&Number>,
+        factor: &TreeNodeRef,
+        written: &String,
+    ) -> Option<String> {
+        // 25 is not 2 * 5, 2\frac{1}{2} reads as a mixed number
+        let ambiguous = factor.val().kind == MathTokenType::Constant
+            || written.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+            || written.starts_with("\\frac");
+        if ambiguous {
+            Some(String::from(match self.options.multiplication {
+                MultiplicationStyle::Cdot => " \\cdot ",
+                MultiplicationStyle::Times => " \\times ",
+            }))
+        } else if ends_with_command(before) && written.starts_with(char::is_alphabetic) {
+            // \pi r, not \pir
+            Some(String::from(" "))
+        } else {
+            None
+        }
+    }
 
-            Self::token_to_latex(child1, res, precedence);
+    fn join(&self, parts: Vec<String>) -> String {
+        parts.concat()
+    }
+}
+
+impl LatexWriter {
+    fn fraction(&self, c: &Number) -> String {
+        let sign = if c.is_sign_negative() { "-" } else { "" };
+        let (numer, denom) = (c.numer().magnitude(), c.denom());
+        match self.options.division {
+            DivisionStyle::Fraction => format!("{}\\frac{{{}}}{{{}}}", sign, numer, denom),
+            DivisionStyle::Slash => format!("{}{}/{}", sign, numer, denom),
         }
     }
 
-    fn token_to_latex(child: &TreeNodeRef, res: &mut String, upper_precedence: i8) {
-        let val = child.val();
-        match val.kind {
-            MathTokenType::Constant => {
-                let cnst = &val.constant.unwrap();
-                // if cnst.is_sign_negative() {
-                //     res.push('(');
-                    res.push_str(&cnst.to_string());
-                //     res.push(')');
-                // } else {
-                //     res.push_str(&cnst.to_string());
-                // }
+    fn operator(&self, node: &TreeNodeRef, op: OperationToken) -> String {
+        let borrow = node.borrow();
+        let childs = borrow
+            .display_iter()
+            .map(|(_, n)| n.clone())
+            .collect::<Vec<_>>();
+        let precedence = op.info().precedence;
+
+        match op {
+            OperationToken::Add => self.sum(&childs),
+            OperationToken::Subtract => {
+                format!(
+                    "{}-{}",
+                    self.write(&childs[0]),
+                    self.operand(&childs[1], precedence, true)
+                )
             }
-            MathTokenType::Variable => res.push_str(&val.variable.unwrap()),
-            MathTokenType::Operator => {
-                let op = val.operation.unwrap();
-                let precedence = op.info().precedence;
-                let parenthesize = precedence <= upper_precedence;
-
-                if parenthesize {
-                    res.push('(');
+            OperationToken::Multiply => self.multiply(&childs),
+            OperationToken::Divide => match self.options.division {
+                DivisionStyle::Fraction => format!(
+                    "\\frac{{{}}}{{{}}}",
+                    self.write(&childs[0]),
+                    self.write(&childs[1])
+                ),
+                DivisionStyle::Slash => format!(
+                    "{}/{}",
+                    self.operand(&childs[0], precedence, false),
+                    self.operand(&childs[1], precedence, true)
+                ),
+            },
+            OperationToken::Pow => {
+                let base = self.write(&childs[0]);
+                // (x+1)^{2}, (-2)^{2}, (x^{2})^{3}, \left(\frac{1}{2}\right)^{2}
+                let val = childs[0].val();
+                let base = if (val.kind == MathTokenType::Operator
+                    && val.operation != Some(OperationToken::Root))
+                    || val.constant.as_ref().is_some_and(|c| {
+                        matches!(
+                            self.options.numbers.form(c),
+                            NumberForm::Fraction | NumberForm::Mixed(..)
+                        )
+                    })
+                    || factorint(&childs[0]).is_some()
+                    || base.starts_with('-')
+                {
+                    self.group(base)
+                } else {
+                    base
+                };
+
+                format!("{}^{{{}}}", base, self.write(&childs[1]))
+            }
+            OperationToken::Root => {
+                let mut res = String::from("\\sqrt");
+                // square root index is implied
+                if childs[1].val().constant != Some(Number::two()) {
+                    res.push_str(&format!("[{}]", self.write(&childs[1])));
                 }
+                res.push_str(&format!("{{{}}}", self.write(&childs[0])));
+                res
+            }
+            OperationToken::LParent | OperationToken::RParent | OperationToken::Comma => {
+                unreachable!()
+            }
+        }
+    }
 
-                Self::to_latex_node(child.clone(), res, precedence);
+    fn function(&self, node: &TreeNodeRef, f: FunctionToken) -> String {
+        // 2^{3}\cdot 3^{2}\cdot 5
+        if let Some(factors) = factorint(node) {
+            return write_factors(&factors, "\\cdot ", |p, k| format!("{}^{{{}}}", p, k));
+        }
+
+        let borrow = node.borrow();
+        let mut args = borrow.calculate_iter().map(|x| x.1);
+        let arg = self.write(args.next().unwrap());
 
-                if parenthesize {
-                    res.push(')');
+        let name = match f {
+            FunctionToken::Abs => return format!("\\left|{}\\right|", arg),
+            FunctionToken::Conj => return format!("\\overline{{{}}}", arg),
+            FunctionToken::Re | FunctionToken::Im | FunctionToken::Factorint => {
+                format!("\\operatorname{{{}}}", f.name())
+            }
+            // \gcd(12, 18)
+            FunctionToken::Gcd | FunctionToken::Lcm => {
+                let rest = args.map(|a| self.write(a)).collect::<Vec<_>>();
+                let name = match f {
+                    FunctionToken::Gcd => String::from("\\gcd"),
+                    _ => format!("\\operatorname{{{}}}", f.name()),
+                };
+                return format!("{}({}, {})", name, arg, rest.join(", "));
+            }
+            FunctionToken::Log => {
+                let base = args.next().unwrap();
+                // base 10 is implied
+                if base.val().constant == Some(Number::ten()) {
+                    String::from("\\log")
+                } else {
+                    format!("\\log_{{{}}}", self.write(base))
                 }
             }
-        }
+            _ => format!("\\{}", f.name()),
+        };
+
+        format!("{}{}", name, self.group(arg))
     }
 }
 
+// \pi, \cdot: a letter written right after would extend the command name
+fn ends_with_command(res: &str) -> bool {
+    let name = res.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    name.len() < res.len() && name.ends_with('\\')
+}
+
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
 
-    use crate::math_tree::MathTree;
+    use super::{DivisionStyle, LatexOptions, MultiplicationStyle};
+    use crate::{
+        lexer::{ParseOptions, VariablePolicy},
+        math_tree::{MathTree, ParseErrorKind, TreeNodeRef},
+        number::{Number, NumberStyle},
+    };
 
     #[test]
     pub fn simple_latex() {
         assert_eq!(MathTree::parse("x").unwrap().to_latex(), "x");
 
         assert_eq!(MathTree::parse("2 * x").unwrap().to_latex(), "2x");
-        // assert_eq!(MathTree::parse("-2 * x").unwrap().to_latex(), "-2x");
 
         assert_eq!(MathTree::parse("2 * (x + 1)").unwrap().to_latex(), "2(x+1)");
 
@@ -122,14 +370,212 @@ mod tests {
 
         assert_eq!(
             MathTree::parse("1 + 5 + 2 * 5 + 3 + 1").unwrap().to_latex(),
-            "2*5+1+5+3+1"
+            "1+5+2 \\cdot 5+3+1"
         );
 
         assert_eq!(
             MathTree::parse("2 * 5 * 3 + 1 * 2 + 3").unwrap().to_latex(),
-            "2*5*3+1*2+3"
+            "2 \\cdot 5 \\cdot 3+1 \\cdot 2+3"
+        );
+
+        assert_eq!(MathTree::parse("e^(x^2)").unwrap().to_latex(), "e^{x^{2}}");
+        assert_eq!(
+            MathTree::parse("(x + 1)^(2 * x)").unwrap().to_latex(),
+            "(x+1)^{2x}"
+        );
+        assert_eq!(MathTree::parse("2 * pi * r").unwrap().to_latex(), "2\\pi r");
+        assert_eq!(
+            MathTree::parse("x - (y - 1)").unwrap().to_latex(),
+            "x-(y-1)"
+        );
+    }
+
+    #[test]
+    pub fn division_latex() {
+        assert_eq!(
+            MathTree::parse("(x + 1) / 2").unwrap().to_latex(),
+            "\\frac{x+1}{2}"
+        );
+        assert_eq!(
+            MathTree::parse("3 * (1 / x)").unwrap().to_latex(),
+            "3 \\cdot \\frac{1}{x}"
+        );
+        assert_eq!(
+            MathTree::parse("(1 / x + 1)^2").unwrap().to_latex(),
+            "\\left(\\frac{1}{x}+1\\right)^{2}"
+        );
+
+        let slash = LatexOptions::new(DivisionStyle::Slash, MultiplicationStyle::Times);
+        assert_eq!(
+            MathTree::parse("(x + 1) / (2 * 3)")
+                .unwrap()
+                .to_latex_with(slash),
+            "(x+1)/(2 \\times 3)"
+        );
+        assert_eq!(
+            MathTree::parse("x / y / z").unwrap().to_latex_with(slash),
+            "x/y/z"
+        );
+    }
+
+    #[test]
+    pub fn number_style_latex() {
+        let style = |numbers| LatexOptions::default().with_numbers(numbers);
+        let latex =
+            |txt: &str, numbers| MathTree::parse(txt).unwrap().to_latex_with(style(numbers));
+        let five_halves = || TreeNodeRef::constant(Number::new(5, 2));
+
+        assert_eq!(latex("0.125", NumberStyle::Auto), "0.125");
+        assert_eq!(latex("0.125", NumberStyle::Fraction), "\\frac{1}{8}");
+        assert_eq!(latex("0.333...", NumberStyle::Decimal), "0.\\overline{3}");
+        assert_eq!(latex("0.1666...", NumberStyle::Decimal), "0.1\\overline{6}");
+        assert_eq!(
+            five_halves().to_latex_with(style(NumberStyle::Mixed)),
+            "2\\frac{1}{2}"
+        );
+        assert_eq!(
+            five_halves()
+                .multiply(TreeNodeRef::var("x"))
+                .to_latex_with(style(NumberStyle::Mixed)),
+            "\\left(2\\frac{1}{2}\\right)x"
+        );
+    }
+
+    #[test]
+    pub fn negative_latex() {
+        let x = || TreeNodeRef::parse("x");
+        let neg = |c| TreeNodeRef::constant(c);
+
+        assert_eq!(neg(dec!(-2)).to_latex(), "-2");
+        assert_eq!(x().add(neg(dec!(-2))).to_latex(), "x-2");
+        assert_eq!(x().subtract(neg(dec!(-2))).to_latex(), "x-(-2)");
+        assert_eq!(neg(dec!(-2)).multiply(x()).to_latex(), "-2x");
+        assert_eq!(neg(dec!(-1)).multiply(x()).to_latex(), "-x");
+        assert_eq!(
+            TreeNodeRef::parse("y")
+                .add(neg(dec!(-3)).multiply(x()))
+                .to_latex(),
+            "y-3x"
+        );
+        assert_eq!(
+            TreeNodeRef::parse("y")
+                .add(neg(dec!(-1)).multiply(x()))
+                .to_latex(),
+            "y-x"
+        );
+        assert_eq!(neg(dec!(-2)).pow(TreeNodeRef::two()).to_latex(), "(-2)^{2}");
+        assert_eq!(
+            TreeNodeRef::two().multiply(neg(dec!(-3))).to_latex(),
+            "2 \\cdot (-3)"
         );
+    }
 
-        assert_eq!(MathTree::parse("e^(x^2)").unwrap().to_latex(), "e^(x^2)");
+    #[test]
+    pub fn function_latex() {
+        assert_eq!(
+            MathTree::parse("2 * sin(x + 1)").unwrap().to_latex(),
+            "2\\sin(x+1)"
+        );
+        assert_eq!(MathTree::parse("log(x)").unwrap().to_latex(), "\\log(x)");
+        assert_eq!(
+            MathTree::parse("log(x, 2)").unwrap().to_latex(),
+            "\\log_{2}(x)"
+        );
+        assert_eq!(
+            MathTree::parse("sqrt(x^2 + 1)").unwrap().to_latex(),
+            "\\sqrt{x^{2}+1}"
+        );
+        assert_eq!(
+            MathTree::parse("root(x, 3)^2").unwrap().to_latex(),
+            "\\sqrt[3]{x}^{2}"
+        );
+        assert_eq!(
+            MathTree::parse("abs(x)").unwrap().to_latex(),
+            "\\left|x\\right|"
+        );
+        assert_eq!(
+            MathTree::parse("ln(1 / x)").unwrap().to_latex(),
+            "\\ln\\left(\\frac{1}{x}\\right)"
+        );
+    }
+
+    #[test]
+    pub fn from_latex() {
+        for (latex, txt) in [
+            ("2x", "2 * x"),
+            ("\\frac{x+1}{2}", "(x + 1) / 2"),
+            ("\\frac12", "1 / 2"),
+            ("x^{2}+2x+1", "x^2 + 2 * x + 1"),
+            ("e^{x^{2}}", "e^(x^2)"),
+            ("2 \\cdot 5", "2 * 5"),
+            ("3\\times 4", "3 * 4"),
+            ("\\left(x+1\\right)(x-1)", "(x + 1) * (x - 1)"),
+            ("2\\pi r", "2 * pi * r"),
+            ("\\sqrt{x}", "sqrt(x)"),
+            ("\\sqrt[3]{x+1}", "root(x + 1, 3)"),
+            ("\\sin x + \\cos(x)", "sin(x) + cos(x)"),
+            ("\\ln\\left(\\frac{1}{x}\\right)", "ln(1 / x)"),
+            ("\\log_{2}(x)", "log(x, 2)"),
+            ("\\log_2 8", "log(8, 2)"),
+            ("\\operatorname{abs}(x)", "abs(x)"),
+            ("\\left|x-1\\right|", "abs(x - 1)"),
+            ("|x|+|y|", "abs(x) + abs(y)"),
+            ("2|x|", "2 * abs(x)"),
+            ("2\\left|x\\right|", "2 * abs(x)"),
+            ("a|b|c", "a * abs(b) * c"),
+            ("|a|b|c|", "abs(a) * b * abs(c)"),
+            ("||x|-1|", "abs(abs(x) - 1)"),
+            ("-x^2", "-x^2"),
+        ] {
+            assert_eq!(
+                MathTree::from_latex(latex).unwrap().root,
+                MathTree::parse(txt).unwrap().root,
+                "{}",
+                latex
+            );
+        }
+    }
+
+    #[test]
+    pub fn latex_round_trip() {
+        let single = ParseOptions::new(VariablePolicy::SingleLetter, true);
+        for txt in [
+            "2 * x",
+            "2 * (x + 1)",
+            "(x + 1) * (x - 1)",
+            "2 * x * y + 1",
+            "e^(x^2)",
+            "2 * pi * r",
+            "(x + 1) / (x - 1)",
+            "sqrt(x^2 + 1) + root(x, 3)",
+            "log(x, 2) * sin(x)",
+        ] {
+            let tree = MathTree::parse_with(txt, single).unwrap();
+            assert_eq!(
+                MathTree::from_latex(&tree.to_latex()).unwrap().root,
+                tree.root
+            );
+        }
+    }
+
+    #[test]
+    pub fn from_latex_errors() {
+        let error = |latex: &str| {
+            let err = MathTree::from_latex(latex).unwrap_err();
+            (err.kind, err.span.start, err.span.end)
+        };
+
+        assert_eq!(
+            error("\\frac{x}{2"),
+            (ParseErrorKind::ParenthesesMismatch, 8, 9)
+        );
+        assert_eq!(error("x + \\foo"), (ParseErrorKind::UnexpectedChar, 4, 8));
+        assert_eq!(
+            error("\\operatorname{foo}(x)"),
+            (ParseErrorKind::UnexpectedChar, 0, 18)
+        );
+        assert_eq!(error("\\frac{x}"), (ParseErrorKind::DanglingOperator, 0, 5));
+        assert_eq!(error("2 \\cdot"), (ParseErrorKind::DanglingOperator, 2, 7));
+        assert_eq!(error(""), (ParseErrorKind::EmptyInput, 0, 0));
     }
 }
@@ -0,0 +1,447 @@
+use std::{collections::HashMap, iter::Peekable, rc::Rc, str::CharIndices, str::FromStr};
+
+use crate::{
+    lexer::{ends_operand, Lexer, Span},
+    math_tree::{ParseError, ParseErrorKind},
+    number::Number,
+    FunctionToken, MathToken, OperationToken,
+};
+
+type Tokens = Vec<(MathToken, Span)>;
+
+// commands written as a variable of the same name
+const GREEK: [&str; 12] = [
+    "alpha", "beta", "gamma", "delta", "epsilon", "theta", "lambda", "mu", "pi", "sigma", "phi",
+    "omega",
+];
+
+impl Lexer {
+    // LaTeX as written by KaTeX/MathLive, lexed into the same tokens as the infix syntax:
+    // \frac{a}{b} is ((a)/(b)), x^{2} is x^(2), \sqrt[3]{x} is root((x), 3).
+    // every letter is its own variable (xy is x * y) and multiplication is implied
+    pub fn from_latex(str: &str) -> Result<Self, ParseError> {
+        let mut lexer = LatexLexer {
+            str,
+            chars: str.char_indices().peekable(),
+            variables: HashMap::new(),
+            suffixes: Vec::new(),
+            next_suffix: None,
+            bars: 0,
+        };
+
+        let (tokens, _) = lexer.read(None, Span::new(0, 0))?;
+        let (tokens, spans) = tokens.into_iter().unzip();
+
+        Ok(Self { tokens, spans }.finish(true))
+    }
+}
+
+struct LatexLexer<'a> {
+    str: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    variables: HashMap<String, Rc<String>>,
+    // for every open parenthesis, the tokens written before its closing one: the base of \log_2(x)
+    suffixes: Vec<Option<Tokens>>,
+    // suffix of the next open parenthesis
+    next_suffix: Option<Tokens>,
+    // |x| groups opened and not yet closed
+    bars: usize,
+}
+
+impl<'a> LatexLexer<'a> {
+    // reads until close (consumed) or the end of the input when close is None,
+    // returns the tokens and where the read stopped
+    fn read(&mut self, close: Option<char>, open: Span) -> Result<(Tokens, usize), ParseError> {
+        let mut out = Tokens::new();
+
+        loop {
+            self.skip_spaces();
+            let Some((i, c)) = self.chars.next() else {
+                return match close {
+                    Some(_) => Err(ParseError::new(ParseErrorKind::ParenthesesMismatch, open)),
+                    None => Ok((out, self.str.len())),
+                };
+            };
+
+            if Some(c) == close {
+                return Ok((out, i + c.len_utf8()));
+            }
+            self.token(i, c, &mut out)?;
+        }
+    }
+
+    fn token(&mut self, i: usize, c: char, out: &mut Tokens) -> Result<(), ParseError> {
+        let span = Span::new(i, i + c.len_utf8());
+        match c {
+            c if c.is_ascii_digit() => {
+                let mut stop = span.end;
+                while let Some((j, c)) =
+                    self.chars.next_if(|(_, c)| c.is_ascii_digit() || *c == '.')
+                {
+                    stop = j + c.len_utf8();
+                }
+                out.push((self.number(Span::new(i, stop))?, Span::new(i, stop)));
+            }
+            c if c.is_alphabetic() => self.variable(c.to_string(), span, out)?,
+            '{' => {
+                let (group, stop) = self.read(Some('}'), span)?;
+                Self::parenthesized(group, Span::new(i, stop), out);
+            }
+            '^' => {
+                out.push((MathToken::operator(OperationToken::Pow), span));
+                let (arg, arg_span) = self.argument(span)?;
+                Self::parenthesized(arg, arg_span, out);
+            }
+            '(' | '[' => self.open(span, out),
+            ')' | ']' => self.close(span, out),
+            // |x| closes after an operand when one is open, and opens otherwise: 2|x|
+            '|' => {
+                if self.bars > 0 && out.last().is_some_and(|(t, _)| ends_operand(t)) {
+                    self.bars -= 1;
+                    self.close(span, out);
+                } else {
+                    self.bars += 1;
+                    out.push((MathToken::function(FunctionToken::Abs), span));
+                    self.open(span, out);
+                }
+            }
+            '\\' => self.command(i, out)?,
+            '}' => return Err(ParseError::new(ParseErrorKind::ParenthesesMismatch, span)),
+            _ => match OperationToken::from_char(c) {
+                Some(op) => out.push((MathToken::operator(op), span)),
+                None => return Err(ParseError::new(ParseErrorKind::UnexpectedChar, span)),
+            },
+        }
+
+        Ok(())
+    }
+
+    // \name, starting at the backslash
+    fn command(&mut self, i: usize, out: &mut Tokens) -> Result<(), ParseError> {
+        let (name, span) = self.command_name(i);
+
+        match name {
+            "frac" | "dfrac" | "tfrac" => {
+                out.push((MathToken::operator(OperationToken::LParent), span));
+                let (num, num_span) = self.argument(span)?;
+                Self::parenthesized(num, num_span, out);
+
+                let (den, den_span) = self.argument(span)?;
+                let at = Span::new(den_span.start, den_span.start);
+                out.push((MathToken::operator(OperationToken::Divide), at));
+                Self::parenthesized(den, den_span, out);
+
+                let end = Span::new(den_span.end, den_span.end);
+                out.push((MathToken::operator(OperationToken::RParent), end));
+            }
+            "sqrt" => {
+                self.skip_spaces();
+                // \sqrt[3]{x}
+                let index = match self.chars.next_if(|(_, c)| *c == '[') {
+                    Some((j, _)) => Some(self.read(Some(']'), Span::new(j, j + 1))?),
+                    None => None,
+                };
+
+                let (radicand, radicand_span) = self.argument(span)?;
+                let f = match index {
+                    Some(_) => FunctionToken::Root,
+                    None => FunctionToken::Sqrt,
+                };
+
+                out.push((MathToken::function(f), span));
+                out.push((MathToken::operator(OperationToken::LParent), span));
+                Self::parenthesized(radicand, radicand_span, out);
+                let end = Span::new(radicand_span.end, radicand_span.end);
+                if let Some((index, _)) = index {
+                    out.push((MathToken::operator(OperationToken::Comma), end));
+                    out.extend(index);
+                }
+                out.push((MathToken::operator(OperationToken::RParent), end));
+            }
+            // conjugate: \overline{z}
+            "overline" => {
+                let (arg, arg_span) = self.argument(span)?;
+                out.push((MathToken::function(FunctionToken::Conj), span));
+                out.push((MathToken::operator(OperationToken::LParent), span));
+                Self::parenthesized(arg, arg_span, out);
+                let end = Span::new(arg_span.end, arg_span.end);
+                out.push((MathToken::operator(OperationToken::RParent), end));
+            }
+            "cdot" | "times" | "ast" => {
+                out.push((MathToken::operator(OperationToken::Multiply), span))
+            }
+            "div" => out.push((MathToken::operator(OperationToken::Divide), span)),
+            "left" | "right" => self.delimiter(name == "left", out)?,
+            // spacing
+            "," | ";" | ":" | "!" | " " | "quad" | "qquad" => {}
+            "operatorname" => {
+                let (name, name_span) = self.raw_argument(span)?;
+                match FunctionToken::from_name(name) {
+                    Some(f) => self.function(f, span.join(name_span), out)?,
+                    None => {
+                        return Err(ParseError::new(
+                            ParseErrorKind::UnexpectedChar,
+                            span.join(name_span),
+                        ))
+                    }
+                }
+            }
+            name if GREEK.contains(&name) => self.variable(name.to_string(), span, out)?,
+            name => match FunctionToken::from_name(name) {
+                Some(f) => self.function(f, span, out)?,
+                None => return Err(ParseError::new(ParseErrorKind::UnexpectedChar, span)),
+            },
+        }
+
+        Ok(())
+    }
+
+    // a function call, the base of \log_{2} goes last: log(x, 2)
+    fn function(
+        &mut self,
+        f: FunctionToken,
+        span: Span,
+        out: &mut Tokens,
+    ) -> Result<(), ParseError> {
+        out.push((MathToken::function(f), span));
+
+        self.skip_spaces();
+        if self.chars.next_if(|(_, c)| *c == '_').is_none() {
+            return Ok(());
+        }
+        let (base, base_span) = self.argument(span)?;
+        let mut suffix = vec![(
+            MathToken::operator(OperationToken::Comma),
+            Span::new(base_span.start, base_span.start),
+        )];
+        suffix.extend(base);
+
+        self.skip_spaces();
+        let rest = self.chars.peek().map_or("", |(j, _)| &self.str[*j..]);
+        if rest.starts_with('(') || rest.starts_with("\\left") {
+            // added before the parenthesis closes
+            self.next_suffix = Some(suffix);
+        } else {
+            // \log_2 x
+            let (arg, arg_span) = self.argument(span)?;
+            let end = Span::new(arg_span.end, arg_span.end);
+            out.push((MathToken::operator(OperationToken::LParent), arg_span));
+            out.extend(arg);
+            out.extend(suffix);
+            out.push((MathToken::operator(OperationToken::RParent), end));
+        }
+
+        Ok(())
+    }
+
+    // what follows \left or \right
+    fn delimiter(&mut self, left: bool, out: &mut Tokens) -> Result<(), ParseError> {
+        self.skip_spaces();
+        let Some((i, c)) = self.chars.next() else {
+            return Err(ParseError::new(
+                ParseErrorKind::ParenthesesMismatch,
+                Span::new(self.str.len(), self.str.len()),
+            ));
+        };
+
+        let (name, span) = match c {
+            '\\' => self.command_name(i),
+            _ => (
+                &self.str[i..i + c.len_utf8()],
+                Span::new(i, i + c.len_utf8()),
+            ),
+        };
+
+        match (left, name) {
+            // \left. is an invisible delimiter
+            (_, ".") => {}
+            (true, "(" | "[" | "{" | "lbrace") => self.open(span, out),
+            (false, ")" | "]" | "}" | "rbrace") => self.close(span, out),
+            (true, "|" | "vert" | "lvert") => {
+                out.push((MathToken::function(FunctionToken::Abs), span));
+                self.open(span, out);
+            }
+            (false, "|" | "vert" | "rvert") => self.close(span, out),
+            _ => return Err(ParseError::new(ParseErrorKind::UnexpectedChar, span)),
+        }
+
+        Ok(())
+    }
+
+    fn open(&mut self, span: Span, out: &mut Tokens) {
+        out.push((MathToken::operator(OperationToken::LParent), span));
+        self.suffixes.push(self.next_suffix.take());
+    }
+
+    fn close(&mut self, span: Span, out: &mut Tokens) {
+        if let Some(Some(suffix)) = self.suffixes.pop() {
+            out.extend(suffix);
+        }
+        out.push((MathToken::operator(OperationToken::RParent), span));
+    }
+
+    // {group} or a single character: \frac12, x^2
+    fn argument(&mut self, command: Span) -> Result<(Tokens, Span), ParseError> {
+        self.skip_spaces();
+        let Some((i, c)) = self.chars.next() else {
+            return Err(ParseError::new(ParseErrorKind::DanglingOperator, command));
+        };
+
+        let span = Span::new(i, i + c.len_utf8());
+        let mut out = Tokens::new();
+        match c {
+            '{' => {
+                let (group, stop) = self.read(Some('}'), span)?;
+                return Ok((group, Span::new(i, stop)));
+            }
+            c if c.is_ascii_digit() => out.push((self.number(span)?, span)),
+            '\\' => {
+                self.command(i, &mut out)?;
+                let end = out.last().map_or(span.end, |(_, s)| s.end);
+                return Ok((out, Span::new(i, end)));
+            }
+            c if c.is_alphabetic() => self.variable(c.to_string(), span, &mut out)?,
+            _ => return Err(ParseError::new(ParseErrorKind::DanglingOperator, command)),
+        }
+
+        Ok((out, span))
+    }
+
+    // text of {group} or a single character, for names: \operatorname{sin}, x_{12}
+    fn raw_argument(&mut self, command: Span) -> Result<(&'a str, Span), ParseError> {
+        self.skip_spaces();
+        let Some((i, c)) = self.chars.next() else {
+            return Err(ParseError::new(ParseErrorKind::DanglingOperator, command));
+        };
+
+        if c != '{' {
+            return Ok((
+                &self.str[i..i + c.len_utf8()],
+                Span::new(i, i + c.len_utf8()),
+            ));
+        }
+
+        for (j, c) in self.chars.by_ref() {
+            if c == '}' {
+                return Ok((self.str[i + 1..j].trim(), Span::new(i, j + 1)));
+            }
+        }
+
+        Err(ParseError::new(
+            ParseErrorKind::ParenthesesMismatch,
+            Span::new(i, i + 1),
+        ))
+    }
+
+    // \frac, \, : letters, or the single character after the backslash
+    fn command_name(&mut self, i: usize) -> (&'a str, Span) {
+        let mut stop = i + 1;
+        while let Some((j, c)) = self.chars.next_if(|(_, c)| c.is_ascii_alphabetic()) {
+            stop = j + c.len_utf8();
+        }
+
+        if stop == i + 1 {
+            if let Some((j, c)) = self.chars.next() {
+                stop = j + c.len_utf8();
+            }
+        }
+
+        (&self.str[i + 1..stop], Span::new(i, stop))
+    }
+
+    // x, x_1, x_{12}
+    fn variable(
+        &mut self,
+        mut name: String,
+        span: Span,
+        out: &mut Tokens,
+    ) -> Result<(), ParseError> {
+        let mut span = span;
+        if self.chars.next_if(|(_, c)| *c == '_').is_some() {
+            let (sub, sub_span) = self.raw_argument(span)?;
+            name = format!("{}_{}", name, sub);
+            span = span.join(sub_span);
+        }
+
+        // avoid allocating same variable string twice
+        let rc = self
+            .variables
+            .entry(name)
+            .or_insert_with_key(|name| Rc::new(name.clone()))
+            .clone();
+        out.push((MathToken::variable(rc), span));
+        Ok(())
+    }
+
+    fn number(&self, span: Span) -> Result<MathToken, ParseError> {
+        match Number::from_str(&self.str[span.start..span.end]) {
+            Ok(d) => Ok(MathToken::constant(d)),
+            // 1.2.3
+            Err(_) => Err(ParseError::new(ParseErrorKind::MalformedNumber, span)),
+        }
+    }
+
+    // (tokens), read from span
+    fn parenthesized(tokens: Tokens, span: Span, out: &mut Tokens) {
+        out.push((
+            MathToken::operator(OperationToken::LParent),
+            Span::new(span.start, span.start),
+        ));
+        out.extend(tokens);
+        out.push((
+            MathToken::operator(OperationToken::RParent),
+            Span::new(span.end, span.end),
+        ));
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[test]
+    fn lex_latex() {
+        assert_eq!(
+            Lexer::from_latex("\\frac{x}{2}").unwrap().tokens,
+            vec![
+                MathToken::operator(OperationToken::LParent),
+                MathToken::operator(OperationToken::LParent),
+                MathToken::variable("x".to_string().into()),
+                MathToken::operator(OperationToken::RParent),
+                MathToken::operator(OperationToken::Divide),
+                MathToken::operator(OperationToken::LParent),
+                MathToken::constant(dec!(2)),
+                MathToken::operator(OperationToken::RParent),
+                MathToken::operator(OperationToken::RParent),
+            ]
+        );
+
+        assert_eq!(
+            Lexer::from_latex("\\log_2 x").unwrap().tokens,
+            vec![
+                MathToken::function(FunctionToken::Log),
+                MathToken::operator(OperationToken::LParent),
+                MathToken::variable("x".to_string().into()),
+                MathToken::operator(OperationToken::Comma),
+                MathToken::constant(dec!(2)),
+                MathToken::operator(OperationToken::RParent),
+            ]
+        );
+
+        assert_eq!(
+            Lexer::from_latex("x_{1}y").unwrap().tokens,
+            vec![
+                MathToken::variable("x_1".to_string().into()),
+                MathToken::operator(OperationToken::Multiply),
+                MathToken::variable("y".to_string().into()),
+            ]
+        );
+    }
+}
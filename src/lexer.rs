@@ -1,60 +1,320 @@
 use std::{collections::HashMap, rc::Rc, str::FromStr};
 
-use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
 
-use crate::{MathToken, OperationToken};
+use crate::{
+    constants::constant_names,
+    math_tree::{ParseError, ParseErrorKind},
+    number::Number,
+    units::Unit,
+    FunctionToken, MathToken, MathTokenType, OperationToken, FUNCTIONS,
+};
+
+// byte offsets into the parsed string, end exclusive
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Span {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    // smallest span covering both
+    pub fn join(&self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+// how a run of letters such as `xy` is read
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VariablePolicy {
+    // xy is one variable named "xy"
+    #[default]
+    MultiLetter,
+    // xy is x * y, known names like pi are kept whole, digits stay with their letter (x1)
+    SingleLetter,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseOptions {
+    pub variables: VariablePolicy,
+    // 2x, 3(x+1), (x+1)(x-1), 2pi
+    pub implicit_multiplication: bool,
+    // 5 m/s reads m and s as units instead of variables
+    pub units: bool,
+    // 0.1(6) is 1/6 instead of 0.1 * 6, 0.1666... repeats either way
+    pub repeating_decimals: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            variables: VariablePolicy::default(),
+            implicit_multiplication: true,
+            units: false,
+            repeating_decimals: false,
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl ParseOptions {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(variables: VariablePolicy, implicit_multiplication: bool) -> Self {
+        Self {
+            variables,
+            implicit_multiplication,
+            units: false,
+            repeating_decimals: false,
+        }
+    }
+
+    pub fn with_units(mut self, units: bool) -> Self {
+        self.units = units;
+        self
+    }
+
+    pub fn with_repeating_decimals(mut self, repeating_decimals: bool) -> Self {
+        self.repeating_decimals = repeating_decimals;
+        self
+    }
+}
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Lexer {
     pub(crate) tokens: Vec<MathToken>,
+    // spans[i] is where tokens[i] was read from
+    pub(crate) spans: Vec<Span>,
 }
 
 impl Lexer {
-    pub fn new(str: &str) -> Self {
+    pub fn new(str: &str) -> Result<Self, ParseError> {
+        Self::with_options(str, ParseOptions::default())
+    }
+
+    pub fn with_options(str: &str, options: ParseOptions) -> Result<Self, ParseError> {
         let mut chars = str.char_indices().peekable();
         let mut tokens = Vec::new();
+        let mut spans = Vec::new();
         let mut variables: HashMap<&str, Rc<String>> = HashMap::new();
 
         while let Some((i, c)) = chars.next() {
+            let mut str_stop = i + c.len_utf8();
             let token = match c {
                 c if c.is_whitespace() => continue,
                 c if c.is_ascii_digit() => {
-                    let mut str_stop = i + 1;
-                    while chars
-                        .next_if(|(_, c)| c.is_ascii_digit() || *c == '.')
-                        .is_some()
+                    // 0.333... and 0.333… repeat the last digits
+                    while let Some((j, c)) =
+                        chars.next_if(|(_, c)| c.is_ascii_digit() || *c == '.' || *c == '…')
                     {
-                        str_stop += 1;
+                        str_stop = j + c.len_utf8();
+                    }
+                    // 0.(3), 0.1(6): digits in parentheses right after a decimal repeat when
+                    // enabled, 2(3) is still a product
+                    if options.repeating_decimals && str[i..str_stop].contains('.') {
+                        if let Some(len) = Self::repeating_period(&str[str_stop..]) {
+                            for _ in 0..len {
+                                chars.next();
+                            }
+                            str_stop += len;
+                        }
+                    }
+
+                    match Number::from_str(&str[i..str_stop]) {
+                        Ok(d) => MathToken::constant(d),
+                        // 1.2.3
+                        Err(_) => {
+                            return Err(ParseError::new(
+                                ParseErrorKind::MalformedNumber,
+                                Span::new(i, str_stop),
+                            ))
+                        }
                     }
-                    MathToken::constant(Decimal::from_str(&str[i..str_stop]).unwrap())
                 }
                 c if c.is_alphabetic() => {
-                    let mut str_stop = i + 1;
-                    while chars.next_if(|(_, c)| c.is_alphanumeric()).is_some() {
-                        str_stop += 1;
+                    while let Some((j, c)) = chars.next_if(|(_, c)| c.is_alphanumeric()) {
+                        str_stop = j + c.len_utf8();
                     }
 
-                    let var = &str[i..str_stop];
-                    // avoid allocating same variable string twice
-                    MathToken::variable(match variables.get(var) {
-                        Some(v) => v.clone(),
-                        None => {
-                            let rc = Rc::new(var.to_string());
-                            let rc2 = rc.clone();
-                            variables.insert(var, rc);
-                            rc2
+                    // km is a single unit under either policy
+                    if let Some(unit) = options
+                        .units
+                        .then(|| Unit::from_symbol(&str[i..str_stop]))
+                        .flatten()
+                    {
+                        tokens.push(MathToken::quantity(1, unit));
+                        spans.push(Span::new(i, str_stop));
+                        continue;
+                    }
+
+                    let names = match options.variables {
+                        VariablePolicy::MultiLetter => vec![(i, &str[i..str_stop])],
+                        VariablePolicy::SingleLetter => Self::split_letters(str, i, str_stop),
+                    };
+
+                    for (start, var) in names {
+                        spans.push(Span::new(start, start + var.len()));
+                        if let Some(f) = FunctionToken::from_name(var) {
+                            tokens.push(MathToken::function(f));
+                            continue;
                         }
-                    })
+
+                        // avoid allocating same variable string twice
+                        tokens.push(MathToken::variable(match variables.get(var) {
+                            Some(v) => v.clone(),
+                            None => {
+                                let rc = Rc::new(var.to_string());
+                                let rc2 = rc.clone();
+                                variables.insert(var, rc);
+                                rc2
+                            }
+                        }));
+                    }
+                    continue;
                 }
+                '√' => MathToken::function(FunctionToken::Sqrt),
                 _ => match OperationToken::from_char(c) {
-                    Some(s) => MathToken::operator(s.clone()),
-                    None => panic!("Unhandled char {}", c),
+                    Some(s) => MathToken::operator(s),
+                    None => {
+                        return Err(ParseError::new(
+                            ParseErrorKind::UnexpectedChar,
+                            Span::new(i, str_stop),
+                        ))
+                    }
                 },
             };
             tokens.push(token);
+            spans.push(Span::new(i, str_stop));
+        }
+
+        Ok(Self { tokens, spans }.finish(options.implicit_multiplication))
+    }
+
+    // length of a (digits) block at the start of rest
+    fn repeating_period(rest: &str) -> Option<usize> {
+        let digits = rest.strip_prefix('(')?.split(')').next()?;
+        let closed = rest.len() > digits.len() + 1;
+        (closed && !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+            .then_some(digits.len() + 2)
+    }
+
+    // passes shared by every input syntax
+    pub(crate) fn finish(mut self, implicit_multiplication: bool) -> Self {
+        self.insert_call_parentheses();
+        if implicit_multiplication {
+            self.insert_implicit_multiplication();
+        }
+        self
+    }
+
+    // splits an identifier (str[start..stop]) into single letter variables,
+    // keeping known names (pi) and trailing digits (x1) whole
+    fn split_letters(str: &str, start: usize, stop: usize) -> Vec<(usize, &str)> {
+        let mut names = Vec::new();
+        let mut i = start;
+        let constants = constant_names();
+
+        while i < stop {
+            let rest = &str[i..stop];
+            let known = constants
+                .iter()
+                .map(|c| c.as_str())
+                .chain(FUNCTIONS.iter().map(|f| f.name()))
+                .filter(|name| rest.starts_with(name))
+                .max_by_key(|name| name.len());
+
+            let len = match known {
+                Some(name) => name.len(),
+                None => {
+                    let letter = rest.chars().next().unwrap().len_utf8();
+                    letter
+                        + rest[letter..]
+                            .find(|c: char| !c.is_ascii_digit())
+                            .unwrap_or(rest.len() - letter)
+                }
+            };
+
+            names.push((i, &str[i..i + len]));
+            i += len;
         }
 
-        Self { tokens }
+        names
+    }
+
+    // a function written before a single operand applies to it: sin x, sinx (single letters)
+    fn insert_call_parentheses(&mut self) {
+        let mut i = 1;
+        while i < self.tokens.len() {
+            let (a, b) = (&self.tokens[i - 1], &self.tokens[i]);
+
+            if a.kind == MathTokenType::Function
+                && matches!(b.kind, MathTokenType::Constant | MathTokenType::Variable)
+            {
+                let span = self.spans[i];
+                self.tokens
+                    .insert(i + 1, MathToken::operator(OperationToken::RParent));
+                self.spans.insert(i + 1, Span::new(span.end, span.end));
+                self.tokens
+                    .insert(i, MathToken::operator(OperationToken::LParent));
+                self.spans.insert(i, Span::new(span.start, span.start));
+                i += 2;
+            }
+            i += 1;
+        }
+    }
+
+    // a multiplication is implied between juxtaposed operands:
+    // 2x, 3(x+1), (x+1)(x-1), (x+1)2, 2pi, 2sin(x)
+    // but not between two numbers, 2 3 is most likely a typo
+    fn insert_implicit_multiplication(&mut self) {
+        let mut i = 1;
+        while i < self.tokens.len() {
+            let (a, b) = (&self.tokens[i - 1], &self.tokens[i]);
+
+            let b_opens = match b.kind {
+                // 5 m
+                MathTokenType::Constant => {
+                    a.operation == Some(OperationToken::RParent) || b.unit.is_some()
+                }
+                _ => starts_operand(b),
+            };
+
+            if ends_operand(a) && b_opens {
+                let at = self.spans[i].start;
+                self.tokens
+                    .insert(i, MathToken::operator(OperationToken::Multiply));
+                self.spans.insert(i, Span::new(at, at));
+                i += 1;
+            }
+            i += 1;
+        }
+    }
+}
+
+// whether an operand can end with this token: 2, x, )
+pub(crate) fn ends_operand(token: &MathToken) -> bool {
+    match token.kind {
+        MathTokenType::Constant | MathTokenType::Variable => true,
+        MathTokenType::Function => false,
+        MathTokenType::Operator => token.operation == Some(OperationToken::RParent),
+    }
+}
+
+// whether an operand can start with this token: 2, x, (, sin
+pub(crate) fn starts_operand(token: &MathToken) -> bool {
+    match token.kind {
+        MathTokenType::Constant | MathTokenType::Variable | MathTokenType::Function => true,
+        MathTokenType::Operator => token.operation == Some(OperationToken::LParent),
     }
 }
 
@@ -68,11 +328,11 @@ mod tests {
     #[test]
     fn lex() {
         assert_eq!(
-            Lexer::new("2 * x").tokens,
+            Lexer::new("2 * x").unwrap().tokens,
             vec![
                 MathToken::constant(dec!(2)),
                 MathToken::operator(OperationToken::Multiply),
-                MathToken::variable("x".to_string())
+                MathToken::variable("x".to_string().into())
             ]
         );
     }
@@ -80,7 +340,7 @@ mod tests {
     #[test]
     fn lex_parentheses() {
         assert_eq!(
-            Lexer::new("2 * (x + 1)").tokens,
+            Lexer::new("2 * (x + 1)").unwrap().tokens,
             vec![
                 MathToken::constant(dec!(2)),
                 MathToken::operator(OperationToken::Multiply),
@@ -92,4 +352,138 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn lex_implicit_multiplication() {
+        assert_eq!(
+            Lexer::new("2x(x + 1)").unwrap().tokens,
+            vec![
+                MathToken::constant(dec!(2)),
+                MathToken::operator(OperationToken::Multiply),
+                MathToken::variable("x".to_string().into()),
+                MathToken::operator(OperationToken::Multiply),
+                MathToken::operator(OperationToken::LParent),
+                MathToken::variable("x".to_string().into()),
+                MathToken::operator(OperationToken::Add),
+                MathToken::constant(dec!(1)),
+                MathToken::operator(OperationToken::RParent),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_variable_policy() {
+        let single = ParseOptions::new(VariablePolicy::SingleLetter, true);
+
+        assert_eq!(
+            Lexer::new("xy").unwrap().tokens,
+            vec![MathToken::variable("xy".to_string().into())]
+        );
+
+        assert_eq!(
+            Lexer::with_options("xy", single).unwrap().tokens,
+            vec![
+                MathToken::variable("x".to_string().into()),
+                MathToken::operator(OperationToken::Multiply),
+                MathToken::variable("y".to_string().into()),
+            ]
+        );
+
+        assert_eq!(
+            Lexer::with_options("2pix1", single).unwrap().tokens,
+            vec![
+                MathToken::constant(dec!(2)),
+                MathToken::operator(OperationToken::Multiply),
+                MathToken::variable("pi".to_string().into()),
+                MathToken::operator(OperationToken::Multiply),
+                MathToken::variable("x1".to_string().into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_functions() {
+        assert_eq!(
+            Lexer::new("log(x, 2)").unwrap().tokens,
+            vec![
+                MathToken::function(FunctionToken::Log),
+                MathToken::operator(OperationToken::LParent),
+                MathToken::variable("x".to_string().into()),
+                MathToken::operator(OperationToken::Comma),
+                MathToken::constant(dec!(2)),
+                MathToken::operator(OperationToken::RParent),
+            ]
+        );
+
+        let single = ParseOptions::new(VariablePolicy::SingleLetter, true);
+        assert_eq!(
+            Lexer::with_options("2sinx", single).unwrap().tokens,
+            vec![
+                MathToken::constant(dec!(2)),
+                MathToken::operator(OperationToken::Multiply),
+                MathToken::function(FunctionToken::Sin),
+                MathToken::operator(OperationToken::LParent),
+                MathToken::variable("x".to_string().into()),
+                MathToken::operator(OperationToken::RParent),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_spans() {
+        let lexer = Lexer::new("π + 12.5").unwrap();
+        assert_eq!(
+            lexer.spans,
+            vec![Span::new(0, 2), Span::new(3, 4), Span::new(5, 9)]
+        );
+    }
+
+    #[test]
+    fn lex_repeating_decimals() {
+        let repeating = ParseOptions::default().with_repeating_decimals(true);
+        let lex = |txt| Lexer::with_options(txt, repeating).unwrap().tokens;
+        let third = MathToken::constant(Number::new(1, 3));
+        assert_eq!(lex("0.(3)"), vec![third.clone()]);
+        assert_eq!(
+            Lexer::new("0.333... + x").unwrap().tokens,
+            vec![
+                third,
+                MathToken::operator(OperationToken::Add),
+                MathToken::variable("x".to_string().into()),
+            ]
+        );
+        // not a period: 2 * 3, 0.5 * (x)
+        assert_eq!(lex("2(3)").len(), 5);
+        assert_eq!(lex("0.5(x)").len(), 5);
+        // a product unless enabled
+        assert_eq!(
+            Lexer::new("0.5(4)").unwrap().tokens,
+            vec![
+                MathToken::constant(Number::new(1, 2)),
+                MathToken::operator(OperationToken::Multiply),
+                MathToken::operator(OperationToken::LParent),
+                MathToken::constant(Number::from(4)),
+                MathToken::operator(OperationToken::RParent),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_errors() {
+        assert_eq!(
+            Lexer::new("2 # x"),
+            Err(ParseError::new(
+                ParseErrorKind::UnexpectedChar,
+                Span::new(2, 3)
+            ))
+        );
+
+        assert_eq!(
+            Lexer::new("x + 1.2.3"),
+            Err(ParseError::new(
+                ParseErrorKind::MalformedNumber,
+                Span::new(4, 9)
+            ))
+        );
+    }
 }
@@ -1,20 +1,38 @@
 pub mod arithmatic;
+pub mod arena;
 pub mod bounds;
 pub mod cancel_op;
+pub mod codegen;
+pub mod complex;
 pub mod constants;
+mod display;
+pub mod dot;
 pub mod equations;
 pub mod expand;
 pub mod factorization;
 pub mod function;
 pub mod geometry;
+pub mod interner;
+pub mod interval;
 pub mod latex;
+pub mod latex_lexer;
 pub mod lexer;
+mod macros;
 pub mod math_tree;
+pub mod mathml;
+pub mod modular;
+pub mod number;
+pub mod number_theory;
 pub mod operands;
+pub mod ops;
 pub mod pattern;
+pub mod pretty;
+mod render;
 mod rewriting_rules;
 pub mod simplify;
+pub mod speech;
 pub mod stepper;
+pub mod units;
 pub mod math_json;
 
 use std::rc::Rc;
@@ -22,9 +40,11 @@ use std::rc::Rc;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-use rust_decimal::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use number::Number;
+use units::Unit;
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum OperationToken {
@@ -38,6 +58,8 @@ pub enum OperationToken {
     /* order critical */
     LParent,
     RParent,
+    // separates function arguments
+    Comma,
 }
 
 // i think a simple match function will evaluate to this too
@@ -88,7 +110,9 @@ impl OperationToken {
                 precedence: 3,
                 orderless: false,
             },
-            OperationToken::LParent | OperationToken::RParent => unreachable!(),
+            OperationToken::LParent | OperationToken::RParent | OperationToken::Comma => {
+                unreachable!()
+            }
         }
     }
 
@@ -128,6 +152,7 @@ impl OperationToken {
             '^' => OperationToken::Pow,
             '(' => OperationToken::LParent,
             ')' => OperationToken::RParent,
+            ',' => OperationToken::Comma,
             _ => return None,
         })
     }
@@ -141,11 +166,136 @@ impl OperationToken {
             OperationToken::Pow => '^',
             OperationToken::LParent => '(',
             OperationToken::RParent => ')',
-            OperationToken::Root => todo!(),
+            OperationToken::Comma => ',',
+            OperationToken::Root => '√',
         }
     }
 }
 
+// named functions applied with call syntax: sin(x), log(x, 2)
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum FunctionToken {
+    Sin,
+    Cos,
+    Tan,
+    // natural logarithm
+    Ln,
+    // log(x, base), base defaults to 10
+    Log,
+    Exp,
+    // sqrt(x) and root(x, n) are parsed into OperationToken::Root
+    Sqrt,
+    Root,
+    Abs,
+    // parts of a complex number: re(3 + 4i) = 3, im(3 + 4i) = 4
+    Re,
+    Im,
+    // conj(3 + 4i) = 3 - 4i
+    Conj,
+    // angle from the positive real axis: arg(i) = pi / 2
+    Arg,
+    // gcd(12, 18) = 6, lcm(4, 6) = 12
+    Gcd,
+    Lcm,
+    // prime factorization, written out as 2^3·3^2·5 for factorint(360)
+    Factorint,
+}
+
+pub const FUNCTIONS: [FunctionToken; 16] = [
+    FunctionToken::Sin,
+    FunctionToken::Cos,
+    FunctionToken::Tan,
+    FunctionToken::Ln,
+    FunctionToken::Log,
+    FunctionToken::Exp,
+    FunctionToken::Sqrt,
+    FunctionToken::Root,
+    FunctionToken::Abs,
+    FunctionToken::Re,
+    FunctionToken::Im,
+    FunctionToken::Conj,
+    FunctionToken::Arg,
+    FunctionToken::Gcd,
+    FunctionToken::Lcm,
+    FunctionToken::Factorint,
+];
+
+pub struct FunctionInfo {
+    // how many operands the function node holds
+    pub arity: u8,
+    // how many arguments must be written, the rest take their default
+    pub min_arity: u8,
+}
+
+impl FunctionToken {
+    pub fn info(&self) -> FunctionInfo {
+        match self {
+            FunctionToken::Log => FunctionInfo {
+                arity: 2,
+                min_arity: 1,
+            },
+            FunctionToken::Root | FunctionToken::Gcd | FunctionToken::Lcm => FunctionInfo {
+                arity: 2,
+                min_arity: 2,
+            },
+            _ => FunctionInfo {
+                arity: 1,
+                min_arity: 1,
+            },
+        }
+    }
+
+    // value of the last operand when it is omitted
+    pub fn default_operand(&self) -> Option<Number> {
+        match self {
+            FunctionToken::Log => Some(Number::ten()),
+            _ => None,
+        }
+    }
+
+    // operand added when lowered to its operation: sqrt(x) = root(x, 2)
+    pub fn lowered_operand(&self) -> Option<Number> {
+        match self {
+            FunctionToken::Sqrt => Some(Number::two()),
+            _ => None,
+        }
+    }
+
+    // functions that are written as calls but are operators in the tree
+    pub fn operation(&self) -> Option<OperationToken> {
+        match self {
+            FunctionToken::Sqrt | FunctionToken::Root => Some(OperationToken::Root),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FunctionToken::Sin => "sin",
+            FunctionToken::Cos => "cos",
+            FunctionToken::Tan => "tan",
+            FunctionToken::Ln => "ln",
+            FunctionToken::Log => "log",
+            FunctionToken::Exp => "exp",
+            FunctionToken::Sqrt => "sqrt",
+            FunctionToken::Root => "root",
+            FunctionToken::Abs => "abs",
+            FunctionToken::Re => "Re",
+            FunctionToken::Im => "Im",
+            FunctionToken::Conj => "conj",
+            FunctionToken::Arg => "arg",
+            FunctionToken::Gcd => "gcd",
+            FunctionToken::Lcm => "lcm",
+            FunctionToken::Factorint => "factorint",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<FunctionToken> {
+        FUNCTIONS.into_iter().find(|f| f.name() == name)
+    }
+}
+
 // struct MathTokenType
 // pub struct ShortString([char; 16]);
 
@@ -153,18 +303,25 @@ impl OperationToken {
 #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct MathToken {
     pub kind: MathTokenType,
-    pub constant: Option<Decimal>,
+    pub constant: Option<Number>,
     // #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
     pub variable: Option<Rc<String>>,
     pub operation: Option<OperationToken>,
+    pub function: Option<FunctionToken>,
+    // 5 m/s, constants only
+    pub unit: Option<Unit>,
 }
 
 impl std::fmt::Debug for MathToken {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.kind {
-            MathTokenType::Constant => write!(f, "{}", self.constant.unwrap()),
+            MathTokenType::Constant => match &self.unit {
+                Some(unit) => write!(f, "{} {}", self.constant.as_ref().unwrap(), unit),
+                None => write!(f, "{}", self.constant.as_ref().unwrap()),
+            },
             MathTokenType::Variable => write!(f, "{}", self.variable.as_ref().unwrap()),
             MathTokenType::Operator => write!(f, "{:?}", self.operation.unwrap()),
+            MathTokenType::Function => write!(f, "{}", self.function.unwrap().name()),
         }
     }
 }
@@ -175,6 +332,7 @@ pub enum MathTokenType {
     Constant,
     Variable,
     Operator,
+    Function,
 }
 // #[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 // pub enum MathToken {
@@ -212,15 +370,19 @@ impl MathToken {
             constant: None,
             variable: Some(s),
             operation: None,
+            function: None,
+            unit: None,
         }
     }
 
-    pub fn constant(d: Decimal) -> Self {
+    pub fn constant(n: impl Into<Number>) -> Self {
         Self {
             kind: MathTokenType::Constant,
-            constant: Some(d),
+            constant: Some(n.into()),
             variable: None,
             operation: None,
+            function: None,
+            unit: None,
         }
     }
 
@@ -230,6 +392,19 @@ impl MathToken {
             constant: None,
             variable: None,
             operation: Some(o),
+            function: None,
+            unit: None,
+        }
+    }
+
+    pub fn function(f: FunctionToken) -> Self {
+        Self {
+            kind: MathTokenType::Function,
+            constant: None,
+            variable: None,
+            operation: None,
+            function: Some(f),
+            unit: None,
         }
     }
 }
@@ -0,0 +1,162 @@
+// expr!(2 * x^2 + sin(x) / 3) builds the tree from Rust tokens, without the string parser.
+// identifiers are variables, ^ binds tighter than * and is right associative,
+// {value} takes a TreeNodeRef, Decimal or integer from the surrounding code: expr!({y} * 2)
+#[macro_export]
+macro_rules! expr {
+    ($($t:tt)+) => {
+        $crate::__expr!(@e [] $($t)+)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __expr {
+    // operators are left to Rust, atoms and ^ chains become TreeNodeRef values
+    (@e [$($out:tt)*]) => {
+        ($($out)*)
+    };
+    (@e [$($out:tt)*] + $($rest:tt)+) => {
+        $crate::__expr!(@e [$($out)* +] $($rest)+)
+    };
+    (@e [$($out:tt)*] - $($rest:tt)+) => {
+        $crate::__expr!(@e [$($out)* -] $($rest)+)
+    };
+    (@e [$($out:tt)*] * $($rest:tt)+) => {
+        $crate::__expr!(@e [$($out)* *] $($rest)+)
+    };
+    (@e [$($out:tt)*] / $($rest:tt)+) => {
+        $crate::__expr!(@e [$($out)* /] $($rest)+)
+    };
+    (@e [$($out:tt)*] $($rest:tt)+) => {
+        $crate::__expr!(@atom [$($out)*] [] [] $($rest)+)
+    };
+
+    // one atom, negated when written after ^-, added to the ^ chain
+    (@atom [$($out:tt)*] [$($chain:tt)*] [$($neg:tt)*] $l:literal $($rest:tt)*) => {
+        $crate::__expr!(@after [$($out)*] [$($chain)* ($($neg)* $crate::math_tree::TreeNodeRef::constant(
+            <$crate::number::Number as ::std::str::FromStr>::from_str(stringify!($l)).unwrap()
+        ))] $($rest)*)
+    };
+    (@atom [$($out:tt)*] [$($chain:tt)*] [$($neg:tt)*] $f:ident ($($args:tt)*) $($rest:tt)*) => {
+        $crate::__expr!(@after [$($out)*] [$($chain)* ($($neg)* $crate::math_tree::TreeNodeRef::call(
+            $crate::__expr!(@function $f),
+            $crate::__expr!(@args [] [] $($args)*)
+        ))] $($rest)*)
+    };
+    (@atom [$($out:tt)*] [$($chain:tt)*] [$($neg:tt)*] $v:ident $($rest:tt)*) => {
+        $crate::__expr!(@after [$($out)*] [$($chain)* ($($neg)* $crate::math_tree::TreeNodeRef::var(
+            stringify!($v)
+        ))] $($rest)*)
+    };
+    (@atom [$($out:tt)*] [$($chain:tt)*] [$($neg:tt)*] ($($inner:tt)+) $($rest:tt)*) => {
+        $crate::__expr!(@after [$($out)*] [$($chain)* ($($neg)* $crate::expr!($($inner)+))] $($rest)*)
+    };
+    (@atom [$($out:tt)*] [$($chain:tt)*] [$($neg:tt)*] {$value:expr} $($rest:tt)*) => {
+        $crate::__expr!(@after [$($out)*] [$($chain)* ($($neg)* $crate::math_tree::TreeNodeRef::from(
+            $value
+        ))] $($rest)*)
+    };
+
+    // x^2^3, x^-1
+    (@after [$($out:tt)*] [$($chain:tt)+] ^ - $($rest:tt)+) => {
+        $crate::__expr!(@atom [$($out)*] [$($chain)+] [-] $($rest)+)
+    };
+    (@after [$($out:tt)*] [$($chain:tt)+] ^ $($rest:tt)+) => {
+        $crate::__expr!(@atom [$($out)*] [$($chain)+] [] $($rest)+)
+    };
+    (@after [$($out:tt)*] [$($chain:tt)+] $($rest:tt)*) => {
+        $crate::__expr!(@e [$($out)* ($crate::__expr!(@pow $($chain)+))] $($rest)*)
+    };
+
+    // a^b^c is a^(b^c)
+    (@pow $a:tt) => {
+        $a
+    };
+    (@pow $a:tt $($rest:tt)+) => {
+        $a.pow($crate::__expr!(@pow $($rest)+))
+    };
+
+    // arguments split on commas, each one an expression
+    (@args [$($done:tt)*] []) => {
+        vec![$($done)*]
+    };
+    (@args [$($done:tt)*] [$($arg:tt)+]) => {
+        vec![$($done)* $crate::expr!($($arg)+)]
+    };
+    (@args [$($done:tt)*] [$($arg:tt)+] , $($rest:tt)*) => {
+        $crate::__expr!(@args [$($done)* $crate::expr!($($arg)+),] [] $($rest)*)
+    };
+    (@args [$($done:tt)*] [$($arg:tt)*] $t:tt $($rest:tt)*) => {
+        $crate::__expr!(@args [$($done)*] [$($arg)* $t] $($rest)*)
+    };
+
+    // unknown function names fail to compile
+    (@function sin) => { $crate::FunctionToken::Sin };
+    (@function cos) => { $crate::FunctionToken::Cos };
+    (@function tan) => { $crate::FunctionToken::Tan };
+    (@function ln) => { $crate::FunctionToken::Ln };
+    (@function log) => { $crate::FunctionToken::Log };
+    (@function exp) => { $crate::FunctionToken::Exp };
+    (@function sqrt) => { $crate::FunctionToken::Sqrt };
+    (@function root) => { $crate::FunctionToken::Root };
+    (@function abs) => { $crate::FunctionToken::Abs };
+    (@function Re) => { $crate::FunctionToken::Re };
+    (@function Im) => { $crate::FunctionToken::Im };
+    (@function conj) => { $crate::FunctionToken::Conj };
+    (@function arg) => { $crate::FunctionToken::Arg };
+    (@function gcd) => { $crate::FunctionToken::Gcd };
+    (@function lcm) => { $crate::FunctionToken::Lcm };
+    (@function factorint) => { $crate::FunctionToken::Factorint };
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    use crate::math_tree::TreeNodeRef;
+
+    #[test]
+    fn expr_macro() {
+        assert_eq!(crate::expr!(2 * x + 1), TreeNodeRef::parse("2 * x + 1"));
+        assert_eq!(
+            crate::expr!(2 * x ^ 2 + 3 * x - 1),
+            TreeNodeRef::parse("2 * x^2 + 3 * x - 1")
+        );
+        assert_eq!(
+            crate::expr!((x + 1) / (x - 1)),
+            TreeNodeRef::parse("(x + 1) / (x - 1)")
+        );
+        assert_eq!(crate::expr!(-x ^ 2), TreeNodeRef::parse("-x^2"));
+        assert_eq!(crate::expr!(1.5 * pi), TreeNodeRef::parse("1.5 * pi"));
+        assert_eq!(
+            crate::expr!(sin(x) + log(x, 2) * sqrt(x + 1)),
+            TreeNodeRef::parse("sin(x) + log(x, 2) * sqrt(x + 1)")
+        );
+        assert_eq!(crate::expr!(log(100)), TreeNodeRef::parse("log(100)"));
+    }
+
+    #[test]
+    fn expr_macro_powers() {
+        let x = TreeNodeRef::var("x");
+
+        // right associative
+        assert_eq!(crate::expr!(x ^ 2 ^ 3), x.pow(TreeNodeRef::two().pow(3)));
+        assert_eq!(crate::expr!(x ^ -1), x.pow(dec!(-1)));
+        assert_eq!(
+            crate::expr!(2 * x ^ (y + 1)),
+            2 * x.pow(crate::expr!(y + 1))
+        );
+    }
+
+    #[test]
+    fn expr_macro_values() {
+        let y = TreeNodeRef::parse("y + 1");
+        let c = dec!(0.5);
+
+        assert_eq!(
+            crate::expr!({ &y } * x + { c }),
+            TreeNodeRef::parse("(y + 1) * x + 0.5")
+        );
+    }
+}
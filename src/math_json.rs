@@ -1,25 +1,409 @@
-// parsing of cortex's MathJSON format, remind infix notation with token list
-
-// use rust_decimal::{prelude::FromPrimitive, Decimal};
-// use rust_decimal_macros::dec;
-// use serde_json::Value;
-
-// use crate::{math_tree::MathTree, MathToken};
-
-// impl MathTree {
-//     pub fn from_math_json(json_tokens: Vec<Value>) -> Self {
-//         let tokens = Vec::new();
-//         for jtoken in json_tokens {
-//             tokens.push(match jtoken {
-//                 Value::Number(c) => {
-//                     MathToken::constant(Decimal::from_f64(c.as_f64().unwrap()).unwrap())
-//                 }
-//                 Value::String(op_or_var) => todo!(),
-//                 Value::Array(_) => todo!(),
-//                 Value::Object(_) | Value::Null | Value::Bool(_) => unreachable!(),
-//             })
-//         }
-
-//         panic!()
-//     }
-// o
+// conversion from and to cortex's MathJSON format: ["Add", ["Multiply", 2, "x"], 1]
+
+use std::{fmt, rc::Rc, str::FromStr};
+
+use itertools::Itertools;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use serde_json::{json, Value};
+
+use crate::{
+    math_tree::{MathTree, TreeNodeRef},
+    number::Number,
+    FunctionToken, MathToken, MathTokenType, OperationToken, FUNCTIONS,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MathJsonError {
+    // null, booleans, quoted strings, dictionaries
+    UnexpectedValue(Value),
+    // 1.2.3, NaN, Infinity
+    MalformedNumber(String),
+    // ["Integrate", ...]
+    UnknownHead(String),
+    // ["Power", "x"]
+    ArityMismatch(String),
+}
+
+impl fmt::Display for MathJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MathJsonError::UnexpectedValue(v) => write!(f, "unexpected value {}", v),
+            MathJsonError::MalformedNumber(n) => write!(f, "malformed number {}", n),
+            MathJsonError::UnknownHead(h) => write!(f, "unknown function {}", h),
+            MathJsonError::ArityMismatch(h) => write!(f, "wrong number of arguments to {}", h),
+        }
+    }
+}
+
+impl std::error::Error for MathJsonError {}
+
+impl MathTree {
+    pub fn from_math_json(json: Value) -> Result<MathTree, MathJsonError> {
+        Ok(MathTree {
+            root: TreeNodeRef::from_math_json(&json)?,
+            bounds: Default::default(),
+        })
+    }
+
+    pub fn to_math_json(&self) -> Value {
+        self.root.to_math_json()
+    }
+}
+
+impl TreeNodeRef {
+    pub fn from_math_json(json: &Value) -> Result<TreeNodeRef, MathJsonError> {
+        match json {
+            Value::Number(n) => Ok(TreeNodeRef::constant(parse_number(&n.to_string())?)),
+            Value::String(s) => symbol(s),
+            Value::Array(expr) => {
+                let Some((Value::String(head), args)) = expr.split_first() else {
+                    return Err(MathJsonError::UnexpectedValue(json.clone()));
+                };
+                let args = args.iter().map(Self::from_math_json).try_collect()?;
+                function(head, args)
+            }
+            // {"num": "1.5"}, {"sym": "x"}, {"fn": ["Add", 1, 2]}
+            Value::Object(obj) => match (obj.get("num"), obj.get("sym"), obj.get("fn")) {
+                (Some(Value::String(n)), _, _) => Ok(TreeNodeRef::constant(parse_number(n)?)),
+                (Some(n @ Value::Number(_)), _, _) => Self::from_math_json(n),
+                (_, Some(Value::String(s)), _) => symbol(s),
+                (_, _, Some(f @ Value::Array(_))) => Self::from_math_json(f),
+                _ => Err(MathJsonError::UnexpectedValue(json.clone())),
+            },
+            Value::Null | Value::Bool(_) => Err(MathJsonError::UnexpectedValue(json.clone())),
+        }
+    }
+
+    pub fn to_math_json(&self) -> Value {
+        let val = self.val();
+        match val.kind {
+            MathTokenType::Constant => {
+                let c = val.constant.unwrap();
+                match c.to_i64() {
+                    Some(i) => json!(i),
+                    None if c.terminates() => json!({ "num": c.to_string() }),
+                    None => json!(["Rational", number(c.numer()), number(c.denom())]),
+                }
+            }
+            MathTokenType::Variable => json!(match val.variable.unwrap().as_str() {
+                "pi" | "π" => "Pi",
+                "e" => "ExponentialE",
+                "i" => "ImaginaryUnit",
+                name => name,
+            }),
+            MathTokenType::Function => {
+                let f = val.function.unwrap();
+                let borrow = self.borrow();
+                let mut args = borrow
+                    .calculate_iter()
+                    .map(|(_, n)| n.clone())
+                    .collect_vec();
+                // base 10 is implied
+                if f.default_operand().is_some()
+                    && args.last().unwrap().val().constant == f.default_operand()
+                {
+                    args.pop();
+                }
+
+                expression(function_head(f), &args)
+            }
+            MathTokenType::Operator => {
+                let borrow = self.borrow();
+                let args = borrow.display_iter().map(|(_, n)| n.clone()).collect_vec();
+
+                match val.operation.unwrap() {
+                    OperationToken::Add => expression("Add", &args),
+                    OperationToken::Subtract => expression("Subtract", &args),
+                    // -x is parsed as -1 * x
+                    OperationToken::Multiply
+                        if args[0].val().constant == Some(Number::neg_one()) =>
+                    {
+                        match args.len() {
+                            2 => expression("Negate", &args[1..]),
+                            _ => json!(["Negate", expression("Multiply", &args[1..])]),
+                        }
+                    }
+                    OperationToken::Multiply => expression("Multiply", &args),
+                    OperationToken::Divide => expression("Divide", &args),
+                    OperationToken::Pow => expression("Power", &args),
+                    OperationToken::Root if args[1].val().constant == Some(Number::two()) => {
+                        expression("Sqrt", &args[..1])
+                    }
+                    OperationToken::Root => expression("Root", &args),
+                    OperationToken::LParent | OperationToken::RParent | OperationToken::Comma => {
+                        unreachable!()
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn expression(head: &str, args: &[TreeNodeRef]) -> Value {
+    let mut expr = vec![json!(head)];
+    expr.extend(args.iter().map(TreeNodeRef::to_math_json));
+    Value::Array(expr)
+}
+
+fn parse_number(n: &str) -> Result<Number, MathJsonError> {
+    // big integers may end with n
+    let trimmed = n.trim().trim_end_matches('n');
+    Number::from_str(trimmed).map_err(|_| MathJsonError::MalformedNumber(n.to_string()))
+}
+
+// exact, numbers would go through f64
+fn number(i: &BigInt) -> Value {
+    match i.to_i64() {
+        Some(i) => json!(i),
+        None => json!({ "num": i.to_string() }),
+    }
+}
+
+fn symbol(s: &str) -> Result<TreeNodeRef, MathJsonError> {
+    // 'text' is a string, not a symbol
+    if s.starts_with('\'') {
+        return Err(MathJsonError::UnexpectedValue(json!(s)));
+    }
+
+    let name = match s {
+        "Pi" => "pi",
+        "ExponentialE" => "e",
+        "ImaginaryUnit" => "i",
+        name => name,
+    };
+    Ok(TreeNodeRef::new_val(MathToken::variable(Rc::new(
+        name.to_string(),
+    ))))
+}
+
+fn function_head(f: FunctionToken) -> &'static str {
+    match f {
+        FunctionToken::Sin => "Sin",
+        FunctionToken::Cos => "Cos",
+        FunctionToken::Tan => "Tan",
+        FunctionToken::Ln => "Ln",
+        FunctionToken::Log => "Log",
+        FunctionToken::Exp => "Exp",
+        FunctionToken::Sqrt => "Sqrt",
+        FunctionToken::Root => "Root",
+        FunctionToken::Abs => "Abs",
+        FunctionToken::Re => "Real",
+        FunctionToken::Im => "Imaginary",
+        FunctionToken::Conj => "Conjugate",
+        FunctionToken::Arg => "Argument",
+        FunctionToken::Gcd => "GCD",
+        FunctionToken::Lcm => "LCM",
+        FunctionToken::Factorint => "FactorInteger",
+    }
+}
+
+// builds the same tree as the infix parser would for head(args)
+fn function(head: &str, mut args: Vec<TreeNodeRef>) -> Result<TreeNodeRef, MathJsonError> {
+    let arity = |min: usize, max: usize, args: &Vec<TreeNodeRef>| {
+        if (min..=max).contains(&args.len()) {
+            Ok(())
+        } else {
+            Err(MathJsonError::ArityMismatch(head.to_string()))
+        }
+    };
+    let op = |op: OperationToken, args: Vec<TreeNodeRef>| {
+        TreeNodeRef::new_vals(MathToken::operator(op), args)
+    };
+
+    Ok(match head {
+        "Add" | "Multiply" => {
+            let (token, empty) = match head {
+                "Add" => (OperationToken::Add, TreeNodeRef::zero()),
+                _ => (OperationToken::Multiply, TreeNodeRef::one()),
+            };
+            match args.len() {
+                0 => empty,
+                1 => args.pop().unwrap(),
+                _ => op(token, args),
+            }
+        }
+        "Subtract" => {
+            arity(1, 2, &args)?;
+            if args.len() == 1 {
+                args.insert(0, TreeNodeRef::zero());
+            }
+            op(OperationToken::Subtract, args)
+        }
+        "Negate" => {
+            arity(1, 1, &args)?;
+            -args.pop().unwrap()
+        }
+        "Divide" | "Rational" => {
+            arity(2, 2, &args)?;
+            op(OperationToken::Divide, args)
+        }
+        "Power" => {
+            arity(2, 2, &args)?;
+            op(OperationToken::Pow, args)
+        }
+        "Square" => {
+            arity(1, 1, &args)?;
+            args.push(TreeNodeRef::two());
+            op(OperationToken::Pow, args)
+        }
+        "Sqrt" => {
+            arity(1, 1, &args)?;
+            args.push(TreeNodeRef::two());
+            op(OperationToken::Root, args)
+        }
+        "Root" => {
+            arity(2, 2, &args)?;
+            op(OperationToken::Root, args)
+        }
+        // parentheses
+        "Delimiter" => {
+            arity(1, 1, &args)?;
+            args.pop().unwrap()
+        }
+        head => {
+            let Some(f) = FUNCTIONS
+                .into_iter()
+                .find(|f| function_head(*f) == head)
+                .or_else(|| FunctionToken::from_name(&head.to_lowercase()))
+            else {
+                return Err(MathJsonError::UnknownHead(head.to_string()));
+            };
+
+            let info = f.info();
+            arity(info.min_arity as usize, info.arity as usize, &args)?;
+            TreeNodeRef::call(f, args)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+    use serde_json::json;
+
+    use super::MathJsonError;
+    use crate::{
+        math_tree::{MathTree, TreeNodeRef},
+        number::Number,
+    };
+
+    #[test]
+    fn from_math_json() {
+        for (json, txt) in [
+            (json!(2), "2"),
+            (json!("x"), "x"),
+            (json!(["Add", ["Multiply", 2, "x"], 1]), "2 * x + 1"),
+            (json!(["Add", 1, 2, 3]), "1 + 2 + 3"),
+            (json!(["Power", "x", ["Divide", 1, 2]]), "x^(1 / 2)"),
+            (json!(["Rational", 1, 3]), "1 / 3"),
+            (json!(["Sqrt", ["Add", "x", 1]]), "sqrt(x + 1)"),
+            (json!(["Root", "x", 3]), "root(x, 3)"),
+            (json!(["Negate", "x"]), "-x"),
+            (json!(["Subtract", "x", 1]), "x - 1"),
+            (json!(["Multiply", "Pi", ["Sin", "x"]]), "pi * sin(x)"),
+            (json!(["Log", "x"]), "log(x)"),
+            (json!(["Log", "x", 2]), "log(x, 2)"),
+            (json!(["Exp", ["Delimiter", "x"]]), "exp(x)"),
+            (json!({"fn": ["Abs", {"sym": "x"}]}), "abs(x)"),
+            (json!({"num": "1.5"}), "1.5"),
+        ] {
+            assert_eq!(
+                MathTree::from_math_json(json.clone()).unwrap().root,
+                MathTree::parse(txt).unwrap().root,
+                "{}",
+                json
+            );
+        }
+
+        assert_eq!(
+            TreeNodeRef::from_math_json(&json!(-2.5)).unwrap(),
+            TreeNodeRef::constant(dec!(-2.5))
+        );
+        assert_eq!(
+            TreeNodeRef::from_math_json(&json!({"num": "12345678901234567890n"})).unwrap(),
+            TreeNodeRef::constant(dec!(12345678901234567890))
+        );
+    }
+
+    #[test]
+    fn to_math_json() {
+        for (txt, json) in [
+            ("2 * x + 1", json!(["Add", ["Multiply", 2, "x"], 1])),
+            ("x - 1", json!(["Subtract", "x", 1])),
+            ("-x", json!(["Negate", "x"])),
+            ("x^2 / 2", json!(["Divide", ["Power", "x", 2], 2])),
+            (
+                "sqrt(x) * root(x, 3)",
+                json!(["Multiply", ["Sqrt", "x"], ["Root", "x", 3]]),
+            ),
+            (
+                "log(x) + log(x, 2)",
+                json!(["Add", ["Log", "x"], ["Log", "x", 2]]),
+            ),
+            (
+                "e^(pi * 0.5)",
+                json!(["Power", "ExponentialE", ["Multiply", {"num": "0.5"}, "Pi"]]),
+            ),
+        ] {
+            assert_eq!(
+                MathTree::parse(txt).unwrap().to_math_json(),
+                json,
+                "{}",
+                txt
+            );
+        }
+
+        assert_eq!(
+            TreeNodeRef::constant(Number::new(-1, 3)).to_math_json(),
+            json!(["Rational", -1, 3])
+        );
+    }
+
+    #[test]
+    fn math_json_round_trip() {
+        for txt in [
+            "2 * x + 1",
+            "(x + 1) * (x - 1) / 2",
+            "-x^2 + sin(x) * cos(x)",
+            "sqrt(2) + root(x, 3) + abs(x)",
+            "ln(x) + log(x) + log(x, 2) + exp(x)",
+            "2 * pi * r + e^1.5",
+        ] {
+            let tree = MathTree::parse(txt).unwrap();
+            assert_eq!(
+                MathTree::from_math_json(tree.to_math_json()).unwrap().root,
+                tree.root,
+                "{}",
+                txt
+            );
+        }
+    }
+
+    #[test]
+    fn math_json_errors() {
+        let error = |json| MathTree::from_math_json(json).unwrap_err();
+
+        assert_eq!(
+            error(json!(["Integrate", "x"])),
+            MathJsonError::UnknownHead("Integrate".to_string())
+        );
+        assert_eq!(
+            error(json!(["Power", "x"])),
+            MathJsonError::ArityMismatch("Power".to_string())
+        );
+        assert_eq!(
+            error(json!({"num": "NaN"})),
+            MathJsonError::MalformedNumber("NaN".to_string())
+        );
+        assert_eq!(
+            error(json!(null)),
+            MathJsonError::UnexpectedValue(json!(null))
+        );
+        assert_eq!(
+            error(json!("'text'")),
+            MathJsonError::UnexpectedValue(json!("'text'"))
+        );
+    }
+}
@@ -1,21 +1,28 @@
-use std::{cell::RefCell, collections::HashMap, f32::consts::E, ops::Index, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{hash_map::DefaultHasher, HashMap},
+    f32::consts::E,
+    hash::{Hash, Hasher},
+    ops::Index,
+    rc::{Rc, Weak},
+};
 
 use itertools::Itertools;
-use rust_decimal::Decimal;
-use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
 use crate::{
     bounds::Bound,
-    lexer::Lexer,
+    constants::get_constant,
+    lexer::{ends_operand, starts_operand, Lexer, ParseOptions, Span},
+    number::Number,
     operands::{OperandPos, Operands, OperandsIt},
     MathToken, MathTokenType, OperationToken,
 };
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct TreeNode {
     val: MathToken,
     // pub childs: Vec<TreeNodeRef>, // left: Option<TreeNodeRef>,
@@ -24,15 +31,48 @@ pub struct TreeNode {
     // #[wasm_bindgen(getter_with_clone)]
     // #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
     operands: Operands,
+    // see TreeNodeRef::structural_hash, cleared when this node or any operand below it changes
+    #[serde(skip)]
+    hash: Cell<Option<u64>>,
+    // nodes that cached a hash of this one, cleared along with it
+    #[serde(skip)]
+    parents: RefCell<Vec<Weak<RefCell<TreeNode>>>>,
+}
+
+impl Clone for TreeNode {
+    // the copy is not an operand of anything yet, and is not registered as a parent of the
+    // operands it shares, so a change to them could not clear a hash it kept
+    fn clone(&self) -> Self {
+        Self {
+            val: self.val.clone(),
+            operands: self.operands.clone(),
+            hash: Cell::new(None),
+            parents: RefCell::default(),
+        }
+    }
+}
+
+// clears the cached hash of a node about to change, and of every node that cached a hash over it.
+// a node without a cached hash has no ancestor with one, that is where it stops
+fn invalidate(node: &TreeNode) {
+    node.hash.set(None);
+    for parent in node.parents.borrow().iter().filter_map(Weak::upgrade) {
+        // a parent borrowed mutably is being changed itself, it was cleared then
+        if let Ok(parent) = parent.try_borrow() {
+            if parent.hash.get().is_some() {
+                invalidate(&parent);
+            }
+        }
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[derive(Clone, Serialize, Deserialize)]
 pub struct TreeNodeRef(Rc<RefCell<TreeNode>>);
 
-impl std::hash::Hash for TreeNodeRef {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        // To_L self.borrow();
+impl Hash for TreeNodeRef {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.structural_hash());
     }
 }
 
@@ -47,14 +87,20 @@ impl Index<OperandPos> for TreeNode {
 impl PartialEq for TreeNodeRef {
     fn eq(&self, other: &TreeNodeRef) -> bool {
         let other_borrow = other.borrow();
+        // operands in calculation order, not the slab slots they are stored in: the slots depend on
+        // which operands were removed and pushed before, x + 2 left after removing 1 from x + 1 + 2
+        // is the same expression as a freshly built x + 2
         self.borrow().val == other_borrow.val
             && self
                 .borrow()
                 .calculate_iter()
-                .eq(other_borrow.calculate_iter())
+                .map(|(_, n)| n)
+                .eq(other_borrow.calculate_iter().map(|(_, n)| n))
     }
 }
 
+impl Eq for TreeNodeRef {}
+
 impl std::fmt::Debug for TreeNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("TreeNode")
@@ -83,10 +129,6 @@ impl TreeNodeRef {
         Self(Rc::new(RefCell::new(TreeNode::new_vals(token, childs))))
     }
 
-    pub fn constant(dec: Decimal) -> Self {
-        Self::new_val(MathToken::constant(dec))
-    }
-
     pub fn val(&self) -> MathToken {
         self.borrow().val.clone()
     }
@@ -96,25 +138,33 @@ impl TreeNodeRef {
     }
 
     pub fn zero() -> Self {
-        Self::constant(dec!(0))
+        Self::constant(0)
     }
 
     pub fn one() -> Self {
-        Self::constant(dec!(1))
+        Self::constant(1)
     }
 
     pub fn two() -> Self {
-        Self::constant(dec!(2))
+        Self::constant(2)
     }
 }
 
 impl TreeNodeRef {
+    pub fn constant(n: impl Into<Number>) -> Self {
+        Self::new_val(MathToken::constant(n))
+    }
+
     pub(crate) fn parse(str: &str) -> Self {
         MathTree::parse(str).unwrap().root
     }
 
     pub fn replace(&self, new: TreeNodeRef) {
-        self.0.replace(new.borrow().clone());
+        let new = new.borrow();
+        // the parents of this node stay, they now hold the new value
+        let mut node = self.borrow_mut();
+        node.val = new.val.clone();
+        node.operands = new.operands.clone();
     }
 
     pub fn borrow(&self) -> std::cell::Ref<'_, TreeNode> {
@@ -122,7 +172,54 @@ impl TreeNodeRef {
     }
 
     pub fn borrow_mut(&self) -> std::cell::RefMut<'_, TreeNode> {
-        self.0.borrow_mut()
+        let node = self.0.borrow_mut();
+        // parents may cache a hash of this node too
+        invalidate(&node);
+        node
+    }
+
+    // same allocation, not only equal
+    pub fn ptr_eq(&self, other: &TreeNodeRef) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    // equal trees hash equally, operands of orderless operators hash the same in any order
+    // (x + y and y + x), cached until the node or an operand below it is mutated
+    pub fn structural_hash(&self) -> u64 {
+        let node = self.borrow();
+        if let Some(hash) = node.hash.get() {
+            return hash;
+        }
+
+        let mut childs = node
+            .operands
+            .iter_order()
+            .map(|(_, n)| {
+                n.add_parent(self);
+                n.structural_hash()
+            })
+            .collect_vec();
+        if node.val.operation.is_some_and(|op| op.info().orderless) {
+            childs.sort_unstable();
+        }
+
+        let mut hasher = DefaultHasher::new();
+        node.val.hash(&mut hasher);
+        childs.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        node.hash.set(Some(hash));
+        hash
+    }
+
+    // parent is about to cache a hash over this node
+    fn add_parent(&self, parent: &TreeNodeRef) {
+        let node = self.borrow();
+        let mut parents = node.parents.borrow_mut();
+        parents.retain(|p| p.strong_count() > 0);
+        if !parents.iter().any(|p| p.as_ptr() == Rc::as_ptr(&parent.0)) {
+            parents.push(Rc::downgrade(&parent.0));
+        }
     }
 }
 
@@ -132,6 +229,8 @@ impl TreeNode {
         Self {
             val: token,
             operands: Operands::default(),
+            hash: Cell::new(None),
+            parents: RefCell::default(),
         }
     }
 
@@ -243,35 +342,124 @@ pub struct MathTree {
 pub struct TreePos(pub Vec<OperandPos>);
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub enum ParseError {
-    MissingOperand,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParseErrorKind {
+    // character that is not part of any token: 2 # 3
+    UnexpectedChar,
+    // number that can't be read: 1.2.3
+    MalformedNumber,
+    // operator without enough operands: 2 *
+    DanglingOperator,
+    // two operands without an operator between them: (2)(3)
+    MissingOperator,
+    // nothing to parse: "", "()"
+    EmptyInput,
     ParenthesesMismatch,
+    // equation without a '=' sign
+    MissingEqualSign,
+    // function called with the wrong amount of arguments: sin(x, 2)
+    ArityMismatch,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    // where in the input the error was found, for underlining
+    pub span: Span,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl ParseError {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(kind: ParseErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    pub fn message(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ParseError {
+    // for errors found in a substring of the original input
+    pub(crate) fn offset(mut self, by: usize) -> Self {
+        self.span.start += by;
+        self.span.end += by;
+        self
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self.kind {
+            ParseErrorKind::UnexpectedChar => "unexpected character",
+            ParseErrorKind::MalformedNumber => "malformed number",
+            ParseErrorKind::DanglingOperator => "operator is missing an operand",
+            ParseErrorKind::MissingOperator => "missing operator between operands",
+            ParseErrorKind::EmptyInput => "empty expression",
+            ParseErrorKind::ParenthesesMismatch => "unbalanced parentheses",
+            ParseErrorKind::MissingEqualSign => "equation is missing '='",
+            ParseErrorKind::ArityMismatch => "wrong number of function arguments",
+        };
+        write!(f, "{} at {}..{}", msg, self.span.start, self.span.end)
+    }
 }
 
+impl std::error::Error for ParseError {}
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 impl MathTree {
     pub fn parse(str: &str) -> Result<MathTree, ParseError> {
-        let rpn = Self::reverse_polish_notation(Lexer::new(str))?;
-        let mut nodes: Vec<TreeNodeRef> = Vec::new();
+        Self::parse_with(str, ParseOptions::default())
+    }
 
-        for token in rpn.into_iter() {
-            let Some(op) = token.operation else {
-                nodes.push(TreeNodeRef::new_val(token));
-                continue;
+    pub fn parse_with(str: &str, options: ParseOptions) -> Result<MathTree, ParseError> {
+        Self::from_lexer(Lexer::with_options(str, options)?, str.len())
+    }
+}
+
+impl MathTree {
+    // len is the length of the input, spanned by EmptyInput
+    pub(crate) fn from_lexer(lexer: Lexer, len: usize) -> Result<MathTree, ParseError> {
+        let rpn = Self::shunting_yard(lexer)?;
+        // every node remembers the span of the input it was built from
+        let mut nodes: Vec<(TreeNodeRef, Span)> = Vec::new();
+
+        for (token, span) in rpn.into_iter() {
+            let arity = match (token.operation, token.function) {
+                (Some(op), _) => op.info().arity,
+                (_, Some(f)) => f.info().arity,
+                _ => {
+                    nodes.push((TreeNodeRef::new_val(token), span));
+                    continue;
+                }
             };
 
-            let op_info = op.info();
-            let split_at = nodes.len() - op_info.arity as usize;
-            let operands = nodes.split_off(split_at);
-            if operands.len() != op_info.arity as usize {
-                return Err(ParseError::MissingOperand);
-            }
-            nodes.push(TreeNodeRef::new_vals(token, operands));
+            let Some(split_at) = nodes.len().checked_sub(arity as usize) else {
+                return Err(ParseError::new(ParseErrorKind::DanglingOperator, span));
+            };
+            let (operands, spans): (Vec<_>, Vec<_>) = nodes.split_off(split_at).into_iter().unzip();
+            let span = spans.into_iter().fold(span, |a, b| a.join(b));
+            nodes.push((TreeNodeRef::new_vals(token, operands), span));
         }
 
+        if nodes.len() > 1 {
+            return Err(ParseError::new(
+                ParseErrorKind::MissingOperator,
+                nodes[1].1,
+            ));
+        }
+
+        let Some((root, _)) = nodes.pop() else {
+            return Err(ParseError::new(
+                ParseErrorKind::EmptyInput,
+                Span::new(0, len),
+            ));
+        };
+
         Ok(MathTree {
-            root: nodes.pop().unwrap(),
+            root,
             bounds: HashMap::new(),
         })
     }
@@ -283,82 +471,332 @@ impl MathTree {
 
 impl MathTree {
     // postfix notation
-    pub fn reverse_polish_notation(mut lexer: Lexer) -> Result<Vec<MathToken>, ParseError> {
+    pub fn reverse_polish_notation(lexer: Lexer) -> Result<Vec<MathToken>, ParseError> {
+        Ok(Self::shunting_yard(lexer)?
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect())
+    }
+
+    // postfix notation, keeping the span each token was read from
+    fn shunting_yard(mut lexer: Lexer) -> Result<Vec<(MathToken, Span)>, ParseError> {
         let mut output = Vec::new();
-        let mut operators: Vec<OperationToken> = Vec::new();
+        // operators and functions waiting for their operands
+        let mut operators: Vec<(MathToken, Span)> = Vec::new();
+        // for every open parenthesis, how many arguments were seen if it belongs to a function call
+        let mut calls: Vec<Option<u8>> = Vec::new();
 
         // there won't be two consecutive operators (not parenthesis) unless its unary +-
         // (because there must be operand before (and after) operator in prefix
-        let mut insert: Vec<(usize, MathToken)> = Vec::new();
-        let mut last_token: Option<MathToken> = None;
-        for (i, a) in lexer.tokens.iter().enumerate() {
+        // tokens from..to replaced by the given ones
+        type Splice = (usize, usize, Vec<(MathToken, Span)>);
+        let mut splice: Vec<Splice> = Vec::new();
+        let mut last_token: Option<&MathToken> = None;
+        for (i, (a, span)) in lexer.tokens.iter().zip(&lexer.spans).enumerate() {
+            // two operands in a row: 2 3, x (1)
+            if let Some(last) = last_token {
+                if ends_operand(last) && starts_operand(a) {
+                    return Err(ParseError::new(ParseErrorKind::MissingOperator, *span));
+                }
+
+                // sin + 2
+                if last.kind == MathTokenType::Function
+                    && a.operation != Some(OperationToken::LParent)
+                {
+                    return Err(ParseError::new(
+                        ParseErrorKind::DanglingOperator,
+                        lexer.spans[i - 1],
+                    ));
+                }
+            }
+
             if let Some(op) = a.operation {
-                // two cases where there can be unary operator:
+                // three cases where there can be unary operator:
                 // before nothing: -x
                 // before LParent: (-x)
+                // before Comma: log(x, -2)
                 let unary = match last_token {
-                    Some(s) => s.operation == Some(OperationToken::LParent),
+                    Some(s) => matches!(
+                        s.operation,
+                        Some(OperationToken::LParent | OperationToken::Comma)
+                    ),
                     None => true,
                 };
 
+                // after *, / and ^: 2^-2, x / -y, 2 * -x
+                let after_operator = op == OperationToken::Subtract
+                    && last_token.is_some_and(|s| {
+                        matches!(
+                            s.operation,
+                            Some(
+                                OperationToken::Multiply
+                                    | OperationToken::Divide
+                                    | OperationToken::Pow
+                            )
+                        )
+                    });
+
+                if after_operator {
+                    let operand = &lexer.tokens[i + 1..];
+                    let powered =
+                        operand.get(1).and_then(|t| t.operation) == Some(OperationToken::Pow);
+                    match operand.first() {
+                        // 2^-2 is 2^(-2)
+                        Some(t) if t.constant.is_some() && !powered => splice.push((
+                            i,
+                            i + 2,
+                            vec![(
+                                MathToken::constant(-t.constant.clone().unwrap()),
+                                span.join(lexer.spans[i + 1]),
+                            )],
+                        )),
+                        // x / -y is x / (-1 * y)
+                        Some(t) if t.kind == MathTokenType::Variable && !powered => {
+                            let var_span = lexer.spans[i + 1];
+                            splice.push((
+                                i,
+                                i + 2,
+                                vec![
+                                    (
+                                        MathToken::operator(OperationToken::LParent),
+                                        Span::new(span.start, span.start),
+                                    ),
+                                    (MathToken::constant(-1), *span),
+                                    (
+                                        MathToken::operator(OperationToken::Multiply),
+                                        Span::new(span.end, span.end),
+                                    ),
+                                    (t.clone(), var_span),
+                                    (
+                                        MathToken::operator(OperationToken::RParent),
+                                        Span::new(var_span.end, var_span.end),
+                                    ),
+                                ],
+                            ))
+                        }
+                        // 2 * -x^2 is 2 * -1 * x^2, a product does not care about grouping
+                        _ if last_token.and_then(|s| s.operation)
+                            == Some(OperationToken::Multiply) =>
+                        {
+                            splice.push((
+                                i,
+                                i + 1,
+                                vec![
+                                    (MathToken::constant(-1), *span),
+                                    (
+                                        MathToken::operator(OperationToken::Multiply),
+                                        Span::new(span.end, span.end),
+                                    ),
+                                ],
+                            ))
+                        }
+                        // x / -sin(x) needs parentheses: x / (-sin(x))
+                        _ => {
+                            return Err(ParseError::new(
+                                ParseErrorKind::DanglingOperator,
+                                *span,
+                            ))
+                        }
+                    }
+                }
+
                 if unary {
                     match op {
-                        OperationToken::Subtract | OperationToken::Add => {
-                            insert.push((i, MathToken::constant(dec!(0))))
+                        OperationToken::Add => splice.push((
+                            i,
+                            i,
+                            vec![(
+                                MathToken::constant(0),
+                                Span::new(span.start, span.start),
+                            )],
+                        )),
+                        // -2 is a negative constant, but -2^2 is -(2^2)
+                        OperationToken::Subtract
+                            if lexer
+                                .tokens
+                                .get(i + 1)
+                                .is_some_and(|t| t.constant.is_some())
+                                && lexer.tokens.get(i + 2).and_then(|t| t.operation)
+                                    != Some(OperationToken::Pow) =>
+                        {
+                            let c = lexer.tokens[i + 1].constant.clone().unwrap();
+                            splice.push((
+                                i,
+                                i + 2,
+                                vec![(MathToken::constant(-c), span.join(lexer.spans[i + 1]))],
+                            ))
                         }
-                        // OperationToken::Add => todo!(),
+                        // -x is -1 * x, the way it is simplified
+                        OperationToken::Subtract => splice.push((
+                            i,
+                            i + 1,
+                            vec![
+                                (MathToken::constant(-1), *span),
+                                (
+                                    MathToken::operator(OperationToken::Multiply),
+                                    Span::new(span.end, span.end),
+                                ),
+                            ],
+                        )),
                         OperationToken::LParent => {}
-                        _ => unreachable!(),
+                        // ()
+                        OperationToken::RParent if last_token.is_some() => {
+                            return Err(ParseError::new(
+                                ParseErrorKind::EmptyInput,
+                                lexer.spans[i - 1].join(*span),
+                            ))
+                        }
+                        OperationToken::RParent => {
+                            return Err(ParseError::new(
+                                ParseErrorKind::ParenthesesMismatch,
+                                *span,
+                            ))
+                        }
+                        // *x
+                        _ => {
+                            return Err(ParseError::new(
+                                ParseErrorKind::DanglingOperator,
+                                *span,
+                            ))
+                        }
                     }
                 }
             }
-            last_token = Some(a.clone());
+            last_token = Some(a);
+        }
+
+        if let Some(last) = lexer.tokens.last() {
+            if last.kind == MathTokenType::Function {
+                return Err(ParseError::new(
+                    ParseErrorKind::DanglingOperator,
+                    *lexer.spans.last().unwrap(),
+                ));
+            }
         }
 
-        for (i, a) in insert {
-            lexer.tokens.insert(i, a);
+        // splicing from the back keeps the earlier indexes valid
+        for (from, to, tokens) in splice.into_iter().rev() {
+            let (tokens, spans): (Vec<_>, Vec<_>) = tokens.into_iter().unzip();
+            lexer.tokens.splice(from..to, tokens);
+            lexer.spans.splice(from..to, spans);
         }
 
-        'outer: for token in lexer.tokens.into_iter() {
+        for (token, span) in lexer.tokens.into_iter().zip(lexer.spans) {
             match token.kind {
-                MathTokenType::Constant | MathTokenType::Variable => output.push(token),
-                MathTokenType::Operator => {
-                    let op = token.operation.unwrap();
-                    if op == OperationToken::RParent {
-                        while let Some(last_op) = operators.pop() {
-                            if last_op == OperationToken::LParent {
-                                continue 'outer;
-                            } else {
-                                output.push(MathToken::operator(last_op));
+                MathTokenType::Constant | MathTokenType::Variable => output.push((token, span)),
+                // always followed by its LParent
+                MathTokenType::Function => operators.push((token, span)),
+                MathTokenType::Operator => match token.operation.unwrap() {
+                    OperationToken::LParent => {
+                        let call = matches!(
+                            operators.last(),
+                            Some((last, _)) if last.kind == MathTokenType::Function
+                        );
+                        calls.push(if call { Some(1) } else { None });
+                        operators.push((token, span));
+                    }
+                    OperationToken::Comma => {
+                        let in_call = Self::pop_until_parenthesis(&mut operators, &mut output)
+                            && matches!(calls.last(), Some(Some(_)));
+                        if !in_call {
+                            return Err(ParseError::new(ParseErrorKind::UnexpectedChar, span));
+                        }
+
+                        if let Some(Some(args)) = calls.last_mut() {
+                            *args += 1;
+                        }
+                    }
+                    OperationToken::RParent => {
+                        if !Self::pop_until_parenthesis(&mut operators, &mut output) {
+                            return Err(ParseError::new(
+                                ParseErrorKind::ParenthesesMismatch,
+                                span,
+                            ));
+                        }
+                        operators.pop();
+
+                        if let Some(args) = calls.pop().unwrap() {
+                            let (f_token, f_span) = operators.pop().unwrap();
+                            let f = f_token.function.unwrap();
+                            let info = f.info();
+                            let f_span = f_span.join(span);
+
+                            if args < info.min_arity || args > info.arity {
+                                return Err(ParseError::new(
+                                    ParseErrorKind::ArityMismatch,
+                                    f_span,
+                                ));
                             }
+
+                            // omitted arguments take their default: log(x) = log(x, 10)
+                            if args < info.arity {
+                                let default = f.default_operand().unwrap();
+                                output.push((MathToken::constant(default), f_span));
+                            }
+
+                            // sqrt(x) = x √ 2
+                            let f_token = match f.operation() {
+                                Some(op) => {
+                                    if let Some(index) = f.lowered_operand() {
+                                        output.push((MathToken::constant(index), f_span));
+                                    }
+                                    MathToken::operator(op)
+                                }
+                                None => f_token,
+                            };
+                            output.push((f_token, f_span));
                         }
-                        return Err(ParseError::ParenthesesMismatch);
-                    } else if op != OperationToken::LParent {
-                        while let Some(last_op) = operators.last() {
-                            if *last_op != OperationToken::LParent
+                    }
+                    op => {
+                        while let Some((last, _)) = operators.last() {
+                            let Some(last_op) = last.operation else {
+                                break;
+                            };
+
+                            if last_op != OperationToken::LParent
                                 && op.info().precedence <= last_op.info().precedence
                             {
-                                output.push(MathToken::operator(operators.pop().unwrap()));
+                                output.push(operators.pop().unwrap());
                             } else {
                                 break;
                             }
                         }
+                        operators.push((token, span))
                     }
-                    operators.push(op)
-                }
+                },
             }
         }
 
-        output.extend(
-            operators
-                .into_iter()
-                .map(|op| MathToken::operator(op))
-                .rev(),
-        );
+        while let Some((token, span)) = operators.pop() {
+            // opened but never closed
+            if token.operation == Some(OperationToken::LParent) {
+                return Err(ParseError::new(
+                    ParseErrorKind::ParenthesesMismatch,
+                    span,
+                ));
+            }
+            output.push((token, span));
+        }
 
         Ok(output)
     }
 
+    // moves operators to the output until reaching an LParent, which is left on the stack,
+    // returns whether one was found
+    fn pop_until_parenthesis(
+        operators: &mut Vec<(MathToken, Span)>,
+        output: &mut Vec<(MathToken, Span)>,
+    ) -> bool {
+        while let Some((last, _)) = operators.last() {
+            if last.operation == Some(OperationToken::LParent) {
+                return true;
+            }
+            output.push(operators.pop().unwrap());
+        }
+
+        false
+    }
+
     // O(n) where n is the amount of leafs between the root and the deired remove
     // pub fn remove(&mut self, mut pos: TreePos) {
     //     let mut node = self.root.clone();
@@ -387,6 +825,53 @@ impl MathTree {
         TreeNodeRef::new_vals(node.val(), children)
     }
 
+    // numeric value of the constants: exact fractions become decimals, 1/3 = 0.3333333333333333333333333333,
+    // and symbols such as pi their value
+    pub fn approximate(&self) -> MathTree {
+        MathTree {
+            root: Self::approximate_node(&self.root),
+            bounds: self.bounds.clone(),
+        }
+    }
+
+    fn approximate_node(node: &TreeNodeRef) -> TreeNodeRef {
+        let mut val = node.val();
+        if let Some(value) = val.variable.as_ref().and_then(|v| get_constant(v)?.value) {
+            return TreeNodeRef::constant(value);
+        }
+        val.constant = val.constant.map(|c| c.approximate());
+
+        let children = node
+            .borrow()
+            .operands()
+            .iter_order()
+            .map(|(_, c)| Self::approximate_node(c))
+            .collect();
+        TreeNodeRef::new_vals(val, children)
+    }
+
+    // the inverse of approximate: each constant becomes the simplest fraction within tolerance,
+    // 0.3333333333333333333333333333 is 1/3 within 1e-20, a tolerance of 0 keeps the exact values
+    pub fn to_fraction(&self, tolerance: &Number) -> MathTree {
+        MathTree {
+            root: Self::to_fraction_node(&self.root, tolerance),
+            bounds: self.bounds.clone(),
+        }
+    }
+
+    fn to_fraction_node(node: &TreeNodeRef, tolerance: &Number) -> TreeNodeRef {
+        let mut val = node.val();
+        val.constant = val.constant.map(|c| c.to_fraction(tolerance));
+
+        let children = node
+            .borrow()
+            .operands()
+            .iter_order()
+            .map(|(_, c)| Self::to_fraction_node(c, tolerance))
+            .collect();
+        TreeNodeRef::new_vals(val, children)
+    }
+
     pub fn find(&self, val: &MathToken) -> Option<TreeNodeRef> {
         Self::find_node(&self.root, val)
     }
@@ -414,16 +899,17 @@ mod tests {
 
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
+    use crate::{lexer::VariablePolicy, FunctionToken};
 
     #[test]
     fn rpn() {
         let txt = "2 * x";
-        let lexer = Lexer::new(txt);
+        let lexer = Lexer::new(txt).unwrap();
         assert_eq!(
             MathTree::reverse_polish_notation(lexer.clone()),
             Ok(vec![
                 MathToken::constant(dec!(2)),
-                MathToken::variable("x".to_string()),
+                MathToken::variable("x".to_string().into()),
                 MathToken::operator(OperationToken::Multiply),
             ])
         );
@@ -434,7 +920,7 @@ mod tests {
                 MathToken::operator(OperationToken::Multiply),
                 vec![
                     TreeNodeRef::new_val(MathToken::constant(dec!(2))),
-                    TreeNodeRef::new_val(MathToken::variable("x".to_string()))
+                    TreeNodeRef::new_val(MathToken::variable("x".to_string().into()))
                 ]
             )
         );
@@ -443,13 +929,13 @@ mod tests {
     #[test]
     fn rpn_precedence() {
         let txt = "2 * x + 1";
-        let lexer = Lexer::new(txt);
+        let lexer = Lexer::new(txt).unwrap();
 
         assert_eq!(
             MathTree::reverse_polish_notation(lexer.clone()),
             Ok(vec![
                 MathToken::constant(dec!(2)),
-                MathToken::variable("x".to_string()),
+                MathToken::variable("x".to_string().into()),
                 MathToken::operator(OperationToken::Multiply),
                 MathToken::constant(dec!(1)),
                 MathToken::operator(OperationToken::Add),
@@ -465,7 +951,7 @@ mod tests {
                         MathToken::operator(OperationToken::Multiply),
                         vec![
                             TreeNodeRef::new_val(MathToken::constant(dec!(2))),
-                            TreeNodeRef::new_val(MathToken::variable("x".to_string()))
+                            TreeNodeRef::new_val(MathToken::variable("x".to_string().into()))
                         ]
                     ),
                     TreeNodeRef::new_val(MathToken::constant(dec!(1))),
@@ -477,10 +963,10 @@ mod tests {
     #[test]
     fn rpn_precedence2() {
         assert_eq!(
-            MathTree::reverse_polish_notation(Lexer::new("2 * x + 1 * 3 + 4")),
+            MathTree::reverse_polish_notation(Lexer::new("2 * x + 1 * 3 + 4").unwrap()),
             Ok(vec![
                 MathToken::constant(dec!(2)),
-                MathToken::variable("x".to_string()),
+                MathToken::variable("x".to_string().into()),
                 MathToken::operator(OperationToken::Multiply),
                 MathToken::constant(dec!(1)),
                 MathToken::constant(dec!(3)),
@@ -495,10 +981,10 @@ mod tests {
     #[test]
     fn rpn_precedence_parentheses() {
         assert_eq!(
-            MathTree::reverse_polish_notation(Lexer::new("2 * (x + 1)")),
+            MathTree::reverse_polish_notation(Lexer::new("2 * (x + 1)").unwrap()),
             Ok(vec![
                 MathToken::constant(dec!(2)),
-                MathToken::variable("x".to_string()),
+                MathToken::variable("x".to_string().into()),
                 MathToken::constant(dec!(1)),
                 MathToken::operator(OperationToken::Add),
                 MathToken::operator(OperationToken::Multiply),
@@ -509,14 +995,14 @@ mod tests {
     #[test]
     fn rpn_precedence_double_parentheses() {
         let txt = "2 * (4 + (x + 1))";
-        let lexer = Lexer::new(txt);
+        let lexer = Lexer::new(txt).unwrap();
         // 2 * (x + 5)
         assert_eq!(
             MathTree::reverse_polish_notation(lexer.clone()),
             Ok(vec![
                 MathToken::constant(dec!(2)),
                 MathToken::constant(dec!(4)),
-                MathToken::variable("x".to_string()),
+                MathToken::variable("x".to_string().into()),
                 MathToken::constant(dec!(1)),
                 MathToken::operator(OperationToken::Add),
                 MathToken::operator(OperationToken::Add),
@@ -534,7 +1020,7 @@ mod tests {
                         MathToken::operator(OperationToken::Add),
                         vec![
                             TreeNodeRef::new_val(MathToken::constant(dec!(4))),
-                            TreeNodeRef::new_val(MathToken::variable("x".to_string())),
+                            TreeNodeRef::new_val(MathToken::variable("x".to_string().into())),
                             TreeNodeRef::new_val(MathToken::constant(dec!(1))),
                         ]
                     )
@@ -543,13 +1029,13 @@ mod tests {
         );
 
         let txt = "2 * (x + 1 + (2 + 3))";
-        let lexer = Lexer::new(&txt);
+        let lexer = Lexer::new(&txt).unwrap();
 
         assert_eq!(
             MathTree::reverse_polish_notation(lexer.clone()),
             Ok(vec![
                 MathToken::constant(dec!(2)),
-                MathToken::variable("x".to_string()),
+                MathToken::variable("x".to_string().into()),
                 MathToken::constant(dec!(1)),
                 MathToken::operator(OperationToken::Add),
                 MathToken::constant(dec!(2)),
@@ -569,7 +1055,7 @@ mod tests {
                     TreeNodeRef::new_vals(
                         MathToken::operator(OperationToken::Add),
                         vec![
-                            TreeNodeRef::new_val(MathToken::variable("x".to_string())),
+                            TreeNodeRef::new_val(MathToken::variable("x".to_string().into())),
                             TreeNodeRef::new_val(MathToken::constant(dec!(1))),
                             TreeNodeRef::new_val(MathToken::constant(dec!(2))),
                             TreeNodeRef::new_val(MathToken::constant(dec!(3))),
@@ -637,4 +1123,310 @@ mod tests {
         //     )
         // );
     }
+
+    fn parse_error(txt: &str, kind: ParseErrorKind, start: usize, end: usize) {
+        assert_eq!(
+            MathTree::parse(txt).map(|_| ()),
+            Err(ParseError::new(kind, Span::new(start, end)))
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        parse_error("", ParseErrorKind::EmptyInput, 0, 0);
+        parse_error("  ", ParseErrorKind::EmptyInput, 0, 2);
+        parse_error("2 * ()", ParseErrorKind::EmptyInput, 4, 6);
+        parse_error("2 $ 3", ParseErrorKind::UnexpectedChar, 2, 3);
+        parse_error("1.2.3 + x", ParseErrorKind::MalformedNumber, 0, 5);
+        parse_error("*x", ParseErrorKind::DanglingOperator, 0, 1);
+        parse_error("2 * x +", ParseErrorKind::DanglingOperator, 6, 7);
+        parse_error("(2 + x", ParseErrorKind::ParenthesesMismatch, 0, 1);
+        parse_error("2 + x)", ParseErrorKind::ParenthesesMismatch, 5, 6);
+        parse_error(")", ParseErrorKind::ParenthesesMismatch, 0, 1);
+        parse_error("(2 + 1) 3 4", ParseErrorKind::MissingOperator, 10, 11);
+        parse_error("sin(x, 2)", ParseErrorKind::ArityMismatch, 0, 9);
+        parse_error("log(x, 2, 3)", ParseErrorKind::ArityMismatch, 0, 12);
+        parse_error("sin + 2", ParseErrorKind::DanglingOperator, 0, 3);
+        parse_error("2 + sin", ParseErrorKind::DanglingOperator, 4, 7);
+        parse_error("(1, 2)", ParseErrorKind::UnexpectedChar, 2, 3);
+
+        let explicit = ParseOptions::new(VariablePolicy::MultiLetter, false);
+        assert_eq!(
+            MathTree::parse_with("2x", explicit).map(|_| ()),
+            Err(ParseError::new(
+                ParseErrorKind::MissingOperator,
+                Span::new(1, 2)
+            ))
+        );
+    }
+
+    #[test]
+    fn implicit_multiplication() {
+        assert_eq!(
+            MathTree::parse("2x").unwrap().root,
+            MathTree::parse("2 * x").unwrap().root
+        );
+
+        assert_eq!(
+            MathTree::parse("3(x+1)").unwrap().root,
+            MathTree::parse("3 * (x + 1)").unwrap().root
+        );
+
+        assert_eq!(
+            MathTree::parse("(x+1)(x-1)").unwrap().root,
+            MathTree::parse("(x + 1) * (x - 1)").unwrap().root
+        );
+
+        assert_eq!(
+            MathTree::parse("2pi").unwrap().root,
+            MathTree::parse("2 * pi").unwrap().root
+        );
+
+        // implicit multiplication binds like *
+        assert_eq!(
+            MathTree::parse("2x^2 + 1").unwrap().root,
+            MathTree::parse("2 * x^2 + 1").unwrap().root
+        );
+
+        let single = ParseOptions::new(VariablePolicy::SingleLetter, true);
+        assert_eq!(
+            MathTree::parse_with("2xy", single).unwrap().root,
+            MathTree::parse("2 * x * y").unwrap().root
+        );
+    }
+
+    #[test]
+    fn parse_functions() {
+        assert_eq!(
+            MathTree::parse("2 * sin(x + 1)").unwrap().root,
+            TreeNodeRef::constant(dec!(2)).multiply(TreeNodeRef::new_vals(
+                MathToken::function(FunctionToken::Sin),
+                vec![MathTree::parse("x + 1").unwrap().root]
+            ))
+        );
+
+        assert_eq!(
+            MathTree::parse("log(x, -2)").unwrap().root,
+            TreeNodeRef::new_vals(
+                MathToken::function(FunctionToken::Log),
+                vec![
+                    TreeNodeRef::new_val(MathToken::variable("x".to_string().into())),
+                    TreeNodeRef::constant(dec!(-2)),
+                ]
+            )
+        );
+
+        // log defaults to base 10
+        assert_eq!(
+            MathTree::parse("log(100)").unwrap().root,
+            MathTree::parse("log(100, 10)").unwrap().root
+        );
+
+        assert_eq!(
+            MathTree::parse("sin x").unwrap().root,
+            MathTree::parse("sin(x)").unwrap().root
+        );
+
+        assert_eq!(
+            MathTree::parse("ln(exp(x))^2").unwrap().root,
+            MathTree::parse("(ln(exp(x)))^2").unwrap().root
+        );
+    }
+
+    #[test]
+    fn parse_roots() {
+        let root = |x: &str, n: TreeNodeRef| {
+            TreeNodeRef::new_vals(
+                MathToken::operator(OperationToken::Root),
+                vec![MathTree::parse(x).unwrap().root, n],
+            )
+        };
+
+        assert_eq!(
+            MathTree::parse("sqrt(x + 1)").unwrap().root,
+            root("x + 1", TreeNodeRef::two())
+        );
+        assert_eq!(
+            MathTree::parse("root(x, 3)").unwrap().root,
+            root("x", TreeNodeRef::constant(dec!(3)))
+        );
+        assert_eq!(
+            MathTree::parse("√x").unwrap().root,
+            root("x", TreeNodeRef::two())
+        );
+        assert_eq!(
+            MathTree::parse("2√(x + 1)").unwrap().root,
+            TreeNodeRef::two().multiply(root("x + 1", TreeNodeRef::two()))
+        );
+        parse_error("root(x)", ParseErrorKind::ArityMismatch, 0, 7);
+        parse_error("sqrt(x, 3)", ParseErrorKind::ArityMismatch, 0, 10);
+    }
+
+    #[test]
+    fn equality_ignores_slots() {
+        let add = |operands: Vec<TreeNodeRef>| {
+            TreeNodeRef::new_vals(MathToken::operator(OperationToken::Add), operands)
+        };
+        let x = TreeNodeRef::var("x");
+
+        let node = add(vec![x.clone(), TreeNodeRef::one(), TreeNodeRef::two()]);
+        let one = node
+            .borrow()
+            .calculate_iter()
+            .find(|(_, n)| n.val().constant == Some(Number::one()))
+            .unwrap()
+            .0;
+        node.borrow_mut().operands_mut().remove(one);
+        // x in slot 0 and 2 in slot 2, against slots 0 and 1
+        assert_eq!(node, add(vec![x.clone(), TreeNodeRef::two()]));
+        // the order still counts
+        assert_ne!(x.subtract(TreeNodeRef::two()), TreeNodeRef::two().subtract(x));
+    }
+
+    #[test]
+    fn latex_round_trip() {
+        // implicit products are written the way the infix parser reads them back,
+        // \pi and braced exponents need from_latex, see latex::tests::latex_round_trip
+        let single = ParseOptions::new(VariablePolicy::SingleLetter, true);
+        for txt in ["2 * x", "2 * (x + 1)", "(x + 1) * (x - 1)", "2 * x * y + 1"] {
+            let tree = MathTree::parse_with(txt, single).unwrap();
+            assert_eq!(
+                MathTree::parse_with(&tree.to_latex(), single).unwrap().root,
+                tree.root
+            );
+        }
+    }
+
+    #[test]
+    fn parse_unary_minus() {
+        let minus_one = TreeNodeRef::constant(dec!(-1));
+
+        assert_eq!(
+            MathTree::parse("-2").unwrap().root,
+            TreeNodeRef::constant(dec!(-2))
+        );
+        assert_eq!(
+            MathTree::parse("x * (-2.5)").unwrap().root,
+            TreeNodeRef::parse("x").multiply(TreeNodeRef::constant(dec!(-2.5)))
+        );
+        // -x is -1 * x
+        assert_eq!(
+            MathTree::parse("-x").unwrap().root,
+            minus_one.multiply(TreeNodeRef::parse("x"))
+        );
+        // -(2^2), not (-2)^2
+        assert_eq!(
+            MathTree::parse("-2^2").unwrap().root,
+            minus_one.multiply(TreeNodeRef::parse("2^2"))
+        );
+        assert_eq!(
+            MathTree::parse("-x + 1").unwrap().root,
+            MathTree::parse("(-1 * x) + 1").unwrap().root
+        );
+    }
+
+    #[test]
+    fn parse_unary_minus_after_operator() {
+        assert_eq!(
+            MathTree::parse("2^-2").unwrap().root,
+            TreeNodeRef::two().pow(TreeNodeRef::constant(-2))
+        );
+        assert_eq!(
+            MathTree::parse("x / -y").unwrap().root,
+            MathTree::parse("x / (-1 * y)").unwrap().root
+        );
+        assert_eq!(
+            MathTree::parse("2^-x").unwrap().root,
+            MathTree::parse("2^(-1 * x)").unwrap().root
+        );
+        assert_eq!(
+            MathTree::parse("2 * -x^2").unwrap().root,
+            MathTree::parse("2 * -1 * x^2").unwrap().root
+        );
+        // -(2^2), not (-2)^2
+        assert_eq!(
+            MathTree::parse("3 * -2^2").unwrap().root,
+            MathTree::parse("3 * -1 * 2^2").unwrap().root
+        );
+        parse_error("x / -y^2", ParseErrorKind::DanglingOperator, 4, 5);
+        parse_error("2^-", ParseErrorKind::DanglingOperator, 2, 3);
+    }
+
+    #[test]
+    // keys are not mutated while in the map
+    #[allow(clippy::mutable_key_type)]
+    fn tree_hash() {
+        let hash = |txt: &str| TreeNodeRef::parse(txt).structural_hash();
+
+        assert_eq!(hash("2 * x + 1"), hash("2 * x + 1"));
+        assert_eq!(hash("x + y + 2"), hash("2 + y + x"));
+        assert_eq!(hash("x * sin(x) * 3"), hash("sin(x) * 3 * x"));
+        assert_eq!(hash("1.50 + x"), hash("x + 1.5"));
+        assert_ne!(hash("x - 1"), hash("1 - x"));
+        assert_ne!(hash("x^2"), hash("2^x"));
+        assert_ne!(hash("x + 1"), hash("x * 1"));
+
+        let mut cache = HashMap::new();
+        cache.insert(TreeNodeRef::parse("(x + 1) / 2"), 1);
+        assert_eq!(cache.get(&TreeNodeRef::parse("(x + 1) / 2")), Some(&1));
+        assert_eq!(cache.get(&TreeNodeRef::parse("(x + 2) / 2")), None);
+    }
+
+    #[test]
+    fn tree_hash_mutation() {
+        let tree = TreeNodeRef::parse("(x + 1) * 2");
+        let before = tree.structural_hash();
+
+        // the cached hash of the parent is stale after the child changes
+        let child = tree.borrow().calculate_iter().last().unwrap().1.clone();
+        child.replace(TreeNodeRef::parse("x + 2"));
+
+        assert_ne!(tree.structural_hash(), before);
+        assert_eq!(
+            tree.structural_hash(),
+            TreeNodeRef::parse("(x + 2) * 2").structural_hash()
+        );
+    }
+
+    #[test]
+    fn tree_hash_invalidates_ancestors_only() {
+        // x + 1 shared by two parents
+        let shared = TreeNodeRef::parse("x + 1");
+        let a = shared.multiply(TreeNodeRef::two());
+        let b = TreeNodeRef::call(FunctionToken::Sin, vec![shared.clone()]);
+        let other = TreeNodeRef::parse("y * 3");
+        let (a_before, b_before) = (a.structural_hash(), b.structural_hash());
+        let other_before = other.structural_hash();
+
+        shared.add_operand(TreeNodeRef::var("z"));
+
+        // an unrelated tree keeps its cached hash
+        assert_eq!(other.borrow().hash.get(), Some(other_before));
+        assert_eq!(a.borrow().hash.get(), None);
+        assert_eq!(b.borrow().hash.get(), None);
+        assert_ne!(a.structural_hash(), a_before);
+        assert_ne!(b.structural_hash(), b_before);
+        assert_eq!(
+            b.structural_hash(),
+            TreeNodeRef::parse("sin(x + 1 + z)").structural_hash()
+        );
+    }
+
+    #[test]
+    fn cloned_node_hash_follows_shared_operands() {
+        let shared = TreeNodeRef::parse("x + 1");
+        let node = shared.multiply(TreeNodeRef::two());
+        node.structural_hash();
+
+        let copy = TreeNodeRef(Rc::new(RefCell::new(node.borrow().clone())));
+        let before = copy.structural_hash();
+        shared.add_operand(TreeNodeRef::var("z"));
+
+        assert_ne!(copy.structural_hash(), before);
+        assert_eq!(copy.structural_hash(), node.structural_hash());
+        assert_eq!(
+            copy.structural_hash(),
+            TreeNodeRef::parse("(x + 1 + z) * 2").structural_hash()
+        );
+    }
 }
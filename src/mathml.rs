@@ -0,0 +1,434 @@
+// MathML for screen readers: presentation markup describes the layout,
+// content markup the meaning (apply, plus, root...)
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    math_tree::{MathTree, TreeNodeRef},
+    number::Number,
+    number_theory::{factorint, write_factors},
+    render::{Emitter, TermEmitter},
+    FunctionToken, MathTokenType, OperationToken,
+};
+
+const MATH: &str = "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">";
+
+// U+2062, read as nothing or "times" depending on the reader: 2x
+const INVISIBLE_TIMES: &str = "<mo>&#x2062;</mo>";
+// U+2061, sin x
+const APPLY_FUNCTION: &str = "<mo>&#x2061;</mo>";
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl MathTree {
+    pub fn to_mathml_presentation(&self) -> String {
+        self.root.to_mathml_presentation()
+    }
+
+    pub fn to_mathml_content(&self) -> String {
+        self.root.to_mathml_content()
+    }
+}
+
+impl TreeNodeRef {
+    pub fn to_mathml_presentation(&self) -> String {
+        format!("{}{}</math>", MATH, presentation(self))
+    }
+
+    pub fn to_mathml_content(&self) -> String {
+        format!("{}{}</math>", MATH, content(self))
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn mrow(inner: &str) -> String {
+    format!("<mrow>{}</mrow>", inner)
+}
+
+fn parenthesize(inner: &str) -> String {
+    mrow(&format!("<mo>(</mo>{}<mo>)</mo>", inner))
+}
+
+// 1/3 as a fraction, decimals when they terminate
+fn number(c: Number) -> String {
+    if c.is_sign_negative() {
+        return mrow(&format!("<mo>-</mo>{}", number(-c)));
+    }
+
+    if c.terminates() {
+        format!("<mn>{}</mn>", c)
+    } else {
+        format!("<mfrac><mn>{}</mn><mn>{}</mn></mfrac>", c.numer(), c.denom())
+    }
+}
+
+fn childs(node: &TreeNodeRef) -> Vec<TreeNodeRef> {
+    node.borrow()
+        .display_iter()
+        .map(|(_, n)| n.clone())
+        .collect()
+}
+
+// node on its own, never parenthesized
+fn presentation(node: &TreeNodeRef) -> String {
+    let val = node.val();
+    match val.kind {
+        MathTokenType::Constant => number(val.constant.unwrap()),
+        MathTokenType::Variable => match val.variable.unwrap().as_str() {
+            "pi" | "π" => String::from("<mi>π</mi>"),
+            name => format!("<mi>{}</mi>", escape(name)),
+        },
+        MathTokenType::Function => presentation_function(node, val.function.unwrap()),
+        MathTokenType::Operator => presentation_operator(node, val.operation.unwrap()),
+    }
+}
+
+// the presentation markup, on the shared precedence and sign handling
+struct Presentation;
+
+impl Emitter for Presentation {
+    type Output = String;
+
+    fn write(&self, node: &TreeNodeRef) -> String {
+        presentation(node)
+    }
+
+    fn precedence(&self, node: &TreeNodeRef) -> i8 {
+        match node.val().operation {
+            // grouped by the radical and the fraction bar
+            Some(OperationToken::Root | OperationToken::Divide) => i8::MAX,
+            Some(op) => op.info().precedence,
+            None if factorint(node).is_some() => OperationToken::Multiply.info().precedence,
+            _ => i8::MAX,
+        }
+    }
+
+    fn group(&self, res: String) -> String {
+        parenthesize(&res)
+    }
+
+    // x - (-2), 2(-x)
+    fn leads_with_minus(&self, node: &TreeNodeRef, _: &String) -> bool {
+        let val = node.val();
+        let lead = match val.operation {
+            Some(OperationToken::Multiply) => childs(node)[0].val(),
+            _ => val,
+        };
+        lead.constant.as_ref().is_some_and(|c| c.is_sign_negative())
+    }
+}
+
+impl TermEmitter for Presentation {
+    fn constant(&self, c: &Number) -> String {
+        number(c.clone())
+    }
+
+    fn symbol(&self, op: OperationToken) -> String {
+        format!("<mo>{}</mo>", op.to_char())
+    }
+
+    fn minus(&self) -> String {
+        String::from("<mo>-</mo>")
+    }
+
+    fn times(
+        &self,
+        _: &String,
+        _: Option<&Number>,
+        factor: &TreeNodeRef,
+        _: &String,
+    ) -> Option<String> {
+        // 25 is not 2 * 5
+        Some(String::from(match factor.val().kind {
+            MathTokenType::Constant => "<mo>·</mo>",
+            _ => INVISIBLE_TIMES,
+        }))
+    }
+
+    fn join(&self, parts: Vec<String>) -> String {
+        mrow(&parts.concat())
+    }
+}
+
+fn presentation_operator(node: &TreeNodeRef, op: OperationToken) -> String {
+    let childs = childs(node);
+    let precedence = op.info().precedence;
+
+    match op {
+        OperationToken::Add => Presentation.sum(&childs),
+        OperationToken::Subtract => mrow(&format!(
+            "{}<mo>-</mo>{}",
+            presentation(&childs[0]),
+            Presentation.operand(&childs[1], precedence, true)
+        )),
+        OperationToken::Multiply => Presentation.multiply(&childs),
+        OperationToken::Divide => format!(
+            "<mfrac>{}{}</mfrac>",
+            presentation(&childs[0]),
+            presentation(&childs[1])
+        ),
+        OperationToken::Pow => {
+            let base = presentation(&childs[0]);
+            // (x+1)^2, (-2)^2, (x^2)^3, (1/3)^2
+            let base = if (childs[0].val().kind == MathTokenType::Operator
+                && childs[0].val().operation != Some(OperationToken::Root))
+                || childs[0]
+                    .val()
+                    .constant
+                    .is_some_and(|c| c.is_sign_negative() || !c.terminates())
+                || factorint(&childs[0]).is_some()
+            {
+                parenthesize(&base)
+            } else {
+                base
+            };
+
+            format!("<msup>{}{}</msup>", base, presentation(&childs[1]))
+        }
+        OperationToken::Root => {
+            if childs[1].val().constant == Some(Number::two()) {
+                format!("<msqrt>{}</msqrt>", presentation(&childs[0]))
+            } else {
+                format!(
+                    "<mroot>{}{}</mroot>",
+                    presentation(&childs[0]),
+                    presentation(&childs[1])
+                )
+            }
+        }
+        OperationToken::LParent | OperationToken::RParent | OperationToken::Comma => {
+            unreachable!()
+        }
+    }
+}
+
+fn presentation_function(node: &TreeNodeRef, f: FunctionToken) -> String {
+    // 2^3·3^2·5
+    if let Some(factors) = factorint(node) {
+        return mrow(&write_factors(&factors, "<mo>·</mo>", |p, k| {
+            format!("<msup><mn>{}</mn><mn>{}</mn></msup>", p, k)
+        }));
+    }
+
+    let borrow = node.borrow();
+    let mut args = borrow.calculate_iter().map(|x| x.1);
+    let arg = presentation(args.next().unwrap());
+
+    let name = match f {
+        FunctionToken::Abs => return mrow(&format!("<mo>|</mo>{}<mo>|</mo>", arg)),
+        FunctionToken::Conj => return format!("<mover>{}<mo>&#xAF;</mo></mover>", arg),
+        FunctionToken::Log => {
+            let base = args.next().unwrap();
+            // base 10 is implied
+            if base.val().constant == Some(Number::ten()) {
+                String::from("<mi>log</mi>")
+            } else {
+                format!("<msub><mi>log</mi>{}</msub>", presentation(base))
+            }
+        }
+        // gcd(12, 18)
+        FunctionToken::Gcd | FunctionToken::Lcm => {
+            let args = std::iter::once(arg)
+                .chain(args.map(presentation))
+                .collect::<Vec<_>>()
+                .join("<mo>,</mo>");
+            let name = format!("<mi>{}</mi>", f.name());
+            return mrow(&format!(
+                "{}{}{}",
+                name,
+                APPLY_FUNCTION,
+                parenthesize(&args)
+            ));
+        }
+        _ => format!("<mi>{}</mi>", f.name()),
+    };
+
+    mrow(&format!("{}{}{}", name, APPLY_FUNCTION, parenthesize(&arg)))
+}
+
+fn apply(head: &str, args: &[String]) -> String {
+    format!("<apply><{}/>{}</apply>", head, args.concat())
+}
+
+fn content(node: &TreeNodeRef) -> String {
+    let val = node.val();
+    match val.kind {
+        MathTokenType::Constant => match val.constant.unwrap() {
+            c if c.terminates() => format!("<cn>{}</cn>", c),
+            c => format!("<cn type=\"rational\">{}<sep/>{}</cn>", c.numer(), c.denom()),
+        },
+        MathTokenType::Variable => match val.variable.unwrap().as_str() {
+            "pi" | "π" => String::from("<pi/>"),
+            "e" => String::from("<exponentiale/>"),
+            "i" => String::from("<imaginaryi/>"),
+            name => format!("<ci>{}</ci>", escape(name)),
+        },
+        MathTokenType::Function => {
+            let f = val.function.unwrap();
+            let borrow = node.borrow();
+            let mut args = borrow.calculate_iter().map(|(_, n)| content(n));
+            let arg = args.next().unwrap();
+
+            match f {
+                FunctionToken::Log => {
+                    let base = borrow.calculate_iter().nth(1).unwrap().1;
+                    // logbase defaults to 10
+                    if base.val().constant == Some(Number::ten()) {
+                        apply("log", &[arg])
+                    } else {
+                        let base = format!("<logbase>{}</logbase>", content(base));
+                        apply("log", &[base, arg])
+                    }
+                }
+                FunctionToken::Re => apply("real", &[arg]),
+                FunctionToken::Im => apply("imaginary", &[arg]),
+                FunctionToken::Conj => apply("conjugate", &[arg]),
+                FunctionToken::Gcd | FunctionToken::Lcm => {
+                    let rest = args.collect::<Vec<_>>();
+                    apply(f.name(), &[vec![arg], rest].concat())
+                }
+                // the same number, factoring is only presentation
+                FunctionToken::Factorint => arg,
+                _ => apply(f.name(), &[arg]),
+            }
+        }
+        MathTokenType::Operator => {
+            let childs = childs(node);
+            let args = childs.iter().map(content).collect::<Vec<_>>();
+
+            match val.operation.unwrap() {
+                OperationToken::Add => apply("plus", &args),
+                OperationToken::Subtract => apply("minus", &args),
+                // -x, a single operand minus
+                OperationToken::Multiply
+                    if childs[0].val().constant == Some(Number::neg_one()) =>
+                {
+                    match args.len() {
+                        2 => apply("minus", &args[1..]),
+                        _ => apply("minus", &[apply("times", &args[1..])]),
+                    }
+                }
+                OperationToken::Multiply => apply("times", &args),
+                OperationToken::Divide => apply("divide", &args),
+                OperationToken::Pow => apply("power", &args),
+                // degree defaults to 2
+                OperationToken::Root if childs[1].val().constant == Some(Number::two()) => {
+                    apply("root", &args[..1])
+                }
+                OperationToken::Root => {
+                    let degree = format!("<degree>{}</degree>", args[1]);
+                    apply("root", &[degree, args[0].clone()])
+                }
+                OperationToken::LParent | OperationToken::RParent | OperationToken::Comma => {
+                    unreachable!()
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::math_tree::MathTree;
+
+    fn presentation(txt: &str) -> String {
+        let res = MathTree::parse(txt).unwrap().to_mathml_presentation();
+        res.strip_prefix("<math xmlns=\"http://www.w3.org/1998/Math/MathML\">")
+            .and_then(|res| res.strip_suffix("</math>"))
+            .unwrap()
+            .to_string()
+    }
+
+    fn content(txt: &str) -> String {
+        let res = MathTree::parse(txt).unwrap().to_mathml_content();
+        res.strip_prefix("<math xmlns=\"http://www.w3.org/1998/Math/MathML\">")
+            .and_then(|res| res.strip_suffix("</math>"))
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn mathml_presentation() {
+        assert_eq!(
+            MathTree::parse("x").unwrap().to_mathml_presentation(),
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mi>x</mi></math>"
+        );
+        assert_eq!(
+            presentation("2 * x + 1"),
+            "<mrow><mrow><mn>2</mn><mo>&#x2062;</mo><mi>x</mi></mrow><mo>+</mo><mn>1</mn></mrow>"
+        );
+        assert_eq!(
+            presentation("2 * 5"),
+            "<mrow><mn>2</mn><mo>·</mo><mn>5</mn></mrow>"
+        );
+        assert_eq!(
+            presentation("x - (y + 1)"),
+            "<mrow><mi>x</mi><mo>-</mo><mrow><mo>(</mo><mrow><mi>y</mi><mo>+</mo><mn>1</mn></mrow><mo>)</mo></mrow></mrow>"
+        );
+        assert_eq!(
+            presentation("x + (-2)"),
+            "<mrow><mi>x</mi><mo>-</mo><mn>2</mn></mrow>"
+        );
+        assert_eq!(presentation("-x"), "<mrow><mo>-</mo><mi>x</mi></mrow>");
+        assert_eq!(
+            presentation("(x + 1)^2 / 2"),
+            "<mfrac><msup><mrow><mo>(</mo><mrow><mi>x</mi><mo>+</mo><mn>1</mn></mrow><mo>)</mo></mrow><mn>2</mn></msup><mn>2</mn></mfrac>"
+        );
+        assert_eq!(
+            presentation("sqrt(x) * root(x, 3)"),
+            "<mrow><msqrt><mi>x</mi></msqrt><mo>&#x2062;</mo><mroot><mi>x</mi><mn>3</mn></mroot></mrow>"
+        );
+        assert_eq!(
+            presentation("sin(pi)"),
+            "<mrow><mi>sin</mi><mo>&#x2061;</mo><mrow><mo>(</mo><mi>π</mi><mo>)</mo></mrow></mrow>"
+        );
+        assert_eq!(
+            presentation("log(x, 2)"),
+            "<mrow><msub><mi>log</mi><mn>2</mn></msub><mo>&#x2061;</mo><mrow><mo>(</mo><mi>x</mi><mo>)</mo></mrow></mrow>"
+        );
+        assert_eq!(
+            presentation("abs(x)"),
+            "<mrow><mo>|</mo><mi>x</mi><mo>|</mo></mrow>"
+        );
+    }
+
+    #[test]
+    fn mathml_content() {
+        assert_eq!(
+            MathTree::parse("x").unwrap().to_mathml_content(),
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><ci>x</ci></math>"
+        );
+        assert_eq!(
+            content("2 * x + 1"),
+            "<apply><plus/><apply><times/><cn>2</cn><ci>x</ci></apply><cn>1</cn></apply>"
+        );
+        assert_eq!(
+            content("x - 1 / 2"),
+            "<apply><minus/><ci>x</ci><apply><divide/><cn>1</cn><cn>2</cn></apply></apply>"
+        );
+        assert_eq!(
+            content("-x^2"),
+            "<apply><minus/><apply><power/><ci>x</ci><cn>2</cn></apply></apply>"
+        );
+        assert_eq!(content("-2"), "<cn>-2</cn>");
+        assert_eq!(
+            content("sqrt(x) + root(x, 3)"),
+            "<apply><plus/><apply><root/><ci>x</ci></apply><apply><root/><degree><cn>3</cn></degree><ci>x</ci></apply></apply>"
+        );
+        assert_eq!(
+            content("log(x) * log(x, 2)"),
+            "<apply><times/><apply><log/><ci>x</ci></apply><apply><log/><logbase><cn>2</cn></logbase><ci>x</ci></apply></apply>"
+        );
+        assert_eq!(
+            content("e^(pi * x) + abs(x)"),
+            "<apply><plus/><apply><power/><exponentiale/><apply><times/><pi/><ci>x</ci></apply></apply><apply><abs/><ci>x</ci></apply></apply>"
+        );
+    }
+}
@@ -0,0 +1,264 @@
+// arithmetic modulo n for congruences: every constant is a residue 0..n,
+// division multiplies by the modular inverse and powers use fast modular exponentiation
+
+use std::ops::{Add, Div, Mul, Sub};
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed};
+
+use crate::{
+    arithmatic::arithmatic::{perform_op_constant, OperationError, Pow},
+    math_tree::{MathTree, TreeNodeRef},
+    number::Number,
+    stepper::Steps,
+    MathTokenType, OperationToken,
+};
+
+// a residue mod modulus, None once an operation had no result (division without an inverse)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Modular {
+    pub value: Option<BigInt>,
+    pub modulus: BigInt,
+}
+
+impl Modular {
+    pub fn new(value: &BigInt, modulus: &BigInt) -> Self {
+        Self {
+            value: Some(value.mod_floor(modulus)),
+            modulus: modulus.clone(),
+        }
+    }
+
+    // 3/4 is 3 * 4^-1, None when 4 has no inverse
+    pub fn from_number(n: &Number, modulus: &BigInt) -> Self {
+        Self::new(n.numer(), modulus) / Self::new(n.denom(), modulus)
+    }
+
+    fn with(&self, value: Option<BigInt>) -> Self {
+        Self {
+            value: value.map(|v| v.mod_floor(&self.modulus)),
+            modulus: self.modulus.clone(),
+        }
+    }
+
+    // x such that a * x = 1, only when gcd(a, n) = 1
+    pub fn inverse(&self) -> Self {
+        self.with(self.value.as_ref().and_then(|a| {
+            let gcd = a.extended_gcd(&self.modulus);
+            gcd.gcd.is_one().then_some(gcd.x)
+        }))
+    }
+
+    // a^e by repeated squaring, e is a whole exponent and not a residue: 2^7 = 2 (mod 7)
+    pub fn pow_exponent(&self, exponent: &BigInt) -> Self {
+        let base = if exponent.is_negative() {
+            self.inverse()
+        } else {
+            self.clone()
+        };
+        self.with(base.value.map(|a| a.modpow(&exponent.abs(), &self.modulus)))
+    }
+
+    pub fn to_node(&self) -> Result<TreeNodeRef, OperationError> {
+        match &self.value {
+            Some(v) => Ok(TreeNodeRef::constant(v.clone())),
+            None => Err(OperationError::NotInvertible),
+        }
+    }
+}
+
+impl Add for Modular {
+    type Output = Modular;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.with(self.value.as_ref().zip(rhs.value).map(|(a, b)| a + b))
+    }
+}
+
+impl Sub for Modular {
+    type Output = Modular;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.with(self.value.as_ref().zip(rhs.value).map(|(a, b)| a - b))
+    }
+}
+
+impl Mul for Modular {
+    type Output = Modular;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.with(self.value.as_ref().zip(rhs.value).map(|(a, b)| a * b))
+    }
+}
+
+impl Div for Modular {
+    type Output = Modular;
+
+    // a / b = a * b^-1
+    fn div(self, rhs: Self) -> Self::Output {
+        self.mul(rhs.inverse())
+    }
+}
+
+impl Pow for Modular {
+    // b holds the exponent itself, see pow_exponent
+    fn pow(&self, b: Self) -> Self {
+        match b.value {
+            Some(exponent) => self.pow_exponent(&exponent),
+            None => self.with(None),
+        }
+    }
+
+    // no modular roots
+    fn root(&self, _b: Self) -> Self {
+        self.with(None)
+    }
+}
+
+impl MathTree {
+    // simplifies with every constant reduced mod n: 3x + 10 = 3x + 3 (mod 7), 2^100 = 2 (mod 7),
+    // x / 2 = 4x (mod 7). errors with NotInvertible for a division by a non invertible residue,
+    // 1 / 2 (mod 4), or by anything but a constant: x / y
+    pub fn simplify_mod(&mut self, n: impl Into<BigInt>) -> Result<(), OperationError> {
+        let n = n.into();
+        debug_assert!(n.is_positive(), "modulus must be positive");
+
+        self.root = Self::reduce_mod_node(&self.root, &n)?;
+        // simplifying may bring back fractions and negatives: -3x, x / 2
+        loop {
+            self.simplify(&mut Steps::new())?;
+            let reduced = Self::reduce_mod_node(&self.root, &n)?;
+            if reduced == self.root {
+                return Ok(());
+            }
+            self.root = reduced;
+        }
+    }
+
+    fn reduce_mod_node(node: &TreeNodeRef, n: &BigInt) -> Result<TreeNodeRef, OperationError> {
+        let val = node.val();
+        if let Some(c) = &val.constant {
+            return Modular::from_number(c, n).to_node();
+        }
+        if val.kind == MathTokenType::Variable {
+            return Ok(node.clone());
+        }
+
+        let children = node
+            .borrow()
+            .calculate_iter()
+            .map(|(_, c)| c.clone())
+            .collect::<Vec<_>>();
+
+        // the exponent is a whole number, not a residue
+        if val.operation == Some(OperationToken::Pow) {
+            let base = Self::reduce_mod_node(&children[0], n)?;
+            let mut exponent = MathTree {
+                root: children[1].clone(),
+                bounds: Default::default(),
+            }
+            .copy();
+            exponent.simplify(&mut Steps::new())?;
+
+            let exponent = exponent.root;
+            return match (base.val().constant, exponent.val().constant) {
+                (Some(b), Some(e)) if e.is_integer() => {
+                    Modular::new(b.numer(), n).pow_exponent(e.numer()).to_node()
+                }
+                _ => Ok(base.pow(exponent)),
+            };
+        }
+
+        let children = children
+            .iter()
+            .map(|c| Self::reduce_mod_node(c, n))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // 3 * 5 = 1 (mod 7), 4 / 3 = 6 (mod 7), roots are left to simplify
+        match val.operation {
+            Some(op)
+                if op != OperationToken::Root
+                    && children.iter().all(|c| c.val().constant.is_some()) =>
+            {
+                let mut residues = children
+                    .iter()
+                    .map(|c| Modular::from_number(&c.val().constant.unwrap(), n));
+                let first = residues.next().unwrap();
+                residues
+                    .fold(first, |a, b| perform_op_constant(a, b, op))
+                    .to_node()
+            }
+            // residues have no fractions: x / 2 = 4x (mod 7)
+            Some(OperationToken::Divide) => match children[1].val().constant {
+                Some(d) => {
+                    let inverse = Modular::from_number(&d, n).inverse().to_node()?;
+                    Ok(children[0].multiply(inverse))
+                }
+                // x / y, y may have no inverse
+                None => Err(OperationError::NotInvertible),
+            },
+            _ => Ok(TreeNodeRef::new_vals(val, children)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigInt;
+    use pretty_assertions::assert_eq;
+
+    use super::Modular;
+    use crate::{
+        arithmatic::arithmatic::OperationError,
+        math_tree::{MathTree, TreeNodeRef},
+        stepper::Steps,
+    };
+
+    fn simplify_mod_test(expr: &str, n: i32, res: TreeNodeRef) {
+        let mut tree = MathTree::parse(expr).unwrap();
+        tree.simplify_mod(n).unwrap();
+        assert_eq!(tree.root, res);
+    }
+
+    #[test]
+    fn modular_constants() {
+        simplify_mod_test("10 + 5", 7, TreeNodeRef::constant(1));
+        simplify_mod_test("3 - 5", 7, TreeNodeRef::constant(5));
+        simplify_mod_test("4 / 3", 7, TreeNodeRef::constant(6));
+        simplify_mod_test("1 / 2", 7, TreeNodeRef::constant(4));
+        // far past Number::max_bits without reducing
+        simplify_mod_test("2^1000000", 7, TreeNodeRef::constant(2));
+        simplify_mod_test("3^-1", 7, TreeNodeRef::constant(5));
+
+        // 2^-3 = 8^-1 = 1
+        simplify_mod_test("2^-3", 7, TreeNodeRef::one());
+
+        let mut tree = MathTree::parse("1 / 2").unwrap();
+        assert_eq!(tree.simplify_mod(4), Err(OperationError::NotInvertible));
+        let mut tree = MathTree::parse("2^-1").unwrap();
+        assert_eq!(tree.simplify_mod(4), Err(OperationError::NotInvertible));
+
+        let n = BigInt::from(13);
+        assert_eq!(
+            Modular::new(&BigInt::from(5), &n).inverse(),
+            Modular::new(&BigInt::from(8), &n)
+        );
+    }
+
+    #[test]
+    fn modular_expressions() {
+        let mut res = MathTree::parse("3x + 3").unwrap();
+        res.simplify(&mut Steps::new()).unwrap();
+        simplify_mod_test("3x + 10", 7, res.root);
+        simplify_mod_test("8x", 7, TreeNodeRef::parse("x"));
+        simplify_mod_test("x * 2^100", 7, TreeNodeRef::parse("2x"));
+        simplify_mod_test("x / 2", 7, TreeNodeRef::parse("4x"));
+        simplify_mod_test("(x + 1) / 3", 7, TreeNodeRef::parse("5(x + 1)"));
+
+        let mut tree = MathTree::parse("x / 2").unwrap();
+        assert_eq!(tree.simplify_mod(4), Err(OperationError::NotInvertible));
+        let mut tree = MathTree::parse("x / y").unwrap();
+        assert_eq!(tree.simplify_mod(7), Err(OperationError::NotInvertible));
+    }
+}
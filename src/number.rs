@@ -0,0 +1,798 @@
+// exact rational constants: 0.1 is 1/10 and 1/3 * 3 is 1,
+// Decimal and f64 are only used when a numeric value is asked for
+
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Rem, Sub, SubAssign},
+    str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Pow, Signed, ToPrimitive, Zero};
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::arithmatic::arithmatic::OperationError;
+
+// largest numerator or denominator in bits, exact results past it are OperationError::Overflow.
+// shared by every thread
+static MAX_BITS: AtomicU64 = AtomicU64::new(DEFAULT_MAX_BITS);
+const DEFAULT_MAX_BITS: u64 = 1 << 17;
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Number(BigRational);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseNumberError;
+
+// how renderers write a constant that is not a whole number
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NumberStyle {
+    // decimals when they terminate, fractions otherwise: 0.125, 1/3
+    #[default]
+    Auto,
+    // 1/8, 1/3
+    Fraction,
+    // 0.125, 0.(3)
+    Decimal,
+    // 2 1/2 for 5/2, fractions below one as is
+    Mixed,
+}
+
+// the shape a constant takes under a NumberStyle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NumberForm {
+    // 2, 0.125, or the approximate value of a long period: 0.0103092783505154639175257732
+    Decimal,
+    // 0.1(6): the digits before the repeating block and the block
+    Repeating(String, String),
+    Fraction,
+    // 2 1/2: the whole part and the positive fraction that follows it
+    Mixed(BigInt, Number),
+}
+
+// longest period written as a repeating decimal, 1/97 repeats after 96 digits
+const MAX_PERIOD: usize = 32;
+
+impl NumberStyle {
+    pub fn form(&self, c: &Number) -> NumberForm {
+        if c.is_integer() {
+            return NumberForm::Decimal;
+        }
+
+        match self {
+            NumberStyle::Auto if c.terminates() => NumberForm::Decimal,
+            NumberStyle::Auto | NumberStyle::Fraction => NumberForm::Fraction,
+            NumberStyle::Decimal => match c.repeating_decimal() {
+                Some((start, period)) => NumberForm::Repeating(start, period),
+                None => NumberForm::Decimal,
+            },
+            NumberStyle::Mixed => match c.mixed() {
+                Some((whole, fraction)) => NumberForm::Mixed(whole, fraction),
+                None => NumberForm::Fraction,
+            },
+        }
+    }
+}
+
+impl Number {
+    // numer / denom in lowest terms, panics on a zero denominator
+    pub fn new(numer: impl Into<BigInt>, denom: impl Into<BigInt>) -> Self {
+        Self(BigRational::new(numer.into(), denom.into()))
+    }
+
+    pub fn zero() -> Self {
+        Self(BigRational::zero())
+    }
+
+    pub fn one() -> Self {
+        Self(BigRational::one())
+    }
+
+    pub fn two() -> Self {
+        Self::from(2)
+    }
+
+    pub fn ten() -> Self {
+        Self::from(10)
+    }
+
+    pub fn neg_one() -> Self {
+        Self::from(-1)
+    }
+
+    pub fn numer(&self) -> &BigInt {
+        self.0.numer()
+    }
+
+    // always positive
+    pub fn denom(&self) -> &BigInt {
+        self.0.denom()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.0.is_one()
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.0.is_integer()
+    }
+
+    pub fn is_sign_negative(&self) -> bool {
+        self.0.is_negative()
+    }
+
+    pub fn abs(&self) -> Self {
+        Self(self.0.abs())
+    }
+
+    // towards zero
+    pub fn trunc(&self) -> Self {
+        Self(self.0.trunc())
+    }
+
+    pub fn fract(&self) -> Self {
+        Self(self.0.fract())
+    }
+
+    pub fn recip(&self) -> Option<Self> {
+        (!self.is_zero()).then(|| Self(self.0.recip()))
+    }
+
+    pub fn checked_div(&self, other: &Number) -> Option<Self> {
+        (!other.is_zero()).then(|| Self(&self.0 / &other.0))
+    }
+
+    pub fn checked_rem(&self, other: &Number) -> Option<Self> {
+        (!other.is_zero()).then(|| Self(&self.0 % &other.0))
+    }
+
+    // whole exponents only, None for 0^-1
+    pub fn powi(&self, exp: i32) -> Option<Self> {
+        if exp < 0 && self.is_zero() {
+            return None;
+        }
+        Some(Self(Pow::pow(&self.0, exp)))
+    }
+
+    // exact self^exp: 4^0.5 = 2, 8^(2/3) = 4, None for 2^0.5,
+    // Err when the result would pass max_bits: 2^(10^9)
+    pub fn pow(&self, exp: &Number) -> Result<Option<Self>, OperationError> {
+        let numer = if self.is_zero() || self.abs().is_one() {
+            // never grows, only the sign and parity matter: (-1)^1001 = -1
+            let parity = if exp.numer().is_odd() { 1 } else { 2 };
+            exp.numer().signum() * parity
+        } else {
+            exp.numer().clone()
+        };
+
+        // bits of the result are at most bits * n
+        if numer.abs() * self.bits() > BigInt::from(Self::max_bits()) {
+            return Err(OperationError::Overflow);
+        }
+
+        let Some(power) = self.powi(numer.to_i32().ok_or(OperationError::Overflow)?) else {
+            return Ok(None);
+        };
+        Ok(match exp.denom().to_u32() {
+            Some(1) => Some(power),
+            Some(n) => power.nth_root(n),
+            None => None,
+        })
+    }
+
+    // exact nth root: root(16, 2) = 4, root(-8, 3) = -2, root(0.25, 2) = 0.5
+    pub fn nth_root(&self, n: u32) -> Option<Self> {
+        if n == 0 || (self.is_sign_negative() && n.is_multiple_of(2)) {
+            return None;
+        }
+
+        let root = |i: &BigInt| {
+            let r = i.nth_root(n);
+            (Pow::pow(&r, n) == *i).then_some(r)
+        };
+        Some(Self::new(root(self.numer())?, root(self.denom())?))
+    }
+
+    // size of the larger of numerator and denominator
+    pub fn bits(&self) -> u64 {
+        self.numer().bits().max(self.denom().bits())
+    }
+
+    pub fn max_bits() -> u64 {
+        MAX_BITS.load(Ordering::Relaxed)
+    }
+
+    // limits exact results for the whole process, 2^131072 and above are an error by default
+    pub fn set_max_bits(bits: u64) {
+        MAX_BITS.store(bits, Ordering::Relaxed);
+    }
+
+    // self when within max_bits
+    pub fn bounded(self) -> Result<Self, OperationError> {
+        if self.bits() > Self::max_bits() {
+            Err(OperationError::Overflow)
+        } else {
+            Ok(self)
+        }
+    }
+
+    // the decimal expansion ends: 1/8 = 0.125, but not 1/3
+    pub fn terminates(&self) -> bool {
+        let mut denom = self.denom().clone();
+        for p in [2, 5] {
+            let p = BigInt::from(p);
+            while denom.is_multiple_of(&p) {
+                denom /= &p;
+            }
+        }
+        denom.is_one()
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        self.is_integer().then(|| self.numer().to_i64()).flatten()
+    }
+
+    pub fn to_u32(&self) -> Option<u32> {
+        self.is_integer().then(|| self.numer().to_u32()).flatten()
+    }
+
+    pub fn to_u128(&self) -> Option<u128> {
+        self.is_integer().then(|| self.numer().to_u128()).flatten()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.0.to_f64().unwrap_or(f64::NAN)
+    }
+
+    // rounded to Decimal's 28 digits, None when out of its range
+    pub fn to_decimal(&self) -> Option<Decimal> {
+        let numer = Decimal::from_i128(self.numer().to_i128()?)?;
+        let denom = Decimal::from_i128(self.denom().to_i128()?)?;
+        numer.checked_div(denom).map(|d| d.normalize())
+    }
+
+    // numeric value as a constant that prints as a decimal: 1/3 = 0.3333333333333333333333333333
+    pub fn approximate(&self) -> Self {
+        if self.terminates() {
+            return self.clone();
+        }
+        // beyond 96 bits, through f64
+        match self
+            .to_decimal()
+            .or_else(|| Decimal::from_f64(self.to_f64()))
+        {
+            Some(d) => Self::from(d),
+            None => self.clone(),
+        }
+    }
+
+    // start followed by period repeated forever: ("0.1", "6") is 1/6
+    fn from_repeating(start: &str, period: &str) -> Result<Self, ParseNumberError> {
+        let Some((_, fraction)) = start.split_once('.') else {
+            return Err(ParseNumberError);
+        };
+        if period.is_empty() || !period.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseNumberError);
+        }
+        // both powers of ten below
+        if (fraction.len() + period.len()) as u64 * 4 > Self::max_bits() {
+            return Err(ParseNumberError);
+        }
+
+        let start_value = Self::from_str(start)?;
+        let nines = Self::from(10).powi(period.len() as i32).unwrap() - Self::one();
+        let scale = Self::from(10).powi(-(fraction.len() as i32)).unwrap();
+        let repeating = Self::from_str(period)? / nines * scale;
+        Ok(if start.starts_with('-') {
+            start_value - repeating
+        } else {
+            start_value + repeating
+        })
+    }
+
+    // exact value of a float: 0.1 is 3602879701896397/36028797018963968, None for NaN and infinities
+    pub fn from_f64(x: f64) -> Option<Self> {
+        BigRational::from_float(x).map(Self)
+    }
+
+    // the fraction with the smallest denominator within tolerance, from the continued fraction of self:
+    // exact for a tolerance of 0, 0.125 = 1/8, and 0.3333333333333333 = 1/3 within 1e-10
+    pub fn to_fraction(&self, tolerance: &Number) -> Number {
+        let tolerance = tolerance.abs();
+        Self::simplest_between(&(self - &tolerance), &(self + &tolerance))
+    }
+
+    fn simplest_between(lo: &Number, hi: &Number) -> Number {
+        if lo.is_sign_negative() && !hi.is_sign_negative() {
+            return Self::zero();
+        }
+        if hi.is_sign_negative() {
+            return -Self::simplest_between(&-hi, &-lo);
+        }
+
+        // the first term of the continued fraction, the rest is the simplest fraction between the inverses
+        let whole = Self::from(lo.numer().div_floor(lo.denom()));
+        if &whole == lo {
+            return whole;
+        }
+        let next = &whole + &Self::one();
+        if &next <= hi {
+            return next;
+        }
+        let rest = Self::simplest_between(
+            &(hi - &whole).recip().unwrap(),
+            &(lo - &whole).recip().unwrap(),
+        );
+        &whole + &rest.recip().unwrap()
+    }
+
+    // 1/3 = 0.(3), 1/6 = 0.1(6), -7/3 = -2.(3): the digits up to the repeating block and the block.
+    // None when the expansion terminates or repeats after more than MAX_PERIOD digits
+    pub fn repeating_decimal(&self) -> Option<(String, String)> {
+        if self.terminates() {
+            return None;
+        }
+
+        let denom = self.denom();
+        let (whole, mut rem) = self.numer().abs().div_rem(denom);
+        let mut digits = String::new();
+        // remainder => where its digit is
+        let mut seen = HashMap::new();
+        let start = loop {
+            if let Some(&start) = seen.get(&rem) {
+                break start;
+            }
+            if digits.len() > MAX_PERIOD + preperiod(denom) {
+                return None;
+            }
+            seen.insert(rem.clone(), digits.len());
+
+            let (digit, next) = (&rem * BigInt::from(10)).div_rem(denom);
+            digits.push_str(&digit.to_string());
+            rem = next;
+        };
+
+        let sign = if self.is_sign_negative() { "-" } else { "" };
+        let (before, period) = digits.split_at(start);
+        Some((format!("{}{}.{}", sign, whole, before), period.to_string()))
+    }
+
+    // 5/2 = 2 1/2, -7/3 = -2 1/3: the whole part and what is left of the magnitude,
+    // None below one or for whole numbers
+    pub fn mixed(&self) -> Option<(BigInt, Number)> {
+        if self.is_integer() || self.abs() < Self::one() {
+            return None;
+        }
+        let whole = self.trunc();
+        Some((whole.numer().clone(), (self - &whole).abs()))
+    }
+
+    // digits after the point of a terminating number
+    fn decimal_places(&self) -> u32 {
+        let mut denom = self.denom().clone();
+        let mut places = 0;
+        while !denom.is_one() {
+            let ten = BigInt::from(10);
+            let gcd = denom.gcd(&ten);
+            denom /= gcd;
+            places += 1;
+        }
+        places
+    }
+}
+
+// digits before the repeating block of 1/denom: 6 = 2 * 3 has 1, 1/6 = 0.1(6)
+fn preperiod(denom: &BigInt) -> usize {
+    [2, 5]
+        .into_iter()
+        .map(|p| {
+            let p = BigInt::from(p);
+            let mut denom = denom.clone();
+            let mut count = 0;
+            while denom.is_multiple_of(&p) {
+                denom /= &p;
+                count += 1;
+            }
+            count
+        })
+        .max()
+        .unwrap()
+}
+
+impl From<Decimal> for Number {
+    fn from(d: Decimal) -> Self {
+        Self::new(d.mantissa(), BigInt::from(10).pow(d.scale()))
+    }
+}
+
+impl From<BigInt> for Number {
+    fn from(i: BigInt) -> Self {
+        Self(BigRational::from_integer(i))
+    }
+}
+
+macro_rules! impl_from_int {
+    ($($t:ty),*) => {
+        $(impl From<$t> for Number {
+            fn from(i: $t) -> Self {
+                Self::from(BigInt::from(i))
+            }
+        })*
+    };
+}
+
+impl_from_int!(i32, i64, i128, u32, u64, u128, usize);
+
+macro_rules! impl_number_op {
+    ($op:ident, $method:ident) => {
+        impl $op for Number {
+            type Output = Number;
+
+            fn $method(self, rhs: Number) -> Number {
+                Number(self.0.$method(rhs.0))
+            }
+        }
+
+        impl $op<&Number> for &Number {
+            type Output = Number;
+
+            fn $method(self, rhs: &Number) -> Number {
+                Number((&self.0).$method(&rhs.0))
+            }
+        }
+    };
+}
+
+impl_number_op!(Add, add);
+impl_number_op!(Sub, sub);
+impl_number_op!(Mul, mul);
+// panics on zero like integer division, see checked_div
+impl_number_op!(Div, div);
+impl_number_op!(Rem, rem);
+
+impl AddAssign for Number {
+    fn add_assign(&mut self, rhs: Number) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Number {
+    fn sub_assign(&mut self, rhs: Number) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl MulAssign for Number {
+    fn mul_assign(&mut self, rhs: Number) {
+        self.0 *= rhs.0;
+    }
+}
+
+impl Neg for Number {
+    type Output = Number;
+
+    fn neg(self) -> Number {
+        Number(-self.0)
+    }
+}
+
+impl Neg for &Number {
+    type Output = Number;
+
+    fn neg(self) -> Number {
+        Number(-&self.0)
+    }
+}
+
+// decimal when it terminates: 2, -0.5, 0.125, otherwise a fraction: 1/3
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.terminates() {
+            return write!(f, "{}/{}", self.numer(), self.denom());
+        }
+
+        let places = self.decimal_places();
+        let scaled = self.numer() * BigInt::from(10).pow(places) / self.denom();
+        let digits = scaled.magnitude().to_string();
+        if self.is_sign_negative() {
+            write!(f, "-")?;
+        }
+        if places == 0 {
+            return write!(f, "{}", digits);
+        }
+
+        let digits = format!("{:0>width$}", digits, width = places as usize + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - places as usize);
+        write!(f, "{}.{}", whole, fraction)
+    }
+}
+
+impl fmt::Debug for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+// 12, -0.5, 1e-3, 2.5E10, 1/3, and repeating decimals: 0.(3), 0.1(6), 0.333...
+impl FromStr for Number {
+    type Err = ParseNumberError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((numer, denom)) = s.split_once('/') {
+            let numer = Self::from_str(numer)?;
+            let denom = Self::from_str(denom)?;
+            return numer.checked_div(&denom).ok_or(ParseNumberError);
+        }
+
+        if let Some((start, period)) = s.strip_suffix(')').and_then(|s| s.split_once('(')) {
+            return Self::from_repeating(start, period);
+        }
+        // 0.333..., 0.142857142857...: the shortest block the digits already end with twice repeats,
+        // otherwise the last digit: 0.12...
+        if let Some(start) = s.strip_suffix("...").or_else(|| s.strip_suffix('…')) {
+            let (_, fraction) = start.split_once('.').ok_or(ParseNumberError)?;
+            if fraction.is_empty() || !fraction.is_ascii() {
+                return Err(ParseNumberError);
+            }
+            let len = fraction.len();
+            let period = (1..=len / 2)
+                .find(|&p| fraction[len - p..] == fraction[len - 2 * p..len - p])
+                .unwrap_or(1);
+            let (start, period) = start.split_at(start.len() - period);
+            return Self::from_repeating(start, period);
+        }
+
+        let (mantissa, exponent) = match s.split_once(['e', 'E']) {
+            Some((m, e)) => (m, e.parse::<i32>().map_err(|_| ParseNumberError)?),
+            None => (s, 0),
+        };
+        // a power of ten takes less than 4 bits per digit: 1e999999999
+        if exponent.unsigned_abs() as u64 * 4 > Self::max_bits() {
+            return Err(ParseNumberError);
+        }
+        let (negative, mantissa) = match mantissa.strip_prefix('-') {
+            Some(m) => (true, m),
+            None => (false, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = format!("{}{}", whole, fraction);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseNumberError);
+        }
+
+        let mut numer = BigInt::from_str(&digits).map_err(|_| ParseNumberError)?;
+        if negative {
+            numer = -numer;
+        }
+        let scale = Self::from(10)
+            .powi(exponent - fraction.len() as i32)
+            .unwrap();
+        Ok(Self::from(numer) * scale)
+    }
+}
+
+// as a string, like Decimal with serde-with-str
+impl Serialize for Number {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NumberVisitor;
+
+        impl de::Visitor<'_> for NumberVisitor {
+            type Value = Number;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a number or a string of a number")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Number, E> {
+                Number::from_str(v).map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Number, E> {
+                Ok(Number::from(v))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Number, E> {
+                Ok(Number::from(v))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Number, E> {
+                self.visit_str(&v.to_string())
+            }
+        }
+
+        deserializer.deserialize_any(NumberVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use num_bigint::BigInt;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    use super::Number;
+    use crate::arithmatic::arithmatic::OperationError;
+
+    #[test]
+    fn number_exact() {
+        let third = Number::new(1, 3);
+        assert_eq!(&third * &Number::from(3), Number::one());
+        assert_eq!(
+            Number::from(dec!(0.1)) + Number::from(dec!(0.2)),
+            Number::from(dec!(0.3))
+        );
+        assert_eq!(Number::from(dec!(0.25)), Number::new(1, 4));
+        assert_eq!(Number::from(2).powi(-2), Some(Number::new(1, 4)));
+        assert_eq!(Number::zero().powi(-1), None);
+        assert_eq!(
+            Number::from(8).pow(&Number::new(2, 3)),
+            Ok(Some(Number::from(4)))
+        );
+        assert_eq!(Number::from(2).pow(&Number::new(1, 2)), Ok(None));
+        assert_eq!(Number::new(-8, 27).nth_root(3), Some(Number::new(-2, 3)));
+        assert_eq!(Number::from(-4).nth_root(2), None);
+    }
+
+    #[test]
+    fn number_limits() {
+        let two = Number::from(2);
+        assert_eq!(
+            two.pow(&Number::from(200)).unwrap().unwrap().to_string(),
+            "1606938044258990275541962092341162602522202993782792835301376"
+        );
+        // never grows
+        assert_eq!(
+            Number::neg_one().pow(&Number::from_str("1e20").unwrap()),
+            Ok(Some(Number::one()))
+        );
+        assert_eq!(
+            two.pow(&Number::from(1_000_000_000)),
+            Err(OperationError::Overflow)
+        );
+
+        assert_eq!(
+            two.pow(&Number::from(100_000)),
+            Err(OperationError::Overflow)
+        );
+
+        // raised, not lowered, so tests running alongside still fit in the limit
+        let max = 1 << 18;
+        Number::set_max_bits(max);
+        let limited = std::thread::spawn(move || {
+            let two = Number::from(2);
+            assert_eq!(Number::max_bits(), max);
+            assert_eq!(
+                two.pow(&Number::from(100_000)).unwrap().unwrap().bits(),
+                100_001
+            );
+            assert_eq!(
+                two.pow(&Number::from(200_000)),
+                Err(OperationError::Overflow)
+            );
+            let largest = Number::from((BigInt::from(1) << max) - 1);
+            assert_eq!(largest.clone().bounded(), Ok(largest.clone()));
+            assert_eq!(
+                (largest + Number::one()).bounded(),
+                Err(OperationError::Overflow)
+            );
+            assert!(Number::from_str("1e60000").is_ok());
+            assert!(Number::from_str("1e70000").is_err());
+        })
+        .join();
+        Number::set_max_bits(super::DEFAULT_MAX_BITS);
+        limited.unwrap();
+    }
+
+    #[test]
+    fn number_strings() {
+        for (s, expected) in [
+            ("12", "12"),
+            ("-0.50", "-0.5"),
+            ("0.125", "0.125"),
+            ("1e-3", "0.001"),
+            ("2.5E3", "2500"),
+            ("-1/3", "-1/3"),
+            ("4/6", "2/3"),
+            (
+                "123456789012345678901234567890.5",
+                "123456789012345678901234567890.5",
+            ),
+        ] {
+            assert_eq!(Number::from_str(s).unwrap().to_string(), expected);
+        }
+
+        for s in ["", "1.2.3", "1/0", "-", "x"] {
+            assert!(Number::from_str(s).is_err(), "{}", s);
+        }
+
+        assert_eq!(
+            Number::new(1, 3).to_decimal(),
+            Some(dec!(0.3333333333333333333333333333))
+        );
+        assert_eq!(
+            Number::new(1, 3).approximate().to_string(),
+            "0.3333333333333333333333333333"
+        );
+        assert_eq!(Number::new(-1, 8).to_f64(), -0.125);
+    }
+
+    #[test]
+    fn repeating_decimals() {
+        for (s, expected) in [
+            ("0.(3)", Number::new(1, 3)),
+            ("0.1(6)", Number::new(1, 6)),
+            ("-2.(3)", Number::new(-7, 3)),
+            ("0.(142857)", Number::new(1, 7)),
+            ("0.333...", Number::new(1, 3)),
+            ("0.1666…", Number::new(1, 6)),
+            ("0.142857142857...", Number::new(1, 7)),
+            ("0.(9)", Number::one()),
+        ] {
+            assert_eq!(Number::from_str(s), Ok(expected), "{}", s);
+        }
+
+        for s in ["0.()", "0.(x)", "1(3)", "1...", "0.(3"] {
+            assert!(Number::from_str(s).is_err(), "{}", s);
+        }
+
+        assert_eq!(
+            Number::new(1, 6).repeating_decimal(),
+            Some((String::from("0.1"), String::from("6")))
+        );
+        assert_eq!(
+            Number::new(-7, 3).repeating_decimal(),
+            Some((String::from("-2."), String::from("3")))
+        );
+        assert_eq!(Number::new(1, 8).repeating_decimal(), None);
+        // period of 96 digits
+        assert_eq!(Number::new(1, 97).repeating_decimal(), None);
+    }
+
+    #[test]
+    fn fractions() {
+        let exact = Number::zero();
+        assert_eq!(
+            Number::from_str("0.125").unwrap().to_fraction(&exact),
+            Number::new(1, 8)
+        );
+
+        let tolerance = Number::from_str("1e-10").unwrap();
+        let third = Number::from_f64(1.0 / 3.0).unwrap();
+        assert_ne!(third, Number::new(1, 3));
+        assert_eq!(third.to_fraction(&tolerance), Number::new(1, 3));
+        assert_eq!(
+            Number::from_f64(-std::f64::consts::PI)
+                .unwrap()
+                .to_fraction(&Number::from_str("0.001").unwrap()),
+            Number::new(-201, 64)
+        );
+        assert_eq!(Number::from_f64(f64::NAN), None);
+
+        assert_eq!(
+            Number::new(-7, 3).mixed(),
+            Some((BigInt::from(-2), Number::new(1, 3)))
+        );
+        assert_eq!(Number::new(1, 3).mixed(), None);
+    }
+}
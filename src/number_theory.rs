@@ -0,0 +1,270 @@
+// exact integer routines: gcd, lcm, primality, factorization, divisors and totient
+
+use std::collections::BTreeMap;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+
+use crate::{math_tree::TreeNodeRef, FunctionToken};
+
+// miller rabin with these bases is exact below 3.3 * 10^24
+const SMALL_PRIMES: [u32; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+// trial division before pollard rho
+const TRIAL_DIVISION_LIMIT: u32 = 1000;
+// factorint(n) is only written out below this, pollard rho gets slow past it
+pub const MAX_FACTORINT_BITS: u64 = 64;
+
+// always non negative, gcd(0, 0) = 0
+pub fn gcd(a: &BigInt, b: &BigInt) -> BigInt {
+    a.gcd(b)
+}
+
+// always non negative, lcm(0, n) = 0
+pub fn lcm(a: &BigInt, b: &BigInt) -> BigInt {
+    a.lcm(b)
+}
+
+// (g, x, y) such that a * x + b * y = g = gcd(a, b)
+pub fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut old_r, mut r) = (a.clone(), b.clone());
+    let (mut old_x, mut x) = (BigInt::one(), BigInt::zero());
+    let (mut old_y, mut y) = (BigInt::zero(), BigInt::one());
+
+    while !r.is_zero() {
+        let q = &old_r / &r;
+        (old_r, r) = (r.clone(), &old_r - &q * &r);
+        (old_x, x) = (x.clone(), &old_x - &q * &x);
+        (old_y, y) = (y.clone(), &old_y - &q * &y);
+    }
+
+    if old_r.is_negative() {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+// miller rabin over the first 13 primes
+pub fn is_prime(n: &BigInt) -> bool {
+    if n < &BigInt::from(2) {
+        return false;
+    }
+    for p in SMALL_PRIMES {
+        let p = BigInt::from(p);
+        if n == &p {
+            return true;
+        }
+        if (n % &p).is_zero() {
+            return false;
+        }
+    }
+
+    // n - 1 = d * 2^s
+    let n_1: BigInt = n - 1u32;
+    let s = n_1.trailing_zeros().unwrap();
+    let d = &n_1 >> s;
+
+    'witness: for a in SMALL_PRIMES {
+        let mut x = BigInt::from(a).modpow(&d, n);
+        if x.is_one() || x == n_1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = x.modpow(&BigInt::from(2), n);
+            if x == n_1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+// prime => exponent, 360 = 2^3 * 3^2 * 5, the sign is dropped and 0 or 1 have no factors
+pub fn factorize(n: &BigInt) -> BTreeMap<BigInt, u32> {
+    let mut factors = BTreeMap::new();
+    let mut n = n.abs();
+    if n.is_zero() {
+        return factors;
+    }
+
+    let mut p = BigInt::from(2);
+    while p <= BigInt::from(TRIAL_DIVISION_LIMIT) && &p * &p <= n {
+        while (&n % &p).is_zero() {
+            n /= &p;
+            *factors.entry(p.clone()).or_insert(0) += 1;
+        }
+        p += 1;
+    }
+
+    let mut rest = vec![n];
+    while let Some(n) = rest.pop() {
+        if n.is_one() {
+            continue;
+        }
+        if is_prime(&n) {
+            *factors.entry(n).or_insert(0) += 1;
+            continue;
+        }
+        let d = pollard_rho(&n);
+        rest.push(&n / &d);
+        rest.push(d);
+    }
+
+    factors
+}
+
+// a non trivial divisor of a composite n
+fn pollard_rho(n: &BigInt) -> BigInt {
+    if n.is_even() {
+        return BigInt::from(2);
+    }
+
+    let mut c = BigInt::one();
+    loop {
+        let f = |x: &BigInt| (x * x + &c) % n;
+        let (mut x, mut y, mut d) = (BigInt::from(2), BigInt::from(2), BigInt::one());
+        while d.is_one() {
+            x = f(&x);
+            y = f(&f(&y));
+            d = (&x - &y).abs().gcd(n);
+        }
+        if &d != n {
+            return d;
+        }
+        // the cycle closed without a divisor, try another polynomial
+        c += 1;
+    }
+}
+
+// every positive divisor in order: 12 => 1, 2, 3, 4, 6, 12
+pub fn divisors(n: &BigInt) -> Vec<BigInt> {
+    if n.is_zero() {
+        return vec![];
+    }
+
+    let mut divisors = vec![BigInt::one()];
+    for (p, k) in factorize(n) {
+        let mut next = Vec::with_capacity(divisors.len() * (k as usize + 1));
+        for d in &divisors {
+            let mut power = d.clone();
+            for _ in 0..=k {
+                next.push(power.clone());
+                power *= &p;
+            }
+        }
+        divisors = next;
+    }
+
+    divisors.sort();
+    divisors
+}
+
+// how many of 1..=n are coprime to n: totient(12) = 4
+pub fn totient(n: &BigInt) -> BigInt {
+    if !n.is_positive() {
+        return BigInt::zero();
+    }
+
+    factorize(n)
+        .into_iter()
+        .fold(n.clone(), |res, (p, _)| res / &p * (&p - 1))
+}
+
+// the factors a factorint(c) node is written as, None when c is not a whole number above 1
+// or too big to factor quickly, those are written as a call
+pub fn factorint(node: &TreeNodeRef) -> Option<BTreeMap<BigInt, u32>> {
+    if node.val().function != Some(FunctionToken::Factorint) {
+        return None;
+    }
+
+    let c = node.borrow().calculate_iter().next()?.1.val().constant?;
+    (c.is_integer() && c.numer() > &BigInt::one() && c.bits() <= MAX_FACTORINT_BITS)
+        .then(|| factorize(c.numer()))
+}
+
+// 2^3·3^2·5, joined with the given product sign and exponent format
+pub fn write_factors(
+    factors: &BTreeMap<BigInt, u32>,
+    times: &str,
+    power: impl Fn(&BigInt, u32) -> String,
+) -> String {
+    factors
+        .iter()
+        .map(|(p, &k)| match k {
+            1 => p.to_string(),
+            k => power(p, k),
+        })
+        .collect::<Vec<_>>()
+        .join(times)
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigInt;
+    use pretty_assertions::assert_eq;
+
+    use super::{divisors, extended_gcd, factorize, gcd, is_prime, lcm, totient};
+    use crate::{
+        math_tree::{MathTree, TreeNodeRef},
+        simplify::simplify::tests::simplify_test,
+        stepper::Steps,
+    };
+
+    fn int(n: i64) -> BigInt {
+        BigInt::from(n)
+    }
+
+    #[test]
+    fn integer_routines() {
+        assert_eq!(gcd(&int(12), &int(18)), int(6));
+        assert_eq!(gcd(&int(-12), &int(18)), int(6));
+        assert_eq!(lcm(&int(4), &int(6)), int(12));
+
+        let (g, x, y) = extended_gcd(&int(240), &int(46));
+        assert_eq!(g, int(2));
+        assert_eq!(int(240) * x + int(46) * y, g);
+
+        assert!(is_prime(&int(2)));
+        assert!(is_prime(&int(1_000_000_007)));
+        assert!(!is_prime(&int(1)));
+        // carmichael number
+        assert!(!is_prime(&int(561)));
+
+        assert_eq!(
+            factorize(&int(360)).into_iter().collect::<Vec<_>>(),
+            vec![(int(2), 3), (int(3), 2), (int(5), 1)]
+        );
+        // two primes past the trial division limit
+        assert_eq!(
+            factorize(&(int(1_000_003) * int(1_000_033)))
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![(int(1_000_003), 1), (int(1_000_033), 1)]
+        );
+
+        assert_eq!(divisors(&int(12)), [1, 2, 3, 4, 6, 12].map(int).to_vec());
+        assert_eq!(totient(&int(12)), int(4));
+        assert_eq!(totient(&int(13)), int(12));
+    }
+
+    #[test]
+    fn number_theory_functions() {
+        simplify_test("gcd(12, 18)", TreeNodeRef::constant(6));
+        simplify_test("lcm(4, 6)", TreeNodeRef::constant(12));
+
+        let tree = MathTree::parse("factorint(360)").unwrap();
+        assert_eq!(tree.to_string(), "2^3·3^2·5");
+        assert_eq!(tree.to_latex(), "2^{3}\\cdot 3^{2}\\cdot 5");
+        // stays as the product
+        let mut simplified = tree.copy();
+        simplified.simplify(&mut Steps::new()).unwrap();
+        assert_eq!(simplified.to_string(), "2^3·3^2·5");
+
+        assert_eq!(
+            MathTree::parse("factorint(x)").unwrap().to_string(),
+            "factorint(x)"
+        );
+    }
+}
@@ -22,6 +22,7 @@ pub struct Operands {
     variables: Slab<usize>,
     operators: Slab<usize>,
     constants: Slab<usize>,
+    functions: Slab<usize>,
 }
 
 impl PartialEq for Operands {
@@ -32,7 +33,8 @@ impl PartialEq for Operands {
 
 type OperandIt<'a> = Map<slab::Iter<'a, usize>, fn((usize, &usize)) -> OperandPos>;
 
-pub type OperandsIt<'a> = Chain<Chain<OperandIt<'a>, OperandIt<'a>>, OperandIt<'a>>;
+pub type OperandsIt<'a> =
+    Chain<Chain<Chain<OperandIt<'a>, OperandIt<'a>>, OperandIt<'a>>, OperandIt<'a>>;
 
 // impl std::fmt::Debug for Operands {
 //     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -43,6 +45,13 @@ pub type OperandsIt<'a> = Chain<Chain<OperandIt<'a>, OperandIt<'a>>, OperandIt<'
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct OperandPos(usize);
 
+impl OperandPos {
+    // slot in the operands slab
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
 impl Index<OperandPos> for Operands {
     type Output = TreeNodeRef;
 
@@ -65,13 +74,15 @@ impl Operands {
             MathTokenType::Constant => &mut self.constants,
             MathTokenType::Variable => &mut self.variables,
             MathTokenType::Operator => &mut self.operators,
+            MathTokenType::Function => &mut self.functions,
         };
 
         index_vec.insert(node_index)
     }
 
     pub fn extend(&mut self, other: &Self) {
-        for pos in other.iter() {
+        // keep the order the operands were written in
+        for (pos, _) in other.iter_order() {
             self.push(other.nodes[pos.0].0.clone());
         }
     }
@@ -99,6 +110,7 @@ impl Operands {
             MathTokenType::Constant => self.constants.remove(type_pos),
             MathTokenType::Operator => self.operators.remove(type_pos),
             MathTokenType::Variable => self.variables.remove(type_pos),
+            MathTokenType::Function => self.functions.remove(type_pos),
         };
     }
 
@@ -117,6 +129,7 @@ impl Operands {
 
     pub fn iter<'a>(&'a self) -> OperandsIt {
         self.operators()
+            .chain(self.functions())
             .chain(self.variables())
             .chain(self.constants())
     }
@@ -124,6 +137,7 @@ impl Operands {
     pub fn iter_mul<'a>(&'a self) -> OperandsIt {
         self.constants()
             .chain(self.variables())
+            .chain(self.functions())
             .chain(self.operators())
     }
 
@@ -175,6 +189,7 @@ impl Operands {
             MathTokenType::Constant => &self.constants,
             MathTokenType::Variable => &self.variables,
             MathTokenType::Operator => &self.operators,
+            MathTokenType::Function => &self.functions,
         };
 
         self.pos_iter(vec.iter())
@@ -192,6 +207,10 @@ impl Operands {
         self.get_kind(MathTokenType::Variable)
     }
 
+    pub fn functions<'a>(&'a self) -> OperandIt {
+        self.get_kind(MathTokenType::Function)
+    }
+
     // pub fn remove_operators(&mut self) -> Vec<TreeNodeRef> {
     //     self.operators
     //         .drain(..)
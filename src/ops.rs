@@ -0,0 +1,175 @@
+// std::ops for building trees: x * 2 + 1, see also expr!
+
+use std::{
+    ops::{Add, Div, Mul, Neg, Sub},
+    rc::Rc,
+};
+
+use rust_decimal::Decimal;
+
+use crate::{
+    math_tree::TreeNodeRef, number::Number, FunctionToken, MathToken, OperationToken,
+};
+
+impl TreeNodeRef {
+    pub fn var(name: &str) -> Self {
+        Self::new_val(MathToken::variable(Rc::new(name.to_string())))
+    }
+
+    // f(args), the same tree the parser builds: an omitted log base is 10, sqrt is a root operator
+    pub fn call(f: FunctionToken, mut args: Vec<TreeNodeRef>) -> Self {
+        if let Some(default) = f.default_operand() {
+            if args.len() < f.info().arity as usize {
+                args.push(Self::constant(default));
+            }
+        }
+
+        match f.operation() {
+            Some(op) => {
+                args.extend(f.lowered_operand().map(Self::constant));
+                Self::new_vals(MathToken::operator(op), args)
+            }
+            None => Self::new_vals(MathToken::function(f), args),
+        }
+    }
+}
+
+impl From<Number> for TreeNodeRef {
+    fn from(c: Number) -> Self {
+        Self::constant(c)
+    }
+}
+
+impl From<Decimal> for TreeNodeRef {
+    fn from(c: Decimal) -> Self {
+        Self::constant(c)
+    }
+}
+
+impl From<i64> for TreeNodeRef {
+    fn from(c: i64) -> Self {
+        Self::constant(c)
+    }
+}
+
+// integer literals default to i32: x * 2
+impl From<i32> for TreeNodeRef {
+    fn from(c: i32) -> Self {
+        Self::constant(c)
+    }
+}
+
+impl From<&TreeNodeRef> for TreeNodeRef {
+    fn from(node: &TreeNodeRef) -> Self {
+        node.clone()
+    }
+}
+
+// TreeNodeRef op rhs, and lhs op TreeNodeRef for constants
+macro_rules! impl_op {
+    ($op:ident, $method:ident, $token:expr) => {
+        impl<T: Into<TreeNodeRef>> $op<T> for TreeNodeRef {
+            type Output = TreeNodeRef;
+
+            fn $method(self, rhs: T) -> TreeNodeRef {
+                self.op($token, rhs.into())
+            }
+        }
+
+        impl<T: Into<TreeNodeRef>> $op<T> for &TreeNodeRef {
+            type Output = TreeNodeRef;
+
+            fn $method(self, rhs: T) -> TreeNodeRef {
+                self.op($token, rhs.into())
+            }
+        }
+
+        impl_op!(@lhs $op, $method, $token, Number);
+        impl_op!(@lhs $op, $method, $token, Decimal);
+        impl_op!(@lhs $op, $method, $token, i64);
+        impl_op!(@lhs $op, $method, $token, i32);
+    };
+    (@lhs $op:ident, $method:ident, $token:expr, $lhs:ty) => {
+        impl $op<TreeNodeRef> for $lhs {
+            type Output = TreeNodeRef;
+
+            fn $method(self, rhs: TreeNodeRef) -> TreeNodeRef {
+                TreeNodeRef::from(self).op($token, rhs)
+            }
+        }
+
+        impl $op<&TreeNodeRef> for $lhs {
+            type Output = TreeNodeRef;
+
+            fn $method(self, rhs: &TreeNodeRef) -> TreeNodeRef {
+                TreeNodeRef::from(self).op($token, rhs.clone())
+            }
+        }
+    };
+}
+
+impl_op!(Add, add, OperationToken::Add);
+impl_op!(Sub, sub, OperationToken::Subtract);
+impl_op!(Mul, mul, OperationToken::Multiply);
+impl_op!(Div, div, OperationToken::Divide);
+
+impl Neg for TreeNodeRef {
+    type Output = TreeNodeRef;
+
+    // -2 is a constant, -x is -1 * x like the parser reads it
+    fn neg(self) -> TreeNodeRef {
+        match self.val().constant {
+            Some(c) => TreeNodeRef::constant(-c),
+            None => TreeNodeRef::constant(-1).op(OperationToken::Multiply, self),
+        }
+    }
+}
+
+impl Neg for &TreeNodeRef {
+    type Output = TreeNodeRef;
+
+    fn neg(self) -> TreeNodeRef {
+        -self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    use crate::{math_tree::TreeNodeRef, FunctionToken};
+
+    #[test]
+    fn tree_ops() {
+        let x = TreeNodeRef::var("x");
+        let y = TreeNodeRef::var("y");
+
+        assert_eq!(&x * 2 + 1, TreeNodeRef::parse("x * 2 + 1"));
+        assert_eq!(2 * &x + &y, TreeNodeRef::parse("2 * x + y"));
+        assert_eq!((&x + 1) / (&x - 1), TreeNodeRef::parse("(x + 1) / (x - 1)"));
+        assert_eq!(dec!(1.5) - &y, TreeNodeRef::parse("1.5 - y"));
+        assert_eq!(&x + &y + 3, TreeNodeRef::parse("x + y + 3"));
+        assert_eq!(x.pow(2) * 3, TreeNodeRef::parse("x^2 * 3"));
+        assert_eq!(-&x, TreeNodeRef::parse("-x"));
+        assert_eq!(-TreeNodeRef::two(), TreeNodeRef::constant(dec!(-2)));
+    }
+
+    #[test]
+    fn tree_calls() {
+        let x = TreeNodeRef::var("x");
+
+        assert_eq!(
+            TreeNodeRef::call(FunctionToken::Sin, vec![x.clone()]),
+            TreeNodeRef::parse("sin(x)")
+        );
+        assert_eq!(
+            TreeNodeRef::call(FunctionToken::Log, vec![x.clone()]),
+            TreeNodeRef::parse("log(x)")
+        );
+        assert_eq!(
+            TreeNodeRef::call(FunctionToken::Sqrt, vec![x + 1]),
+            TreeNodeRef::parse("sqrt(x + 1)")
+        );
+    }
+}
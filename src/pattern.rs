@@ -45,6 +45,17 @@ impl MathTree {
                     false
                 }
             }
+            MathTokenType::Function => {
+                let b1 = check_node.borrow();
+                let b2 = pattern_node.borrow();
+                // same function, arguments must match in order
+                val == check_node.val()
+                    && b1
+                        .operands()
+                        .iter_order()
+                        .zip(b2.operands().iter_order())
+                        .all(|((_, a), (_, b))| Self::node_like(a, b, variables))
+            }
             MathTokenType::Variable => {
                 // pattern expects a variable
                 let v = val.variable.unwrap();
@@ -72,15 +83,15 @@ mod tests {
     #[test]
     fn like_test() {
         assert_eq!(MathTree::like(&MathTree::parse("2^3*2^4").unwrap().root, "x^m*x^n"), Some(HashMap::from([
-            ("x".to_string(), TreeNodeRef::constant(dec!(2))),
-            ("m".to_string(), TreeNodeRef::constant(dec!(3))),
-            ("n".to_string(), TreeNodeRef::constant(dec!(4))),
+            ("x".to_string().into(), TreeNodeRef::constant(dec!(2))),
+            ("m".to_string().into(), TreeNodeRef::constant(dec!(3))),
+            ("n".to_string().into(), TreeNodeRef::constant(dec!(4))),
         ])));
 
         
         assert_eq!(MathTree::like(&MathTree::parse("(x + 2)^2").unwrap().root, "(a + b)^2"), Some(HashMap::from([
-            ("a".to_string(), TreeNodeRef::new_val(MathToken::variable(String::from("x").into()))),
-            ("b".to_string(), TreeNodeRef::constant(dec!(2))),
+            ("a".to_string().into(), TreeNodeRef::new_val(MathToken::variable(String::from("x").into()))),
+            ("b".to_string().into(), TreeNodeRef::constant(dec!(2))),
         ])));
 
     }
@@ -0,0 +1,559 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    math_tree::{MathTree, TreeNodeRef},
+    number::{Number, NumberForm, NumberStyle},
+    number_theory::{factorint, write_factors},
+    render::{Emitter, TermEmitter},
+    FunctionToken, MathTokenType, OperationToken,
+};
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PrettyLayout {
+    // one line: x² + √(x + 1)/2
+    #[default]
+    Inline,
+    // fractions stacked over a bar, exponents that have no superscript raised a line
+    Stacked,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl MathTree {
+    pub fn to_pretty_string(&self) -> String {
+        self.root.to_pretty_string()
+    }
+
+    pub fn to_pretty_string_with(&self, layout: PrettyLayout) -> String {
+        self.root.to_pretty_string_with(layout)
+    }
+
+    pub fn to_pretty_string_styled(&self, layout: PrettyLayout, numbers: NumberStyle) -> String {
+        self.root.to_pretty_string_styled(layout, numbers)
+    }
+}
+
+impl TreeNodeRef {
+    pub fn to_pretty_string(&self) -> String {
+        self.to_pretty_string_with(PrettyLayout::default())
+    }
+
+    pub fn to_pretty_string_with(&self, layout: PrettyLayout) -> String {
+        self.to_pretty_string_styled(layout, NumberStyle::default())
+    }
+
+    // constants written as fractions, decimals or mixed numbers: 1/8, 0.125, 2 1/2
+    pub fn to_pretty_string_styled(&self, layout: PrettyLayout, numbers: NumberStyle) -> String {
+        PrettyWriter { layout, numbers }.write(self).to_string()
+    }
+}
+
+// lines of text of the same width, lined up with others on the baseline row
+#[derive(Debug, Clone)]
+struct Block {
+    lines: Vec<String>,
+    baseline: usize,
+}
+
+impl Block {
+    fn text(s: &str) -> Self {
+        Self {
+            lines: vec![s.to_string()],
+            baseline: 0,
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.lines[0].chars().count()
+    }
+
+    fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn starts_with(&self, f: impl FnMut(char) -> bool) -> bool {
+        self.lines[self.baseline].starts_with(f)
+    }
+
+    // side by side, baselines on the same row
+    fn beside(blocks: &[Block]) -> Self {
+        let above = blocks.iter().map(|b| b.baseline).max().unwrap_or(0);
+        let below = blocks
+            .iter()
+            .map(|b| b.height() - b.baseline)
+            .max()
+            .unwrap_or(1);
+
+        let lines = (0..above + below)
+            .map(|row| {
+                blocks
+                    .iter()
+                    .map(|b| match (row + b.baseline).checked_sub(above) {
+                        Some(i) if i < b.height() => b.lines[i].clone(),
+                        _ => " ".repeat(b.width()),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            lines,
+            baseline: above,
+        }
+    }
+
+    fn push(self, s: &str) -> Self {
+        Self::beside(&[self, Self::text(s)])
+    }
+
+    fn fraction(num: Block, den: Block) -> Self {
+        let width = num.width().max(den.width());
+        let center = |b: Block| {
+            let left = (width - b.width()) / 2;
+            b.lines.into_iter().map(move |line| {
+                format!(
+                    "{}{}{}",
+                    " ".repeat(left),
+                    line,
+                    " ".repeat(width - left - line.chars().count())
+                )
+            })
+        };
+
+        let baseline = num.height();
+        let mut lines = center(num).collect::<Vec<_>>();
+        lines.push("─".repeat(width));
+        lines.extend(center(den));
+        Self { lines, baseline }
+    }
+
+    // exponent above and right of the base
+    fn raise(base: Block, exp: Block) -> Self {
+        let (base_width, exp_width) = (base.width(), exp.width());
+        let baseline = exp.height() + base.baseline;
+        let lines = exp
+            .lines
+            .into_iter()
+            .map(|line| format!("{}{}", " ".repeat(base_width), line))
+            .chain(
+                base.lines
+                    .into_iter()
+                    .map(|line| format!("{}{}", line, " ".repeat(exp_width))),
+            )
+            .collect();
+        Self { lines, baseline }
+    }
+
+    // delimiters as tall as the content
+    fn surround(self, left: [char; 4], right: [char; 4]) -> Self {
+        let height = self.height();
+        let delimiter = |d: [char; 4], row: usize| match (height, row) {
+            (1, _) => d[0],
+            (_, 0) => d[1],
+            (_, r) if r == height - 1 => d[3],
+            _ => d[2],
+        };
+
+        let lines = self
+            .lines
+            .into_iter()
+            .enumerate()
+            .map(|(row, line)| format!("{}{}{}", delimiter(left, row), line, delimiter(right, row)))
+            .collect();
+        Self {
+            lines,
+            baseline: self.baseline,
+        }
+    }
+
+    fn parenthesize(self) -> Self {
+        self.surround(['(', '⎛', '⎜', '⎝'], [')', '⎞', '⎟', '⎠'])
+    }
+}
+
+impl std::fmt::Display for Block {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines = self
+            .lines
+            .iter()
+            .map(|line| line.trim_end())
+            .collect::<Vec<_>>();
+        f.write_str(&lines.join("\n"))
+    }
+}
+
+struct PrettyWriter {
+    layout: PrettyLayout,
+    numbers: NumberStyle,
+}
+impl Emitter for PrettyWriter {
+    type Output = Block;
+
+    fn write(&self, node: &TreeNodeRef) -> Block {
+        let val = node.val();
+        match val.kind {
+            MathTokenType::Constant => self.constant(&val.constant.unwrap()),
+            MathTokenType::Variable => {
+                let name = val.variable.unwrap();
+                match name.as_str() {
+                    "pi" => Block::text("π"),
+                    _ => Block::text(&name),
+                }
+            }
+            MathTokenType::Function => self.function(node, val.function.unwrap()),
+            MathTokenType::Operator => self.operator(node, val.operation.unwrap()),
+        }
+    }
+
+    fn precedence(&self, node: &TreeNodeRef) -> i8 {
+        let val = node.val();
+        match val.operation {
+            // roots are grouped by √, stacked fractions by their bar
+            Some(OperationToken::Root) => i8::MAX,
+            Some(OperationToken::Divide) if self.layout == PrettyLayout::Stacked => i8::MAX,
+            Some(op) => op.info().precedence,
+            None if factorint(node).is_some() => OperationToken::Multiply.info().precedence,
+            None => val
+                .constant
+                .as_ref()
+                .map_or(i8::MAX, |c| self.constant_precedence(c)),
+        }
+    }
+
+    fn group(&self, res: Block) -> Block {
+        res.parenthesize()
+    }
+
+    // x - (-2), 2(-x)
+    fn leads_with_minus(&self, _: &TreeNodeRef, res: &Block) -> bool {
+        res.starts_with(|c| c == '-')
+    }
+}
+
+impl TermEmitter for PrettyWriter {
+    // in the NumberStyle, by default decimals when they terminate, 1/3 as a fraction
+    fn constant(&self, c: &Number) -> Block {
+        match self.numbers.form(c) {
+            NumberForm::Decimal => Block::text(&c.approximate().to_string()),
+            NumberForm::Repeating(start, period) => Block::text(&format!("{}({})", start, period)),
+            NumberForm::Fraction => self.fraction(c),
+            NumberForm::Mixed(whole, fraction) => Block::beside(&[
+                Block::text(&format!("{} ", whole)),
+                self.fraction(&fraction),
+            ]),
+        }
+    }
+
+    fn constant_precedence(&self, c: &Number) -> i8 {
+        if self.inline_fraction(Some(c)) {
+            OperationToken::Divide.info().precedence
+        } else if self.mixed(Some(c)) {
+            // 2 1/2 is 2 + 1/2
+            OperationToken::Add.info().precedence
+        } else {
+            i8::MAX
+        }
+    }
+
+    fn symbol(&self, op: OperationToken) -> Block {
+        Block::text(&format!(" {} ", op.to_char()))
+    }
+
+    fn minus(&self) -> Block {
+        Block::text("-")
+    }
+
+    // 2x and 2(x + 1), but 2·5 and x·y
+    fn times(
+        &self,
+        _: &Block,
+        lead: Option<&Number>,
+        _: &TreeNodeRef,
+        written: &Block,
+    ) -> Option<Block> {
+        // 1/3x would read as 1/(3x)
+        let juxtaposed = lead.is_some_and(|c| !self.inline_fraction(Some(c)))
+            && written.height() == 1
+            && !written.starts_with(|c: char| c.is_ascii_digit() || c == '.');
+        (!juxtaposed).then(|| Block::text("·"))
+    }
+
+    fn join(&self, parts: Vec<Block>) -> Block {
+        Block::beside(&parts)
+    }
+}
+
+impl PrettyWriter {
+    fn fraction(&self, c: &Number) -> Block {
+        match self.layout {
+            PrettyLayout::Inline => Block::text(&format!("{}/{}", c.numer(), c.denom())),
+            PrettyLayout::Stacked => {
+                let fraction = Block::fraction(
+                    Block::text(&c.numer().magnitude().to_string()),
+                    Block::text(&c.denom().to_string()),
+                );
+                if c.is_sign_negative() {
+                    Block::beside(&[Block::text("-"), fraction])
+                } else {
+                    fraction
+                }
+            }
+        }
+    }
+
+    fn inline_fraction(&self, c: Option<&Number>) -> bool {
+        self.layout == PrettyLayout::Inline
+            && c.is_some_and(|c| self.numbers.form(c) == NumberForm::Fraction)
+    }
+
+    fn mixed(&self, c: Option<&Number>) -> bool {
+        c.is_some_and(|c| matches!(self.numbers.form(c), NumberForm::Mixed(..)))
+    }
+
+    fn operator(&self, node: &TreeNodeRef, op: OperationToken) -> Block {
+        let borrow = node.borrow();
+        let childs = borrow
+            .display_iter()
+            .map(|(_, n)| n.clone())
+            .collect::<Vec<_>>();
+        let precedence = op.info().precedence;
+
+        match op {
+            OperationToken::Add => self.sum(&childs),
+            OperationToken::Subtract => Block::beside(&[
+                self.write(&childs[0]),
+                Block::text(" - "),
+                self.operand(&childs[1], precedence, true),
+            ]),
+            OperationToken::Multiply => self.multiply(&childs),
+            OperationToken::Divide => match self.layout {
+                PrettyLayout::Stacked => {
+                    Block::fraction(self.write(&childs[0]), self.write(&childs[1]))
+                }
+                PrettyLayout::Inline => Block::beside(&[
+                    self.operand(&childs[0], precedence, false),
+                    Block::text("/"),
+                    self.operand(&childs[1], precedence, true),
+                ]),
+            },
+            OperationToken::Pow => {
+                let base = self.write(&childs[0]);
+                // (x + 1)², (-2)², (x²)³, (1/3)²
+                let val = childs[0].val();
+                let base = if (val.kind == MathTokenType::Operator
+                    && val.operation != Some(OperationToken::Root))
+                    || self.inline_fraction(val.constant.as_ref())
+                    || self.mixed(val.constant.as_ref())
+                    || factorint(&childs[0]).is_some()
+                    || base.starts_with(|c| c == '-')
+                {
+                    base.parenthesize()
+                } else {
+                    base
+                };
+
+                let exp = self.write(&childs[1]);
+                if let Some(sup) = single_line(&exp).and_then(superscript) {
+                    return base.push(&sup);
+                }
+
+                match self.layout {
+                    PrettyLayout::Stacked => Block::raise(base, exp),
+                    PrettyLayout::Inline => Block::beside(&[
+                        base,
+                        Block::text("^"),
+                        self.operand(&childs[1], precedence, true),
+                    ]),
+                }
+            }
+            OperationToken::Root => {
+                let index = childs[1].val().constant;
+                let sign = match index {
+                    Some(c) if c == Number::two() => Some(String::from("√")),
+                    Some(c) if c == Number::from(3) => Some(String::from("∛")),
+                    Some(c) if c == Number::from(4) => Some(String::from("∜")),
+                    _ => single_line(&self.write(&childs[1]))
+                        .and_then(superscript)
+                        .map(|sup| format!("{}√", sup)),
+                };
+
+                let Some(sign) = sign else {
+                    return self.call("root", &[&childs[0], &childs[1]]);
+                };
+
+                let radicand = self.write(&childs[0]);
+                let radicand = match childs[0].val().kind {
+                    MathTokenType::Operator => radicand.parenthesize(),
+                    _ => radicand,
+                };
+                Block::beside(&[Block::text(&sign), radicand])
+            }
+            OperationToken::LParent | OperationToken::RParent | OperationToken::Comma => {
+                unreachable!()
+            }
+        }
+    }
+
+    fn function(&self, node: &TreeNodeRef, f: FunctionToken) -> Block {
+        // 2³·3²·5
+        if let Some(factors) = factorint(node) {
+            return Block::text(&write_factors(&factors, "·", |p, k| {
+                format!("{}{}", p, superscript(&k.to_string()).unwrap())
+            }));
+        }
+
+        let borrow = node.borrow();
+        let args = borrow.calculate_iter().map(|x| x.1).collect::<Vec<_>>();
+
+        match f {
+            FunctionToken::Abs => self.write(args[0]).surround(['|'; 4], ['|'; 4]),
+            FunctionToken::Log => {
+                let base = args[1];
+                // base 10 is implied
+                if base.val().constant == Some(Number::ten()) {
+                    return self.call("log", &args[..1]);
+                }
+
+                match single_line(&self.write(base)).and_then(subscript) {
+                    Some(sub) => self.call(&format!("log{}", sub), &args[..1]),
+                    None => self.call("log", &args),
+                }
+            }
+            _ => self.call(f.name(), &args),
+        }
+    }
+
+    fn call(&self, name: &str, args: &[&TreeNodeRef]) -> Block {
+        let mut res = Vec::new();
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                res.push(Block::text(", "));
+            }
+            res.push(self.write(arg));
+        }
+
+        Block::beside(&[Block::text(name), Block::beside(&res).parenthesize()])
+    }
+}
+
+fn single_line(block: &Block) -> Option<&str> {
+    (block.height() == 1).then(|| block.lines[0].as_str())
+}
+
+// x², x⁻¹, xⁿ: None when a character has no superscript form
+fn superscript(s: &str) -> Option<String> {
+    s.chars()
+        .map(|c| {
+            Some(match c {
+                '0' => '⁰',
+                '1' => '¹',
+                '2' => '²',
+                '3' => '³',
+                '4' => '⁴',
+                '5' => '⁵',
+                '6' => '⁶',
+                '7' => '⁷',
+                '8' => '⁸',
+                '9' => '⁹',
+                '-' => '⁻',
+                'n' => 'ⁿ',
+                'i' => 'ⁱ',
+                _ => return None,
+            })
+        })
+        .collect()
+}
+
+// log₂
+fn subscript(s: &str) -> Option<String> {
+    s.chars()
+        .map(|c| {
+            Some(match c {
+                '0' => '₀',
+                '1' => '₁',
+                '2' => '₂',
+                '3' => '₃',
+                '4' => '₄',
+                '5' => '₅',
+                '6' => '₆',
+                '7' => '₇',
+                '8' => '₈',
+                '9' => '₉',
+                _ => return None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::PrettyLayout;
+    use crate::{math_tree::MathTree, number::NumberStyle};
+
+    fn pretty(txt: &str) -> String {
+        MathTree::parse(txt).unwrap().to_pretty_string()
+    }
+
+    fn stacked(txt: &str) -> String {
+        MathTree::parse(txt)
+            .unwrap()
+            .to_pretty_string_with(PrettyLayout::Stacked)
+    }
+
+    #[test]
+    fn pretty_inline() {
+        assert_eq!(pretty("x^2 + 2 * x + 1"), "x² + 2x + 1");
+        assert_eq!(pretty("x^(-1) + x^n + x^(n + 1)"), "x⁻¹ + xⁿ + x^(n + 1)");
+        assert_eq!(pretty("(x + 1)^2"), "(x + 1)²");
+        assert_eq!(pretty("2 * pi * r"), "2π·r");
+        assert_eq!(pretty("2 * 5"), "2·5");
+        assert_eq!(pretty("x - 2 * y"), "x - 2y");
+        assert_eq!(pretty("x + (-2) * y"), "x - 2y");
+        assert_eq!(pretty("sqrt(x + 1) / 2"), "√(x + 1)/2");
+        assert_eq!(pretty("sqrt(2) * root(x, 3) * root(x, 5)"), "√2·∛x·⁵√x");
+        assert_eq!(pretty("root(x, y)"), "root(x, y)");
+        assert_eq!(
+            pretty("log(x) + log(x, 2) + log(x, y)"),
+            "log(x) + log₂(x) + log(x, y)"
+        );
+        assert_eq!(pretty("abs(x - 1) * sin(x)^2"), "|x - 1|·sin(x)²");
+        assert_eq!(pretty("factorint(360)"), "2³·3²·5");
+    }
+
+    #[test]
+    fn pretty_number_style() {
+        let styled = |txt: &str, numbers| {
+            MathTree::parse(txt)
+                .unwrap()
+                .to_pretty_string_styled(PrettyLayout::Inline, numbers)
+        };
+
+        assert_eq!(styled("0.125 * x", NumberStyle::Fraction), "1/8·x");
+        assert_eq!(styled("0.333...", NumberStyle::Auto), "1/3");
+        assert_eq!(
+            styled("1 + 0.142857142857...", NumberStyle::Decimal),
+            "1 + 0.(142857)"
+        );
+        assert_eq!(styled("2.5", NumberStyle::Mixed), "2 1/2");
+        assert_eq!(styled("2.5^2", NumberStyle::Mixed), "(2 1/2)²");
+    }
+
+    #[test]
+    fn pretty_stacked() {
+        assert_eq!(stacked("(x + 1) / 2"), "x + 1\n─────\n  2");
+        assert_eq!(stacked("x^2 + 1"), "x² + 1");
+        assert_eq!(stacked("1 / 2 + x^(n + 1)"), "1    n + 1\n─ + x\n2");
+        assert_eq!(stacked("(1 / x)^2"), ["⎛1⎞", "⎜─⎟²", "⎝x⎠"].join("\n"));
+        assert_eq!(
+            stacked("x / (y + 1) - 1"),
+            ["  x", "───── - 1", "y + 1"].join("\n")
+        );
+        // nothing between the minus and the fraction after it
+        assert_eq!(stacked("-(x / y)"), [" x", "-─", " y"].join("\n"));
+    }
+}
@@ -0,0 +1,159 @@
+// the precedence and sign handling shared by the writers (latex, pretty, mathml, speech, display
+// and code): when an operand is grouped, x + (-2) written as x - 2 and the constant factor of a
+// product written first: -x, 2x. each writer only supplies its own syntax through the traits
+
+use crate::{math_tree::TreeNodeRef, number::Number, OperationToken};
+
+pub(crate) trait Emitter {
+    type Output;
+
+    // node on its own, never grouped
+    fn write(&self, node: &TreeNodeRef) -> Self::Output;
+
+    // how tightly node is written, i8::MAX when it is already grouped: f(x), \sqrt{x}
+    fn precedence(&self, node: &TreeNodeRef) -> i8;
+
+    // (x + 1), \left(\frac{1}{2}\right), "the quantity x plus one,"
+    fn group(&self, res: Self::Output) -> Self::Output;
+
+    // written with a leading minus, grouped even where its precedence is high enough: x - (-2)
+    fn leads_with_minus(&self, _node: &TreeNodeRef, _res: &Self::Output) -> bool {
+        false
+    }
+
+    // node as an operand of an operator with the given precedence,
+    // right is set where equal precedence needs grouping: x - (y + 1), x / (2y)
+    fn operand(&self, node: &TreeNodeRef, precedence: i8, right: bool) -> Self::Output {
+        let res = self.write(node);
+        let inner = self.precedence(node);
+
+        if inner < precedence || (right && inner == precedence) || self.leads_with_minus(node, &res)
+        {
+            self.group(res)
+        } else {
+            res
+        }
+    }
+
+    // first operand of a left associative operator, a leading minus reads as unary there: -2 * x
+    fn leading(&self, node: &TreeNodeRef, precedence: i8) -> Self::Output {
+        let res = self.write(node);
+        if self.precedence(node) < precedence {
+            self.group(res)
+        } else {
+            res
+        }
+    }
+}
+
+pub(crate) trait TermEmitter: Emitter {
+    fn constant(&self, c: &Number) -> Self::Output;
+
+    // how tightly a constant is written: 1/3 as a division, 2 1/2 as a sum
+    fn constant_precedence(&self, _c: &Number) -> i8 {
+        i8::MAX
+    }
+
+    // + and - between terms
+    fn symbol(&self, op: OperationToken) -> Self::Output;
+
+    // the minus of -x
+    fn minus(&self) -> Self::Output;
+
+    // between a factor and the one before it, None when they are juxtaposed: 2x.
+    // lead is the constant factor when it is the one before
+    fn times(
+        &self,
+        before: &Self::Output,
+        lead: Option<&Number>,
+        factor: &TreeNodeRef,
+        written: &Self::Output,
+    ) -> Option<Self::Output>;
+
+    fn join(&self, parts: Vec<Self::Output>) -> Self::Output;
+
+    fn sum(&self, terms: &[TreeNodeRef]) -> Self::Output {
+        let precedence = OperationToken::Add.info().precedence;
+        let mut parts = vec![self.write(&terms[0])];
+        for term in &terms[1..] {
+            // x + (-2) is x - 2
+            match self.negation(term) {
+                Some(negated) => {
+                    parts.push(self.symbol(OperationToken::Subtract));
+                    parts.push(negated);
+                }
+                None => {
+                    parts.push(self.symbol(OperationToken::Add));
+                    parts.push(self.operand(term, precedence, false));
+                }
+            }
+        }
+        self.join(parts)
+    }
+
+    // factors in display order, a constant is always the first
+    fn multiply(&self, factors: &[TreeNodeRef]) -> Self::Output {
+        match factors[0].val().constant {
+            Some(lead) => self.product(Some(lead), &factors[1..]),
+            None => self.product(None, factors),
+        }
+    }
+
+    // lead is the constant factor, written first: -x, 2x, 2 * 5
+    fn product(&self, lead: Option<Number>, factors: &[TreeNodeRef]) -> Self::Output {
+        let precedence = OperationToken::Multiply.info().precedence;
+
+        // nothing is written between the minus and the first factor
+        let mut minus = false;
+        let mut parts = match &lead {
+            Some(c) if *c == Number::neg_one() && !factors.is_empty() => {
+                minus = true;
+                vec![self.minus()]
+            }
+            // (2 1/2)x
+            Some(c) if !factors.is_empty() && self.constant_precedence(c) < precedence => {
+                vec![self.group(self.constant(c))]
+            }
+            Some(c) => vec![self.constant(c)],
+            None => vec![],
+        };
+
+        for (i, factor) in factors.iter().enumerate() {
+            let written = self.operand(factor, precedence, false);
+            if let Some(before) = parts.last().filter(|_| !minus) {
+                let lead = lead.as_ref().filter(|_| i == 0);
+                if let Some(times) = self.times(before, lead, factor, &written) {
+                    parts.push(times);
+                }
+            }
+
+            parts.push(written);
+            minus = false;
+        }
+
+        self.join(parts)
+    }
+
+    // node without its leading minus, when it is written with one: -2, -2x
+    fn negation(&self, node: &TreeNodeRef) -> Option<Self::Output> {
+        let val = node.val();
+        if let Some(c) = val.constant {
+            return c.is_sign_negative().then(|| self.constant(&-c));
+        }
+
+        if val.operation != Some(OperationToken::Multiply) {
+            return None;
+        }
+
+        let borrow = node.borrow();
+        let factors = borrow
+            .display_iter()
+            .map(|(_, n)| n.clone())
+            .collect::<Vec<_>>();
+        let lead = factors[0].val().constant.filter(|c| c.is_sign_negative())?;
+
+        // -1x is x
+        let lead = (lead != Number::neg_one()).then_some(-lead);
+        Some(self.product(lead, &factors[1..]))
+    }
+}
@@ -4,10 +4,10 @@ use itertools::Itertools;
 
 use crate::{
     arithmatic::arithmatic::{perform_op, OperationError},
-    constants::CONSTANTS_MAP,
+    complex::Complex,
     math_tree::{MathTree, TreeNodeRef, VarBounds},
     stepper::Steps,
-    OperationToken,
+    MathTokenType, OperationToken,
 };
 
 use super::symmetry::symmetrical_scan;
@@ -34,6 +34,10 @@ impl MathTree {
         // let node = &mut self.root;
         let val = node.val();
 
+        if val.kind == MathTokenType::Function {
+            return Self::simplify_function(node, steps, bounds);
+        }
+
         if !val.is_operator() {
             return Ok(None);
         }
@@ -59,7 +63,12 @@ impl MathTree {
         // let mut operators = borrow.operands().operators().collect_vec();
         // let mut multipliers = Vec::new();
         let mut skip = 0;
-        for op_pos in borrow.operands().operators().collect_vec() {
+        let compound = borrow
+            .operands()
+            .operators()
+            .chain(borrow.operands().functions())
+            .collect_vec();
+        for op_pos in compound {
             let mut op = borrow[op_pos].clone();
             skip += 1;
             
@@ -75,19 +84,10 @@ impl MathTree {
             }
         }
         
-        // inject constants
-        // for v in borrow.operands.variables().collect_vec() {
-            //     // if val.kind == MathTokenType::Variable {
-                //     let val = borrow[v].val();
-
-        //     let var = val.variable.as_ref().unwrap();
-        //     if let Some(c) = CONSTANTS_MAP.get(var.as_str()) {
-            //         borrow.operands.replace_val(v, TreeNodeRef::constant(*c));
-            //     }
-            //     // }
-            // }
+        // constants such as pi are never injected, they stay symbols: 2π, see MathTree::approximate
             std::mem::drop(borrow);
-            if val.operation == Some(OperationToken::Add) {
+            // -1/5 + 2/5i is a folded complex constant, factoring 1/5 out of it would undo the fold
+            if val.operation == Some(OperationToken::Add) && Complex::from_node(node).is_none() {
                 if let Some(factored) = MathTree::factorize_node(node.clone()) {
                     println!("{} FACTORED TO {}", node.to_latex(), factored.to_latex());
                     return Ok(Some(factored));
@@ -99,15 +99,35 @@ impl MathTree {
             
             Ok(perform_op(bounds, node, steps)?)
         }
+
+    // simplifies the arguments, then evaluates the function if it has an exact value
+    fn simplify_function(
+        node: &mut TreeNodeRef,
+        steps: &mut Steps,
+        bounds: &mut VarBounds,
+    ) -> Result<Option<TreeNodeRef>, OperationError> {
+        let args = node.borrow().operands().iter_order().map(|x| x.0).collect_vec();
+        for pos in args {
+            let mut arg = node.borrow()[pos].clone();
+            while let Some(complete) = Self::simplify_node(&mut arg, steps, bounds)? {
+                arg = complete;
+            }
+            node.borrow_mut().replace_operand(pos, arg);
+        }
+
+        perform_op(bounds, node, steps)
+    }
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
     use crate::{
         math_tree::{MathTree, TreeNodeRef},
+        number::Number,
         stepper::Steps,
         MathToken, OperationToken,
     };
+    use num_bigint::BigInt;
     use pretty_assertions::assert_eq;
     use rust_decimal_macros::dec;
 
@@ -143,6 +163,35 @@ pub(crate) mod tests {
         simplify_test("2 + 2^3", TreeNodeRef::constant(dec!(10)));
     }
 
+    #[test]
+    fn simplify_fractions() {
+        simplify_test("1/3*3", TreeNodeRef::one());
+
+        simplify_test("1/3 + 1/6", TreeNodeRef::constant(Number::new(1, 2)));
+
+        simplify_test("0.1 + 0.2", TreeNodeRef::constant(dec!(0.3)));
+
+        simplify_test("2^-2", TreeNodeRef::constant(dec!(0.25)));
+
+        // past Decimal's 96 bits
+        simplify_test(
+            "2^100 * 2^100 / 3",
+            TreeNodeRef::constant(Number::new(BigInt::from(2).pow(200), 3)),
+        );
+
+        simplify_test_latex("1/3", "\\frac{1}{3}");
+
+        simplify_test_latex("-2/6", "-\\frac{1}{3}");
+
+        // decimals only when asked for
+        let mut third = MathTree::parse("1/3").unwrap();
+        third.simplify(&mut Steps::new()).unwrap();
+        assert_eq!(
+            third.approximate().root,
+            TreeNodeRef::constant(dec!(0.3333333333333333333333333333))
+        );
+    }
+
     #[test]
     fn simplify_x() {
         simplify_test(
@@ -0,0 +1,596 @@
+// expressions read aloud: (2x^2 + 1) / (x - 3) is
+// "the quantity two x squared plus one, over the quantity x minus three"
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    math_tree::{MathTree, TreeNodeRef},
+    number::Number,
+    render::{Emitter, TermEmitter},
+    FunctionToken, MathTokenType, OperationToken,
+};
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+    Hebrew,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl MathTree {
+    pub fn to_speech(&self, lang: Language) -> String {
+        self.root.to_speech(lang)
+    }
+}
+
+impl TreeNodeRef {
+    pub fn to_speech(&self, lang: Language) -> String {
+        let writer = SpeechWriter {
+            words: Words::of(lang),
+        };
+
+        let mut phrase = Vec::new();
+        writer.say(self, &mut phrase);
+
+        // a pause closes a group, nothing to close at the end
+        while phrase.last().is_some_and(|w| w == PAUSE) {
+            phrase.pop();
+        }
+        phrase.dedup_by(|a, b| a == PAUSE && b == PAUSE);
+        phrase.join(" ").replace(" ,", ",")
+    }
+}
+
+const PAUSE: &str = ",";
+
+struct Words {
+    plus: &'static str,
+    minus: &'static str,
+    times: &'static str,
+    over: &'static str,
+    squared: &'static str,
+    cubed: &'static str,
+    power: &'static str,
+    // opens parentheses
+    quantity: &'static str,
+    square_root: &'static str,
+    cube_root: &'static str,
+    // the root of index n of
+    root_index: &'static str,
+    of: &'static str,
+    // gcd of a and b
+    and: &'static str,
+    log_base: &'static str,
+    point: &'static str,
+    number: fn(u64) -> String,
+    function: fn(FunctionToken) -> &'static str,
+    variable: fn(&str) -> Option<&'static str>,
+}
+
+impl Words {
+    fn of(lang: Language) -> &'static Words {
+        match lang {
+            Language::English => &ENGLISH,
+            Language::Hebrew => &HEBREW,
+        }
+    }
+}
+
+const ENGLISH: Words = Words {
+    plus: "plus",
+    minus: "minus",
+    times: "times",
+    over: "over",
+    squared: "squared",
+    cubed: "cubed",
+    power: "to the power of",
+    quantity: "the quantity",
+    square_root: "the square root of",
+    cube_root: "the cube root of",
+    root_index: "the root of index",
+    of: "of",
+    and: "and",
+    log_base: "log base",
+    point: "point",
+    number: english_number,
+    function: |f| match f {
+        FunctionToken::Sin => "sine of",
+        FunctionToken::Cos => "cosine of",
+        FunctionToken::Tan => "tangent of",
+        FunctionToken::Ln => "natural log of",
+        FunctionToken::Log => "log of",
+        FunctionToken::Exp => "exponential of",
+        FunctionToken::Sqrt => "the square root of",
+        FunctionToken::Root => "the root of",
+        FunctionToken::Abs => "the absolute value of",
+        FunctionToken::Re => "the real part of",
+        FunctionToken::Im => "the imaginary part of",
+        FunctionToken::Conj => "the conjugate of",
+        FunctionToken::Arg => "the argument of",
+        FunctionToken::Gcd => "the greatest common divisor of",
+        FunctionToken::Lcm => "the least common multiple of",
+        FunctionToken::Factorint => "the prime factorization of",
+    },
+    variable: |name| match name {
+        "pi" | "π" => Some("pi"),
+        _ => None,
+    },
+};
+
+const HEBREW: Words = Words {
+    plus: "ועוד",
+    minus: "פחות",
+    times: "כפול",
+    over: "חלקי",
+    squared: "בריבוע",
+    cubed: "בשלישית",
+    power: "בחזקת",
+    quantity: "הביטוי",
+    square_root: "שורש של",
+    cube_root: "שורש שלישי של",
+    root_index: "שורש מסדר",
+    of: "של",
+    and: "וגם",
+    log_base: "לוגריתם בבסיס",
+    point: "נקודה",
+    number: hebrew_number,
+    function: |f| match f {
+        FunctionToken::Sin => "סינוס של",
+        FunctionToken::Cos => "קוסינוס של",
+        FunctionToken::Tan => "טנגנס של",
+        FunctionToken::Ln => "לוגריתם טבעי של",
+        FunctionToken::Log => "לוגריתם של",
+        FunctionToken::Exp => "אקספוננט של",
+        FunctionToken::Sqrt => "שורש של",
+        FunctionToken::Root => "שורש של",
+        FunctionToken::Abs => "ערך מוחלט של",
+        FunctionToken::Re => "החלק הממשי של",
+        FunctionToken::Im => "החלק המדומה של",
+        FunctionToken::Conj => "הצמוד של",
+        FunctionToken::Arg => "הארגומנט של",
+        FunctionToken::Gcd => "המחלק המשותף הגדול ביותר של",
+        FunctionToken::Lcm => "הכפולה המשותפת הקטנה ביותר של",
+        FunctionToken::Factorint => "הפירוק לגורמים ראשוניים של",
+    },
+    // the letters by their hebrew names
+    variable: |name| match name {
+        "pi" | "π" => Some("פאי"),
+        "x" => Some("איקס"),
+        "y" => Some("וואי"),
+        "z" => Some("זד"),
+        _ => None,
+    },
+};
+
+struct SpeechWriter {
+    words: &'static Words,
+}
+
+impl Emitter for SpeechWriter {
+    type Output = Vec<String>;
+
+    fn write(&self, node: &TreeNodeRef) -> Vec<String> {
+        let mut phrase = Vec::new();
+        self.say(node, &mut phrase);
+        phrase
+    }
+
+    fn precedence(&self, node: &TreeNodeRef) -> i8 {
+        let val = node.val();
+        match val.operation {
+            // "the square root of" already groups
+            Some(op) if op != OperationToken::Root => op.info().precedence,
+            None => val
+                .constant
+                .as_ref()
+                .map_or(i8::MAX, |c| self.constant_precedence(c)),
+            _ => i8::MAX,
+        }
+    }
+
+    // the quantity x plus one, ...
+    fn group(&self, res: Vec<String>) -> Vec<String> {
+        let mut phrase = vec![self.words.quantity.to_string()];
+        phrase.extend(res);
+        phrase.push(PAUSE.to_string());
+        phrase
+    }
+}
+
+impl TermEmitter for SpeechWriter {
+    fn constant(&self, c: &Number) -> Vec<String> {
+        let mut phrase = Vec::new();
+        self.number(c.clone(), &mut phrase);
+        phrase
+    }
+
+    // one over three
+    fn constant_precedence(&self, c: &Number) -> i8 {
+        if c.terminates() {
+            i8::MAX
+        } else {
+            OperationToken::Divide.info().precedence
+        }
+    }
+
+    fn symbol(&self, op: OperationToken) -> Vec<String> {
+        match op {
+            OperationToken::Subtract => self.minus(),
+            _ => vec![self.words.plus.to_string()],
+        }
+    }
+
+    fn minus(&self) -> Vec<String> {
+        vec![self.words.minus.to_string()]
+    }
+
+    // two x, but two times five, x times y and two times the quantity ...
+    fn times(
+        &self,
+        _: &Vec<String>,
+        lead: Option<&Number>,
+        factor: &TreeNodeRef,
+        said: &Vec<String>,
+    ) -> Option<Vec<String>> {
+        // one over three x would be heard as one over 3x
+        let juxtaposed = lead.is_some_and(|c| c.terminates())
+            && factor.val().kind != MathTokenType::Constant
+            && said[0] != self.words.quantity;
+        (!juxtaposed).then(|| vec![self.words.times.to_string()])
+    }
+
+    fn join(&self, parts: Vec<Vec<String>>) -> Vec<String> {
+        parts.concat()
+    }
+}
+
+impl SpeechWriter {
+    // node on its own, never grouped
+    fn say(&self, node: &TreeNodeRef, phrase: &mut Vec<String>) {
+        let val = node.val();
+        match val.kind {
+            MathTokenType::Constant => self.number(val.constant.unwrap(), phrase),
+            MathTokenType::Variable => {
+                let name = val.variable.unwrap();
+                let word = (self.words.variable)(&name).map(String::from);
+                phrase.push(word.unwrap_or_else(|| name.to_string()));
+            }
+            MathTokenType::Function => self.function(node, val.function.unwrap(), phrase),
+            MathTokenType::Operator => self.operator(node, val.operation.unwrap(), phrase),
+        }
+    }
+
+    // radicands, arguments and exponents are grouped when they are more than one word
+    fn argument(&self, node: &TreeNodeRef, phrase: &mut Vec<String>) {
+        phrase.extend(self.operand(node, i8::MAX, false));
+    }
+
+    fn operator(&self, node: &TreeNodeRef, op: OperationToken, phrase: &mut Vec<String>) {
+        let borrow = node.borrow();
+        let childs = borrow
+            .display_iter()
+            .map(|(_, n)| n.clone())
+            .collect::<Vec<_>>();
+        let precedence = op.info().precedence;
+        let word = |w: &str| w.to_string();
+
+        match op {
+            OperationToken::Add => phrase.extend(self.sum(&childs)),
+            OperationToken::Subtract => {
+                self.say(&childs[0], phrase);
+                phrase.push(word(self.words.minus));
+                phrase.extend(self.operand(&childs[1], precedence, true));
+            }
+            OperationToken::Multiply => phrase.extend(self.multiply(&childs)),
+            OperationToken::Divide => {
+                phrase.extend(self.operand(&childs[0], precedence, false));
+                phrase.push(word(self.words.over));
+                phrase.extend(self.operand(&childs[1], precedence, true));
+            }
+            OperationToken::Pow => {
+                phrase.extend(self.operand(&childs[0], precedence, true));
+                match childs[1].val().constant {
+                    Some(c) if c == Number::two() => phrase.push(word(self.words.squared)),
+                    Some(c) if c == Number::from(3) => phrase.push(word(self.words.cubed)),
+                    _ => {
+                        phrase.push(word(self.words.power));
+                        self.argument(&childs[1], phrase);
+                    }
+                }
+            }
+            OperationToken::Root => {
+                match childs[1].val().constant {
+                    Some(c) if c == Number::two() => phrase.push(word(self.words.square_root)),
+                    Some(c) if c == Number::from(3) => phrase.push(word(self.words.cube_root)),
+                    _ => {
+                        phrase.push(word(self.words.root_index));
+                        self.argument(&childs[1], phrase);
+                        phrase.push(word(self.words.of));
+                    }
+                }
+                self.argument(&childs[0], phrase);
+            }
+            OperationToken::LParent | OperationToken::RParent | OperationToken::Comma => {
+                unreachable!()
+            }
+        }
+    }
+
+    fn function(&self, node: &TreeNodeRef, f: FunctionToken, phrase: &mut Vec<String>) {
+        let borrow = node.borrow();
+        let mut args = borrow.calculate_iter().map(|x| x.1);
+        let arg = args.next().unwrap();
+
+        match f {
+            FunctionToken::Log => {
+                let base = args.next().unwrap();
+                // base 10 is implied
+                if base.val().constant == Some(Number::ten()) {
+                    phrase.push((self.words.function)(f).to_string());
+                } else {
+                    phrase.push(self.words.log_base.to_string());
+                    self.argument(base, phrase);
+                    phrase.push(self.words.of.to_string());
+                }
+            }
+            FunctionToken::Gcd | FunctionToken::Lcm => {
+                phrase.push((self.words.function)(f).to_string());
+                self.argument(arg, phrase);
+                for other in args {
+                    phrase.push(self.words.and.to_string());
+                    self.argument(other, phrase);
+                }
+                return;
+            }
+            _ => phrase.push((self.words.function)(f).to_string()),
+        }
+
+        self.argument(arg, phrase);
+    }
+
+    // minus two point five, one over three
+    fn number(&self, c: Number, phrase: &mut Vec<String>) {
+        if c.is_sign_negative() {
+            phrase.push(self.words.minus.to_string());
+        }
+
+        let c = c.abs();
+        if !c.terminates() {
+            self.number(Number::from(c.numer().clone()), phrase);
+            phrase.push(self.words.over.to_string());
+            self.number(Number::from(c.denom().clone()), phrase);
+            return;
+        }
+
+        match c.trunc().to_i64().filter(|n| *n < 1_000_000) {
+            Some(n) => phrase.push((self.words.number)(n as u64)),
+            // too long to be read as a number
+            None => phrase.push(c.trunc().to_string()),
+        }
+
+        if let Some((_, digits)) = c.to_string().split_once('.') {
+            phrase.push(self.words.point.to_string());
+            for d in digits.chars() {
+                phrase.push((self.words.number)(d.to_digit(10).unwrap() as u64));
+            }
+        }
+    }
+}
+
+fn english_number(n: u64) -> String {
+    const ONES: [&str; 20] = [
+        "zero",
+        "one",
+        "two",
+        "three",
+        "four",
+        "five",
+        "six",
+        "seven",
+        "eight",
+        "nine",
+        "ten",
+        "eleven",
+        "twelve",
+        "thirteen",
+        "fourteen",
+        "fifteen",
+        "sixteen",
+        "seventeen",
+        "eighteen",
+        "nineteen",
+    ];
+    const TENS: [&str; 10] = [
+        "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+    ];
+
+    let (head, rest) = match n {
+        0..=19 => return ONES[n as usize].to_string(),
+        20..=99 => (TENS[n as usize / 10].to_string(), n % 10),
+        100..=999 => (format!("{} hundred", ONES[n as usize / 100]), n % 100),
+        _ => (format!("{} thousand", english_number(n / 1000)), n % 1000),
+    };
+
+    match (n, rest) {
+        (_, 0) => head,
+        // twenty-one
+        (20..=99, _) => format!("{}-{}", head, ONES[rest as usize]),
+        _ => format!("{} {}", head, english_number(rest)),
+    }
+}
+
+// counting numbers, feminine: אחת, שתיים
+fn hebrew_number(n: u64) -> String {
+    const ONES: [&str; 10] = [
+        "אפס",
+        "אחת",
+        "שתיים",
+        "שלוש",
+        "ארבע",
+        "חמש",
+        "שש",
+        "שבע",
+        "שמונה",
+        "תשע",
+    ];
+    const TEENS: [&str; 10] = [
+        "עשר",
+        "אחת עשרה",
+        "שתים עשרה",
+        "שלוש עשרה",
+        "ארבע עשרה",
+        "חמש עשרה",
+        "שש עשרה",
+        "שבע עשרה",
+        "שמונה עשרה",
+        "תשע עשרה",
+    ];
+    const TENS: [&str; 10] = [
+        "",
+        "",
+        "עשרים",
+        "שלושים",
+        "ארבעים",
+        "חמישים",
+        "שישים",
+        "שבעים",
+        "שמונים",
+        "תשעים",
+    ];
+    const HUNDREDS: [&str; 10] = [
+        "",
+        "מאה",
+        "מאתיים",
+        "שלוש מאות",
+        "ארבע מאות",
+        "חמש מאות",
+        "שש מאות",
+        "שבע מאות",
+        "שמונה מאות",
+        "תשע מאות",
+    ];
+    const THOUSANDS: [&str; 11] = [
+        "",
+        "אלף",
+        "אלפיים",
+        "שלושת אלפים",
+        "ארבעת אלפים",
+        "חמשת אלפים",
+        "ששת אלפים",
+        "שבעת אלפים",
+        "שמונת אלפים",
+        "תשעת אלפים",
+        "עשרת אלפים",
+    ];
+
+    if n == 0 {
+        return ONES[0].to_string();
+    }
+
+    let mut parts = Vec::new();
+    match n / 1000 {
+        0 => {}
+        t @ 1..=10 => parts.push(THOUSANDS[t as usize].to_string()),
+        t => parts.push(format!("{} אלף", hebrew_number(t))),
+    }
+
+    let rest = n % 1000;
+    if rest >= 100 {
+        parts.push(HUNDREDS[rest as usize / 100].to_string());
+    }
+    match rest % 100 {
+        0 => {}
+        r @ 1..=9 => parts.push(ONES[r as usize].to_string()),
+        r @ 10..=19 => parts.push(TEENS[r as usize - 10].to_string()),
+        r => {
+            parts.push(TENS[r as usize / 10].to_string());
+            if r % 10 != 0 {
+                parts.push(ONES[r as usize % 10].to_string());
+            }
+        }
+    }
+
+    // the last part takes "and": מאה עשרים ושלוש
+    if parts.len() > 1 {
+        let last = parts.pop().unwrap();
+        parts.push(format!("ו{}", last));
+    }
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{english_number, hebrew_number, Language};
+    use crate::math_tree::MathTree;
+
+    fn english(txt: &str) -> String {
+        MathTree::parse(txt).unwrap().to_speech(Language::English)
+    }
+
+    fn hebrew(txt: &str) -> String {
+        MathTree::parse(txt).unwrap().to_speech(Language::Hebrew)
+    }
+
+    #[test]
+    fn speech_numbers() {
+        assert_eq!(english_number(0), "zero");
+        assert_eq!(english_number(21), "twenty-one");
+        assert_eq!(english_number(105), "one hundred five");
+        assert_eq!(english_number(3040), "three thousand forty");
+        assert_eq!(hebrew_number(21), "עשרים ואחת");
+        assert_eq!(hebrew_number(12), "שתים עשרה");
+        assert_eq!(hebrew_number(123), "מאה עשרים ושלוש");
+        assert_eq!(hebrew_number(2005), "אלפיים וחמש");
+        assert_eq!(english("2.05"), "two point zero five");
+        assert_eq!(english("-3"), "minus three");
+        assert_eq!(english("1234567"), "1234567");
+    }
+
+    #[test]
+    fn speech_english() {
+        assert_eq!(english("2 * x^2 + 1"), "two x squared plus one");
+        assert_eq!(
+            english("(2 * x^2 + 1) / (x - 3)"),
+            "the quantity two x squared plus one, over the quantity x minus three"
+        );
+        assert_eq!(english("x - (y + 1)"), "x minus the quantity y plus one");
+        assert_eq!(english("x + (-2) * y"), "x minus two y");
+        assert_eq!(english("-x^3"), "minus x cubed");
+        assert_eq!(english("2 * 5 * x"), "two times five times x");
+        assert_eq!(
+            english("(x + 1)^n * 2"),
+            "two times the quantity x plus one, to the power of n"
+        );
+        assert_eq!(
+            english("sqrt(x + 1) + root(x, 5)"),
+            "the square root of the quantity x plus one, plus the root of index five of x"
+        );
+        assert_eq!(
+            english("sin(pi * x) + log(x, 2)"),
+            "sine of the quantity pi times x, plus log base two of x"
+        );
+        assert_eq!(english("abs(x)^2"), "the absolute value of x squared");
+    }
+
+    #[test]
+    fn speech_hebrew() {
+        assert_eq!(hebrew("2 * x^2 + 1"), "שתיים איקס בריבוע ועוד אחת");
+        assert_eq!(
+            hebrew("(x + 1) / (x - 3)"),
+            "הביטוי איקס ועוד אחת, חלקי הביטוי איקס פחות שלוש"
+        );
+        assert_eq!(hebrew("sqrt(y) * 3"), "שלוש שורש של וואי");
+        assert_eq!(hebrew("3 * (y + 1)"), "שלוש כפול הביטוי וואי ועוד אחת");
+        assert_eq!(
+            hebrew("ln(x) - 0.5"),
+            "לוגריתם טבעי של איקס פחות אפס נקודה חמש"
+        );
+    }
+}
@@ -0,0 +1,422 @@
+// physical units on constants: 5 m/s is the constant 5 with the unit m/s.
+// units are only read when ParseOptions::units is set, otherwise m and s are variables
+
+use std::{fmt, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arithmatic::{arithmatic::OperationError, power},
+    math_tree::{MathTree, TreeNodeRef},
+    number::Number,
+    MathToken, OperationToken,
+};
+
+// exponents of the SI base units: m, kg, s, A, K, mol, cd
+pub type Dimension = [i32; 7];
+
+const DIMENSIONLESS: Dimension = [0; 7];
+const LENGTH: Dimension = [1, 0, 0, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0, 0, 0];
+const CURRENT: Dimension = [0, 0, 0, 1, 0, 0, 0];
+const TEMPERATURE: Dimension = [0, 0, 0, 0, 1, 0, 0];
+const AMOUNT: Dimension = [0, 0, 0, 0, 0, 1, 0];
+const LUMINOSITY: Dimension = [0, 0, 0, 0, 0, 0, 1];
+
+// size in SI base units and dimension of each symbol, before prefixes: g is 1/1000 kg
+fn base_unit(symbol: &str) -> Option<(Number, Dimension)> {
+    Some(match symbol {
+        "m" => (Number::one(), LENGTH),
+        "g" => (Number::new(1, 1000), MASS),
+        "s" => (Number::one(), TIME),
+        "A" => (Number::one(), CURRENT),
+        "K" => (Number::one(), TEMPERATURE),
+        "mol" => (Number::one(), AMOUNT),
+        "cd" => (Number::one(), LUMINOSITY),
+        // kg m s^-2
+        "N" => (Number::one(), [1, 1, -2, 0, 0, 0, 0]),
+        "J" => (Number::one(), [2, 1, -2, 0, 0, 0, 0]),
+        "W" => (Number::one(), [2, 1, -3, 0, 0, 0, 0]),
+        "Pa" => (Number::one(), [-1, 1, -2, 0, 0, 0, 0]),
+        "Hz" => (Number::one(), [0, 0, -1, 0, 0, 0, 0]),
+        "C" => (Number::one(), [0, 0, 1, 1, 0, 0, 0]),
+        "V" => (Number::one(), [2, 1, -3, -1, 0, 0, 0]),
+        "min" => (Number::from(60), TIME),
+        "h" => (Number::from(3600), TIME),
+        "L" => (Number::new(1, 1000), [3, 0, 0, 0, 0, 0, 0]),
+        _ => return None,
+    })
+}
+
+fn prefix(c: char) -> Option<Number> {
+    Some(match c {
+        'G' => Number::from(1_000_000_000),
+        'M' => Number::from(1_000_000),
+        'k' => Number::from(1000),
+        'c' => Number::new(1, 100),
+        'm' => Number::new(1, 1000),
+        'µ' | 'u' => Number::new(1, 1_000_000),
+        'n' => Number::new(1, 1_000_000_000),
+        _ => return None,
+    })
+}
+
+// size and dimension of a symbol, with an optional prefix: km, ms, µA
+fn symbol_unit(symbol: &str) -> Option<(Number, Dimension)> {
+    if let Some(unit) = base_unit(symbol) {
+        return Some(unit);
+    }
+
+    let mut chars = symbol.chars();
+    let scale = prefix(chars.next()?)?;
+    let (base, dimension) = base_unit(chars.as_str())?;
+    Some((scale * base, dimension))
+}
+
+// a product of unit symbols with whole exponents: kg m s^-2
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Unit {
+    factors: Vec<(Rc<String>, i32)>,
+}
+
+// symbols with their exponents, borrowed from a Unit: [("m", 1), ("s", 2)]
+type Factors<'a> = Vec<(&'a str, i32)>;
+
+impl Unit {
+    pub fn none() -> Self {
+        Self { factors: vec![] }
+    }
+
+    // a single symbol: m, km, N
+    pub fn from_symbol(symbol: &str) -> Option<Unit> {
+        symbol_unit(symbol)?;
+        Some(Self {
+            factors: vec![(Rc::new(symbol.to_string()), 1)],
+        })
+    }
+
+    // km/h, m/s^2, kg*m/s^2, N m
+    pub fn parse(str: &str) -> Option<Unit> {
+        let mut parts = str.split('/');
+        let mut unit = Self::parse_product(parts.next()?)?;
+        for part in parts {
+            unit = unit.div(&Self::parse_product(part)?);
+        }
+        Some(unit)
+    }
+
+    fn parse_product(str: &str) -> Option<Unit> {
+        str.split(|c: char| c == '*' || c == '·' || c.is_whitespace())
+            .filter(|factor| !factor.is_empty())
+            .try_fold(Self::none(), |unit, factor| {
+                let (symbol, exponent) = match factor.split_once('^') {
+                    Some((symbol, exponent)) => (symbol, exponent.parse().ok()?),
+                    None => (factor, 1),
+                };
+                Some(unit.mul(&Self::from_symbol(symbol)?.powi(exponent)))
+            })
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.factors.is_empty()
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        let mut dimension = DIMENSIONLESS;
+        for (symbol, exponent) in &self.factors {
+            let (_, base) = symbol_unit(symbol).unwrap();
+            for (d, b) in dimension.iter_mut().zip(base) {
+                *d += b * exponent;
+            }
+        }
+        dimension
+    }
+
+    // size in SI base units: km/h = 5/18 m/s
+    pub fn scale(&self) -> Number {
+        self.factors
+            .iter()
+            .map(|(symbol, exponent)| symbol_unit(symbol).unwrap().0.powi(*exponent).unwrap())
+            .fold(Number::one(), |a, b| a * b)
+    }
+
+    // m/s * s = m
+    pub fn mul(&self, other: &Unit) -> Unit {
+        let mut factors = self.factors.clone();
+        for (symbol, exponent) in &other.factors {
+            match factors.iter_mut().find(|(s, _)| s == symbol) {
+                Some((_, e)) => *e += exponent,
+                None => factors.push((symbol.clone(), *exponent)),
+            }
+        }
+        factors.retain(|(_, e)| *e != 0);
+        Self { factors }
+    }
+
+    pub fn div(&self, other: &Unit) -> Unit {
+        self.mul(&other.powi(-1))
+    }
+
+    pub fn powi(&self, n: i32) -> Unit {
+        if n == 0 {
+            return Self::none();
+        }
+        Self {
+            factors: self
+                .factors
+                .iter()
+                .map(|(symbol, exponent)| (symbol.clone(), exponent * n))
+                .collect(),
+        }
+    }
+
+    // root(m^2, 2) = m, None for root(m, 2)
+    pub fn root(&self, n: u32) -> Option<Unit> {
+        let n = n as i32;
+        Some(Self {
+            factors: self
+                .factors
+                .iter()
+                .map(|(symbol, exponent)| {
+                    (exponent % n == 0).then(|| (symbol.clone(), exponent / n))
+                })
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    // (numerator, denominator) factors with positive exponents
+    fn split(&self) -> (Factors<'_>, Factors<'_>) {
+        let factors = |numerator: bool| {
+            self.factors
+                .iter()
+                .filter(|(_, e)| (*e > 0) == numerator)
+                .map(|(symbol, e)| (symbol.as_str(), e.abs()))
+                .collect()
+        };
+        (factors(true), factors(false))
+    }
+
+    // \mathrm{m}/\mathrm{s}^{2}
+    pub fn to_latex(&self) -> String {
+        let product = |factors: Factors| {
+            factors
+                .into_iter()
+                .map(|(symbol, e)| match e {
+                    1 => format!("\\mathrm{{{}}}", symbol),
+                    e => format!("\\mathrm{{{}}}^{{{}}}", symbol, e),
+                })
+                .collect::<Vec<_>>()
+                .join("\\cdot ")
+        };
+
+        let (numerator, denominator) = self.split();
+        match (numerator.is_empty(), denominator.is_empty()) {
+            (_, true) => product(numerator),
+            (true, false) => format!("1/{}", product(denominator)),
+            (false, false) => format!("{}/{}", product(numerator), product(denominator)),
+        }
+    }
+}
+
+// kg*m/s^2
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let product = |factors: Factors| {
+            factors
+                .into_iter()
+                .map(|(symbol, e)| match e {
+                    1 => symbol.to_string(),
+                    e => format!("{}^{}", symbol, e),
+                })
+                .collect::<Vec<_>>()
+                .join("*")
+        };
+
+        let (numerator, denominator) = self.split();
+        match (numerator.is_empty(), denominator.is_empty()) {
+            (_, true) => write!(f, "{}", product(numerator)),
+            (true, false) => write!(f, "1/{}", product(denominator)),
+            (false, false) => write!(f, "{}/{}", product(numerator), product(denominator)),
+        }
+    }
+}
+
+impl fmt::Debug for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl MathToken {
+    // 5 m/s
+    pub fn quantity(n: impl Into<Number>, unit: Unit) -> Self {
+        let mut token = Self::constant(n);
+        token.unit = (!unit.is_none()).then_some(unit);
+        token
+    }
+}
+
+impl TreeNodeRef {
+    pub fn quantity(n: impl Into<Number>, unit: Unit) -> Self {
+        Self::new_val(MathToken::quantity(n, unit))
+    }
+}
+
+// a op b when either is a quantity with a unit: 5 m/s * 3 s = 15 m, 2 m + 30 cm = 2.3 m,
+// 2 kg + 3 s is a DimensionMismatch
+pub fn perform_op_units(
+    op: OperationToken,
+    a: &TreeNodeRef,
+    b: &TreeNodeRef,
+) -> Result<Option<TreeNodeRef>, OperationError> {
+    let (a, b) = (a.val(), b.val());
+    if a.unit.is_none() && b.unit.is_none() {
+        return Ok(None);
+    }
+    let (Some(x), Some(y)) = (a.constant, b.constant) else {
+        return Ok(None);
+    };
+    let (ua, ub) = (
+        a.unit.unwrap_or_else(Unit::none),
+        b.unit.unwrap_or_else(Unit::none),
+    );
+
+    let (value, unit) = match op {
+        // b in the units of a
+        OperationToken::Add | OperationToken::Subtract => {
+            if ua.dimension() != ub.dimension() {
+                return Err(OperationError::DimensionMismatch);
+            }
+            let y = (y * ub.scale()).checked_div(&ua.scale()).unwrap();
+            match op {
+                OperationToken::Add => (x + y, ua),
+                _ => (x - y, ua),
+            }
+        }
+        OperationToken::Multiply => (x * y, ua.mul(&ub)),
+        OperationToken::Divide => match x.checked_div(&y) {
+            Some(value) => (value, ua.div(&ub)),
+            None => return Ok(None),
+        },
+        // (3 m)^2 = 9 m^2, the exponent has no unit
+        OperationToken::Pow => {
+            if !ub.is_none() {
+                return Err(OperationError::DimensionMismatch);
+            }
+            let (Some(n), Some(value)) =
+                (y.to_i64().and_then(|n| i32::try_from(n).ok()), x.pow(&y)?)
+            else {
+                return Ok(None);
+            };
+            (value, ua.powi(n))
+        }
+        OperationToken::Root => {
+            if !ub.is_none() {
+                return Err(OperationError::DimensionMismatch);
+            }
+            let Some(n) = power::root_index(&y) else {
+                return Ok(None);
+            };
+            match (x.nth_root(n), ua.root(n)) {
+                (Some(value), Some(unit)) => (value, unit),
+                _ => return Ok(None),
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(TreeNodeRef::quantity(value.bounded()?, unit)))
+}
+
+impl MathTree {
+    // the quantity in other units of the same dimension: 10 m/s to km/h = 36 km/h,
+    // the tree should be simplified to a single quantity first
+    pub fn to(&self, unit: &Unit) -> Result<MathTree, OperationError> {
+        let val = self.root.val();
+        let from = val.unit.unwrap_or_else(Unit::none);
+        let Some(value) = val.constant else {
+            return Err(OperationError::DimensionMismatch);
+        };
+        if from.dimension() != unit.dimension() {
+            return Err(OperationError::DimensionMismatch);
+        }
+
+        let value = (value * from.scale()).checked_div(&unit.scale()).unwrap();
+        Ok(MathTree {
+            root: TreeNodeRef::quantity(value, unit.clone()),
+            bounds: self.bounds.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::Unit;
+    use crate::{
+        arithmatic::arithmatic::OperationError,
+        lexer::ParseOptions,
+        math_tree::{MathTree, TreeNodeRef},
+        number::Number,
+        stepper::Steps,
+    };
+
+    fn simplify_units(expr: &str) -> Result<MathTree, OperationError> {
+        let mut tree =
+            MathTree::parse_with(expr, ParseOptions::default().with_units(true)).unwrap();
+        tree.simplify(&mut Steps::new())?;
+        Ok(tree)
+    }
+
+    #[test]
+    fn unit_arithmetic() {
+        let m = Unit::parse("m").unwrap();
+        assert_eq!(
+            simplify_units("5 m/s * 3 s").unwrap().root,
+            TreeNodeRef::quantity(15, m.clone())
+        );
+        assert_eq!(
+            simplify_units("2 m + 30 cm").unwrap().root,
+            TreeNodeRef::quantity(Number::new(23, 10), m.clone())
+        );
+        assert_eq!(
+            simplify_units("(3 m)^2").unwrap().root,
+            TreeNodeRef::quantity(9, Unit::parse("m^2").unwrap())
+        );
+        assert_eq!(
+            simplify_units("10 N / (2 kg)").unwrap().root,
+            TreeNodeRef::quantity(5, Unit::parse("N/kg").unwrap())
+        );
+        assert_eq!(
+            simplify_units("2 kg + 3 s").map(|t| t.root),
+            Err(OperationError::DimensionMismatch)
+        );
+    }
+
+    #[test]
+    fn unit_conversion() {
+        let speed = simplify_units("10 m/s").unwrap();
+        assert_eq!(
+            speed.to(&Unit::parse("km/h").unwrap()).unwrap().root,
+            TreeNodeRef::quantity(36, Unit::parse("km/h").unwrap())
+        );
+        assert_eq!(
+            speed.to(&Unit::parse("kg").unwrap()).map(|t| t.root),
+            Err(OperationError::DimensionMismatch)
+        );
+        assert_eq!(
+            Unit::parse("N").unwrap().dimension(),
+            Unit::parse("kg*m/s^2").unwrap().dimension()
+        );
+
+        assert_eq!(speed.to_string(), "10 m/s");
+        assert_eq!(speed.to_latex(), "10\\,\\mathrm{m}/\\mathrm{s}");
+        assert_eq!(
+            simplify_units("3 kg * 2 m^2").unwrap().to_latex(),
+            "6\\,\\mathrm{kg}\\cdot \\mathrm{m}^{2}"
+        );
+    }
+}